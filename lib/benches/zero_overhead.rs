@@ -0,0 +1,96 @@
+//! Compares a hot loop using partial borrows with the same loop using manually split references.
+//! Run with `cargo bench`. Fails if the partial borrows are noticeably slower, which means that a
+//! method on the hot path was not inlined.
+
+#![allow(clippy::print_stdout)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+use std::hint::black_box;
+use std::time::Duration;
+use std::time::Instant;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Ctx {
+    pub positions: Vec<f32>,
+    pub velocities: Vec<f32>,
+    pub steps: usize,
+    pub log: Vec<String>,
+}
+
+const SIZE: usize = 1024;
+const ITERATIONS: usize = 100_000;
+const RUNS: usize = 5;
+/// Maximum slowdown of the partial borrows, leaving room for the measurement noise.
+const MAX_RATIO: f64 = 1.5;
+
+// ====================
+// === Hand-Written ===
+// ====================
+
+#[inline(never)]
+fn step_manual(positions: &mut [f32], velocities: &[f32], steps: &mut usize) {
+    for (position, velocity) in positions.iter_mut().zip(velocities) {
+        *position += velocity;
+    }
+    *steps += 1;
+}
+
+fn run_manual(ctx: &mut Ctx) {
+    for _ in 0..ITERATIONS {
+        step_manual(&mut ctx.positions, &ctx.velocities, &mut ctx.steps);
+        black_box(&mut ctx.positions);
+    }
+}
+
+// =======================
+// === Partial Borrows ===
+// =======================
+
+#[inline(never)]
+fn step_partial(ctx: p!(&<mut positions, velocities, mut steps> Ctx)) {
+    let (positions, ctx) = ctx.extract_positions();
+    for (position, velocity) in positions.iter_mut().zip(ctx.velocities.iter()) {
+        *position += velocity;
+    }
+    *ctx.steps += 1;
+}
+
+fn run_partial(ctx: &mut Ctx) {
+    let mut refs = ctx.as_refs_mut();
+    for _ in 0..ITERATIONS {
+        step_partial(refs.partial_borrow());
+        black_box(&mut refs.positions);
+    }
+}
+
+// =============
+// === Bench ===
+// =============
+
+fn measure(f: fn(&mut Ctx)) -> Duration {
+    (0..RUNS).map(|_| {
+        let mut ctx = Ctx { positions: vec![0.0; SIZE], velocities: vec![1.0; SIZE], ..Ctx::default() };
+        let start = Instant::now();
+        f(black_box(&mut ctx));
+        let time = start.elapsed();
+        assert_eq!(ctx.steps, ITERATIONS);
+        time
+    }).min().unwrap_or_default()
+}
+
+fn main() {
+    let manual = measure(run_manual);
+    let partial = measure(run_partial);
+    let ratio = partial.as_secs_f64() / manual.as_secs_f64();
+    println!("hand-written:    {manual:?}");
+    println!("partial borrows: {partial:?}");
+    println!("ratio:           {ratio:.3}");
+    assert!(ratio < MAX_RATIO, "Partial borrows are {ratio:.3}x slower than hand-written code.");
+}
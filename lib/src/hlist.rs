@@ -11,6 +11,71 @@ pub struct Cons<H, T> {
 #[derive(Clone, Copy, Debug)]
 pub struct Nil;
 
+
+// ===========
+// === Nat ===
+// ===========
+
+/// Type-level natural numbers used to index `HList` elements.
+#[derive(Clone, Copy, Debug)]
+pub struct Zero;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Succ<N>(pub N);
+
+
+// ==============
+// === ItemAt ===
+// ==============
+
+/// Type of the `HList` element at the `N`-th position.
+pub trait ItemAt<N> { type Item; }
+
+impl<H, T> ItemAt<Zero> for Cons<H, T> {
+    type Item = H;
+}
+
+impl<H, T, N> ItemAt<Succ<N>> for Cons<H, T> where T: ItemAt<N> {
+    type Item = <T as ItemAt<N>>::Item;
+}
+
+
+// =================
+// === IntoTuple ===
+// =================
+
+/// Conversion of an `HList` to a tuple with the same elements in the same order. Implemented for
+/// lists of up to 16 elements.
+pub trait IntoTuple {
+    type Tuple;
+    fn into_tuple(self) -> Self::Tuple;
+}
+
+macro_rules! impl_into_tuple {
+    () => {
+        impl IntoTuple for Nil {
+            type Tuple = ();
+            #[inline(always)]
+            fn into_tuple(self) -> Self::Tuple {}
+        }
+    };
+    ($t:ident $($ts:ident)*) => {
+        #[allow(non_snake_case)]
+        impl<$t, $($ts,)*> IntoTuple for crate::HList![$t, $($ts),*] {
+            type Tuple = ($t, $($ts,)*);
+            #[inline(always)]
+            fn into_tuple(self) -> Self::Tuple {
+                let crate::hlist_pat![$t, $($ts),*] = self;
+                ($t, $($ts,)*)
+            }
+        }
+        impl_into_tuple! { $($ts)* }
+    };
+}
+
+impl_into_tuple! { T0 T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 T11 T12 T13 T14 T15 }
+
+
 // ==============
 // === Macros ===
 // ==============
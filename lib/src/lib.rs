@@ -1,88 +1,1751 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
 pub mod hlist;
+pub mod reflect;
+pub mod trace;
 
 use hlist::Cons;
 use hlist::Nil;
 
-use std::fmt::Debug;
-pub use borrow_macro::*;
+use core::cell::Cell;
+use core::ffi::c_void;
+use core::fmt;
+use core::fmt::Debug;
+use core::iter;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::pin::Pin;
+use core::ptr::NonNull;
+use core::ptr;
+use core::slice;
+
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+pub use borrow_macro::*;
+
+
+// ==========================
+// === Compile Fail Tests ===
+// ==========================
+
+/// Borrowing the same field mutably twice with `scoped_split!` fails to compile, as the field is
+/// hidden in the rest of the fields after the first borrow:
+///
+/// ```compile_fail,E0599
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// fn detach(graph: p!(&<mut *> Graph)) {
+///     // Error: `nodes` is already borrowed mutably.
+///     borrow::scoped_split!(graph => mut nodes, mut nodes);
+/// }
+/// # fn main() {}
+/// ```
+#[cfg(doctest)]
+pub struct ScopedSplitSameFieldTwice;
+
+/// The derive generates the `extract_$field_ref` methods, so a field named like the method of
+/// another field fails to compile with an error pointing at it:
+///
+/// ```compile_fail
+/// use borrow::PartialBorrow;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     // Error: Fields 'nodes' and 'nodes_ref' can't be borrowed together, as both generate a
+///     // method named 'extract_nodes_ref'.
+///     nodes_ref: usize,
+/// }
+/// # fn main() {}
+/// ```
+#[cfg(doctest)]
+pub struct ExtractRefClash;
+
+/// The same holds for the `extract_$field_as` methods:
+///
+/// ```compile_fail
+/// use borrow::PartialBorrow;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     // Error: Fields 'nodes' and 'nodes_as' can't be borrowed together, as both generate a
+///     // method named 'extract_nodes_as'.
+///     nodes_as: usize,
+/// }
+/// # fn main() {}
+/// ```
+#[cfg(doctest)]
+pub struct ExtractAsClash;
+
+
+// ==============
+// === Traits ===
+// ==============
+
+pub mod traits {
+    pub use super::Acquire as _;
+    pub use super::PartialBorrow as _;
+    pub use super::PartialBorrowHelper as _;
+    pub use super::RefCast as _;
+    pub use super::AsRefs as _;
+    pub use super::AsRefsHelper as _;
+    pub use super::AsRefsEach as _;
+    pub use super::AsSharedRefs as _;
+    pub use super::AsSharedRefsHelper as _;
+    pub use super::SharedRefCast as _;
+    pub use super::AsPinRefs as _;
+    pub use super::AsPinRefsHelper as _;
+    pub use super::PinRefCast as _;
+    pub use super::UnifyImpl as _;
+    pub use super::Join as _;
+    pub use super::Rejoin as _;
+    pub use super::RefFlatten as _;
+}
+
+
+// ==============
+// === AsRefs ===
+// ==============
+
+pub trait AsRefs<'t, T> {
+    fn as_refs_impl(&'t mut self) -> T;
+}
+
+/// Marker of types partial borrows can be created from with the `as_refs` and `as_shared_refs`
+/// methods of [`AsRefsHelper`] and [`AsSharedRefsHelper`]. The derive implements it for the
+/// struct, its ref struct, and `#[borrow(deref)]` newtypes. Types implementing [`AsRefs`] or
+/// [`AsSharedRefs`] manually need to implement it as well to get the methods.
+///
+/// The methods are provided only for the marked types, so calling them on a guard or a smart
+/// pointer, like `MutexGuard<Ctx>`, dereferences it to the struct. If they were provided for all
+/// types, method resolution would pick them for the guard itself and fail, as the guard doesn't
+/// implement `AsRefs`:
+///
+/// ```
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+/// use std::sync::Mutex;
+///
+/// #[derive(Default, PartialBorrow)]
+/// #[module(crate)]
+/// struct Ctx {
+///     scene: Vec<usize>,
+///     log: Vec<String>,
+/// }
+///
+/// # fn main() {
+/// let ctx = Mutex::new(Ctx::default());
+/// let mut guard = ctx.lock().unwrap_or_else(|err| err.into_inner());
+/// let scene = guard.as_refs::<p!(<mut scene> Ctx)>();
+/// scene.scene.push(0);
+/// # }
+/// ```
+///
+/// The borrow is tied to the guard, so the guard has to outlive it:
+///
+/// ```compile_fail
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+/// use std::sync::Mutex;
+///
+/// #[derive(Default, PartialBorrow)]
+/// #[module(crate)]
+/// struct Ctx {
+///     scene: Vec<usize>,
+///     log: Vec<String>,
+/// }
+///
+/// # fn main() {
+/// let ctx = Mutex::new(Ctx::default());
+/// let mut guard = ctx.lock().unwrap_or_else(|err| err.into_inner());
+/// let scene = guard.as_refs::<p!(<mut scene> Ctx)>();
+/// drop(guard);
+/// scene.scene.push(0);
+/// # }
+/// ```
+pub trait AsRefsSource {}
+
+impl<'t, T: AsRefsSource> AsRefsHelper<'t> for T {}
+pub trait AsRefsHelper<'t> {
+    #[inline(always)]
+    fn as_refs<T>(&'t mut self) -> T
+    where Self: AsRefs<'t, T> { self.as_refs_impl() }
+}
+
+
+// ==================
+// === AsRefsEach ===
+// ==================
+
+/// Partial borrows of every element of a slice of structs, like
+/// `ctxs.as_refs_each::<p!(<mut scene> Ctx)>()` for `ctxs: Vec<Ctx>`. The borrows of different
+/// elements are independent of each other, so they can be used at the same time, e.g. collected
+/// into a `Vec`. The slice stays borrowed mutably as long as any of them is alive.
+pub trait AsRefsEach<'t, S: 't> {
+    #[allow(clippy::type_complexity)]
+    fn as_refs_each<T>(&'t mut self) -> iter::Map<slice::IterMut<'t, S>, fn(&'t mut S) -> T>
+    where S: AsRefs<'t, T>;
+}
+
+impl<'t, S: 't> AsRefsEach<'t, S> for [S] {
+    #[inline(always)]
+    fn as_refs_each<T>(&'t mut self) -> iter::Map<slice::IterMut<'t, S>, fn(&'t mut S) -> T>
+    where S: AsRefs<'t, T> {
+        self.iter_mut().map(AsRefs::as_refs_impl)
+    }
+}
+
+
+// ====================
+// === AsSharedRefs ===
+// ====================
+
+/// Like `AsRefs`, but borrows fields from a shared reference. Fields can only be borrowed
+/// immutably or hidden, so the result is `Send` and `Sync` if the borrowed fields are `Sync` and
+/// the hidden ones are `Send` and `Sync`, respectively. It allows read-only passes over disjoint
+/// fields of a struct on different threads.
+pub trait AsSharedRefs<'t, T> {
+    fn as_shared_refs_impl(&'t self) -> T;
+}
+
+impl<'t, T: AsRefsSource> AsSharedRefsHelper<'t> for T {}
+pub trait AsSharedRefsHelper<'t> {
+    #[inline(always)]
+    fn as_shared_refs<T>(&'t self) -> T
+    where Self: AsSharedRefs<'t, T> { self.as_shared_refs_impl() }
+}
+
+
+// =================
+// === AsPinRefs ===
+// =================
+
+/// Like `AsRefs`, but borrows fields from a pinned reference. Fields can be borrowed mutably only
+/// if they are `Unpin`, so the pinned struct is never moved. Pinned projections of the fields, like
+/// `Pin<&mut T>`, are not supported, as they would require structural pinning guarantees that the
+/// derive can't check.
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+/// use core::marker::PhantomPinned;
+/// use core::pin::pin;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Ctx {
+///     task: PhantomPinned,
+///     log: Vec<String>,
+/// }
+///
+/// # fn main() {
+/// let mut ctx = pin!(Ctx { task: PhantomPinned, log: vec![] });
+/// // Error: `PhantomPinned` can't be borrowed mutably, as it is not `Unpin`.
+/// let _ = ctx.as_mut().as_refs_pin_mut::<p!(<mut task> Ctx)>();
+/// # }
+/// ```
+pub trait AsPinRefs<'t, T> {
+    fn as_pin_refs_impl(self: Pin<&'t mut Self>) -> T;
+}
+
+impl<'t, T> AsPinRefsHelper<'t> for T {}
+pub trait AsPinRefsHelper<'t> {
+    #[inline(always)]
+    fn as_refs_pin_mut<T>(self: Pin<&'t mut Self>) -> T
+    where Self: AsPinRefs<'t, T> { self.as_pin_refs_impl() }
+}
+
+
+// =======================
+// === Struct Generics ===
+// =======================
+
+/// Borrow slots of a ref struct as an `HList`, like `HList![&'t mut GeometryCtx, Hidden<MeshCtx>]`.
+/// Together with [`FromFields`], this is all a type needs to support `partial_borrow`, `split`,
+/// and the other methods of [`PartialBorrowHelper`]. Both are implemented by the derive, but they
+/// can be implemented manually for ref structs that can't be derived, like views of FFI handles.
+///
+/// # Safety
+/// Partial borrows are performed by casting pointers between instantiations of the ref struct, so
+/// the implementing type must be a struct that:
+/// - is `#[repr(C)]`, with one field per type parameter, in the order of the parameters;
+/// - has `Fields` equal to `HList!` of its type parameters, in the same order;
+/// - is instantiated with the borrow slots only: `&T`, `&mut T`, `Option<&T>`, `Option<&mut T>`,
+///   [`Hidden<T>`], or [`Nested`];
+/// - has all fields of a single instance borrowing disjoint places, unless borrowed immutably.
+///
+/// A type owning the fields can create the ref struct with `as_refs` and `as_shared_refs` by
+/// implementing [`AsRefs`] and [`AsSharedRefs`] for the ref struct instantiations it provides,
+/// along with the [`AsRefsSource`] marker, which enables the methods for it.
+pub unsafe trait HasFields { type Fields; }
+type Fields<T> = <T as HasFields>::Fields;
+
+/// The ref struct with its borrow slots replaced by `Fields`, like `CtxRef<A, B>` for
+/// `CtxRef<geometry, mesh>` and `HList![A, B]`. See [`HasFields`].
+///
+/// # Safety
+/// The `Result` must be the same struct as `Self`, instantiated with the types from `Fields`.
+pub unsafe trait FromFields<Fields> { type Result; }
+type WithFields<T, Fields> = <T as FromFields<Fields>>::Result;
+
+/// Types of the fields of the original struct. Used by the generated selector macro to resolve
+/// field types of parametrized structs.
+pub trait HasFieldTypes { type FieldTypes; }
+pub type FieldAt<N, T> = <<T as HasFieldTypes>::FieldTypes as hlist::ItemAt<N>>::Item;
+
+/// Index of a field of the original struct. The `Field` is a marker type generated for every
+/// field in the module named with `#[borrow(field_markers = ...)]`, like `ctx_fields::geometry`
+/// for `#[borrow(field_markers = ctx_fields)]`. Structs without the attribute don't implement it.
+pub trait FieldIndex<Field> {
+    /// Type-level index, usable with `FieldAt`.
+    type Index;
+    const INDEX: usize;
+}
+pub type FieldIndexOf<T, Field> = <T as FieldIndex<Field>>::Index;
+
+/// Names of the borrowed fields of the original struct, in the order of the ref struct fields, which
+/// is also the order of `FieldIndex` and `HasFieldTypes`. It is the declaration order, or the name
+/// order for structs marked with `#[borrow(sorted)]`. Fields marked with `#[borrow(skip)]` are not
+/// included. Raw identifiers are reported without the `r#` prefix.
+pub trait Reflect {
+    const FIELD_NAMES: &'static [&'static str];
+    const FIELD_COUNT: usize = Self::FIELD_NAMES.len();
+}
+
+/// Ref structs borrowing all fields of the original struct for the lifetime `'t`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` does not derive `PartialBorrow`",
+    label = "partial borrows of this type are not available"
+)]
+pub trait HasRefs<'t> {
+    type RefsMut;
+    type Refs;
+}
+
+/// Fails to compile if `T` does not derive `PartialBorrow`. Called by the derive for the wrapped
+/// type of `#[borrow(deref)]` newtypes, so that the error points at the wrapped type.
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+///
+/// struct Ctx {
+///     scene: Vec<usize>,
+/// }
+///
+/// #[derive(PartialBorrow)]
+/// #[borrow(deref)]
+/// // Error: `Ctx` does not derive `PartialBorrow`.
+/// struct App(Ctx);
+/// # fn main() {}
+/// ```
+#[doc(hidden)]
+#[inline(always)]
+pub const fn assert_derives_partial_borrow<T>() where for<'t> T: HasRefs<'t> {}
+
+
+// =========================
+// === No Access Wrapper ===
+// =========================
+
+/// Marks a field that is not part of the borrow. The pointer is never dereferenced. It is either
+/// null or points at the `T` value of the field. Optional and `Cow` fields are hidden with a null
+/// pointer when borrowed from the struct, as hiding a field must not read it to find the value.
+///
+/// It is `Copy`, so partial borrows with immutably borrowed and hidden fields only are `Copy` as
+/// well. Partial borrows with mutably borrowed fields are not:
+///
+/// ```compile_fail,E0382
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// fn copy_mut_borrow(graph: p!(<mut nodes, edges> Graph)) {
+///     let copy = graph;
+///     let _ = (graph, copy);
+/// }
+/// # fn main() {}
+/// ```
+#[repr(transparent)]
+pub struct Hidden<T>(*mut T);
+
+impl<T> Debug for Hidden<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<hidden>")
+    }
+}
+
+// SAFETY: The pointer is never dereferenced, it only marks a field that is not part of the
+// borrow. Sending or sharing it is as safe as sending or sharing `T` itself.
+unsafe impl<T: Send> Send for Hidden<T> {}
+unsafe impl<T: Sync> Sync for Hidden<T> {}
+
+impl<T> Copy for Hidden<T> {}
+#[allow(clippy::non_canonical_clone_impl)]
+impl<T> Clone for Hidden<T> {
+    #[inline(always)]
+    fn clone(&self) -> Self { Self(self.0) }
+}
+
+
+// ==============
+// === Nested ===
+// ==============
+
+/// Borrow slot of a field that is partially borrowed itself, like `scene(mut lights)` in
+/// `p!(&<mut log, scene(mut lights)> Ctx)`. The `Target` is the ref struct of the field type. Use
+/// `get` to access the borrowed fields.
+#[repr(transparent)]
+pub struct Nested<T, Target>(*mut T, PhantomData<Target>);
+
+impl<T, Target> Nested<T, Target> {
+    /// Borrows the fields of the nested struct required by `Target` for the lifetime `'t`.
+    #[inline(always)]
+    pub fn get<'t>(&'t mut self) -> <Target as RefFlatten<'t>>::Output
+    where Target: RefFlatten<'t>, T: AsRefsRaw<'t, <Target as RefFlatten<'t>>::Output> {
+        // SAFETY: The pointer was obtained from a mutable borrow of the field, and `Target`
+        // describes the fields of the nested struct that are borrowed by this slot.
+        unsafe { T::as_refs_raw(self.0) }
+    }
+}
+
+impl<T, Target> Debug for Nested<T, Target> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<nested>")
+    }
+}
+
+// SAFETY: The slot provides access to the fields borrowed by `Target` only.
+unsafe impl<T, Target: Send> Send for Nested<T, Target> {}
+unsafe impl<T, Target: Sync> Sync for Nested<T, Target> {}
+
+impl<T, Target: Copy> Copy for Nested<T, Target> {}
+impl<T, Target: Copy> Clone for Nested<T, Target> {
+    #[inline(always)]
+    fn clone(&self) -> Self { *self }
+}
+
+
+// ===================
+// === MergeNested ===
+// ===================
+
+/// Borrow slot of a `#[borrow(flatten)]` field after selecting more of its fields with `Target`,
+/// like `mut geometry` in `p!(&<mut geometry> Ctx)` for a flattened field of type `RenderingCtx`.
+/// Selections of the fields of the same flattened field are merged with `Union`.
+pub trait MergeNested<Target> { type Output; }
+
+impl<T, Target> MergeNested<Target> for Hidden<T> {
+    type Output = Nested<T, Target>;
+}
+
+impl<T, Source, Target> MergeNested<Target> for Nested<T, Source> where Source: Unify<Target> {
+    type Output = Nested<T, Union<Source, Target>>;
+}
+
+/// The slot of a `#[borrow(flatten)]` field after merging `Target` into the `Slot`.
+pub type MergedNested<Slot, Target> = <Slot as MergeNested<Target>>::Output;
+
+
+// =================
+// === RefTarget ===
+// =================
+
+/// The type of the field a borrow slot refers to.
+pub trait RefTarget { type Target; }
+impl<T> RefTarget for &T        { type Target = T; }
+impl<T> RefTarget for &mut T    { type Target = T; }
+impl<T> RefTarget for Hidden<T> { type Target = T; }
+impl<T> RefTarget for Option<&T>     { type Target = T; }
+impl<T> RefTarget for Option<&mut T> { type Target = T; }
+impl<T, Target> RefTarget for Nested<T, Target> { type Target = T; }
+
+
+// =====================
+// === OptionalField ===
+// =====================
+
+/// The type wrapped by a field marked with `#[borrow(optional)]`, like `SceneCtx` for
+/// `Option<SceneCtx>`. Such fields are borrowed as `Option<&T>` or `Option<&mut T>` instead of
+/// `&Option<T>` or `&mut Option<T>`, and hidden as `Hidden<T>`.
+pub trait OptionalField { type Inner; }
+impl<T> OptionalField for Option<T> { type Inner = T; }
+
+
+
+// ========================
+// === MaybeUninitField ===
+// ========================
+
+/// The type stored in a field marked with `#[borrow(unsafe(assume_init))]`, like `SceneCtx` for
+/// `MaybeUninit<SceneCtx>`. Such fields are borrowed as `&T` or `&mut T` with
+/// `MaybeUninit::assume_init_ref` and `MaybeUninit::assume_init_mut`, so they must be initialized
+/// whenever the struct is borrowed. As the derive can't check it, the attribute needs to be marked
+/// `unsafe`:
+///
+/// ```compile_fail
+/// use std::mem::MaybeUninit;
+/// use borrow::PartialBorrow;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Arena {
+///     // Error: 'assume_init' is unsafe.
+///     #[borrow(assume_init)]
+///     block: MaybeUninit<Vec<u8>>,
+/// }
+/// # fn main() {}
+/// ```
+pub trait MaybeUninitField { type Inner; }
+impl<T> MaybeUninitField for MaybeUninit<T> { type Inner = T; }
+
+// ================
+// === CowField ===
+// ================
+
+/// The type borrowed by a field marked with `#[borrow(cow)]`, like `SceneCtx` for
+/// `Cow<'a, SceneCtx>`. Such fields are borrowed as `&T` or `&mut T` instead of `&Cow<'a, T>` or
+/// `&mut Cow<'a, T>`. Borrowing the field mutably calls [`Cow::to_mut`], which clones the value if
+/// it is borrowed, so that every following borrow refers to the owned copy.
+#[cfg(feature = "std")]
+pub trait CowField { type Inner; }
+#[cfg(feature = "std")]
+impl<T: Clone> CowField for Cow<'_, T> { type Inner = T; }
+
+
+// ==================
+// === RefFlatten ===
+// ==================
+
+/// Reborrows a borrow slot for the lifetime `'t`, so that `&'t mut &'s mut T` becomes
+/// `&'t mut T`. Implemented for ref structs by flattening every slot.
+///
+/// ```
+/// use borrow::Hidden;
+/// use borrow::PartialBorrow;
+/// use borrow::RefFlatten;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+///     labels: Vec<String>,
+/// }
+///
+/// # fn main() {
+/// let mut graph = Graph { nodes: vec![0], edges: vec![], labels: vec![] };
+/// let mut refs = graph.as_refs_mut();
+/// let borrow = refs.partial_borrow::<p!(<mut nodes, edges> Graph)>();
+///
+/// // `&'s mut T` becomes `&'t mut T`.
+/// let nodes: &mut Vec<usize> = borrow.nodes.ref_flatten();
+/// // `&'s T` becomes `&'t T`.
+/// let edges: &Vec<usize> = borrow.edges.ref_flatten();
+/// // `Hidden<T>` stays `Hidden<T>`.
+/// let _labels: Hidden<Vec<String>> = borrow.labels.ref_flatten();
+///
+/// nodes.push(edges.len());
+/// assert_eq!(graph.nodes, vec![0, 0]);
+/// # }
+/// ```
+pub trait RefFlatten<'t> {
+    type Output;
+    fn ref_flatten(&'t mut self) -> Self::Output;
+}
+
+impl<'t, 's: 't, T> RefFlatten<'t> for &'s mut T {
+    type Output = &'t mut T;
+    #[inline(always)]
+    fn ref_flatten(&'t mut self) -> Self::Output { self }
+}
+
+impl<'t, 's: 't, T> RefFlatten<'t> for &'s T {
+    type Output = &'t T;
+    #[inline(always)]
+    fn ref_flatten(&'t mut self) -> Self::Output { self }
+}
+
+impl<'t, 's: 't, T> RefFlatten<'t> for Option<&'s mut T> {
+    type Output = Option<&'t mut T>;
+    #[inline(always)]
+    fn ref_flatten(&'t mut self) -> Self::Output { self.as_deref_mut() }
+}
+
+impl<'t, 's: 't, T> RefFlatten<'t> for Option<&'s T> {
+    type Output = Option<&'t T>;
+    #[inline(always)]
+    fn ref_flatten(&'t mut self) -> Self::Output { *self }
+}
+
+impl<'t, T> RefFlatten<'t> for Hidden<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn ref_flatten(&'t mut self) -> Self::Output { *self }
+}
+
+impl<'t, T, Target: RefFlatten<'t>> RefFlatten<'t> for Nested<T, Target> {
+    type Output = Nested<T, <Target as RefFlatten<'t>>::Output>;
+    #[inline(always)]
+    fn ref_flatten(&'t mut self) -> Self::Output { Nested(self.0, PhantomData) }
+}
+
+
+// =====================
+// === ReborrowField ===
+// =====================
+
+/// Reborrows a borrow slot for the lifetime `'t` as the slot `T`. A mutably borrowed field can be
+/// reborrowed mutably, immutably, or hidden, and an immutably borrowed field immutably or hidden.
+/// Hidden fields stay hidden. Used by the `AsRefs` impls of ref structs, so that
+/// `ctx.as_refs::<p!(<...> Ctx)>()` selects a new set of the currently borrowed fields:
+///
+/// ```
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// fn push_node(mut graph: p!(<mut nodes, edges> Graph)) {
+///     let nodes = graph.as_refs::<p!(<mut nodes> Graph)>();
+///     nodes.nodes.push(0);
+/// }
+/// # fn main() {}
+/// ```
+///
+/// Borrowing a hidden field fails to compile:
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// fn push_edge(mut graph: p!(<mut nodes> Graph)) {
+///     let edges = graph.as_refs::<p!(<mut edges> Graph)>();
+///     edges.edges.push(0);
+/// }
+/// # fn main() {}
+/// ```
+pub trait ReborrowField<'t, T> {
+    fn reborrow_field(&'t mut self) -> T;
+}
+
+impl<'t, 's: 't, T> ReborrowField<'t, &'t mut T> for &'s mut T {
+    #[inline(always)]
+    fn reborrow_field(&'t mut self) -> &'t mut T { self }
+}
+
+impl<'t, 's: 't, T> ReborrowField<'t, &'t T> for &'s mut T {
+    #[inline(always)]
+    fn reborrow_field(&'t mut self) -> &'t T { self }
+}
+
+impl<'t, T> ReborrowField<'t, Hidden<T>> for &mut T {
+    #[inline(always)]
+    fn reborrow_field(&'t mut self) -> Hidden<T> { Hidden(ptr::from_mut(*self)) }
+}
+
+impl<'t, 's: 't, T> ReborrowField<'t, &'t T> for &'s T {
+    #[inline(always)]
+    fn reborrow_field(&'t mut self) -> &'t T { self }
+}
+
+impl<'t, T> ReborrowField<'t, Hidden<T>> for &T {
+    #[inline(always)]
+    fn reborrow_field(&'t mut self) -> Hidden<T> { Hidden(ptr::from_ref(*self).cast_mut()) }
+}
+
+impl<'t, 's: 't, T> ReborrowField<'t, Option<&'t mut T>> for Option<&'s mut T> {
+    #[inline(always)]
+    fn reborrow_field(&'t mut self) -> Option<&'t mut T> { self.as_deref_mut() }
+}
+
+impl<'t, 's: 't, T> ReborrowField<'t, Option<&'t T>> for Option<&'s mut T> {
+    #[inline(always)]
+    fn reborrow_field(&'t mut self) -> Option<&'t T> { self.as_deref() }
+}
+
+// The hidden optional field keeps the pointer to the `T` value. A `None` field has no value to
+// point at, so it is hidden with a null pointer, see `Hidden`.
+impl<'t, T> ReborrowField<'t, Hidden<T>> for Option<&mut T> {
+    #[inline(always)]
+    fn reborrow_field(&'t mut self) -> Hidden<T> {
+        Hidden(self.as_deref_mut().map_or(ptr::null_mut(), ptr::from_mut))
+    }
+}
+
+impl<'t, 's: 't, T> ReborrowField<'t, Option<&'t T>> for Option<&'s T> {
+    #[inline(always)]
+    fn reborrow_field(&'t mut self) -> Option<&'t T> { *self }
+}
+
+impl<'t, T> ReborrowField<'t, Hidden<T>> for Option<&T> {
+    #[inline(always)]
+    fn reborrow_field(&'t mut self) -> Hidden<T> {
+        Hidden(self.map_or(ptr::null_mut(), |field| ptr::from_ref(field).cast_mut()))
+    }
+}
+
+impl<'t, T> ReborrowField<'t, Self> for Hidden<T> {
+    #[inline(always)]
+    fn reborrow_field(&'t mut self) -> Self { *self }
+}
+
+impl<'t, T, Target: RefFlatten<'t>> ReborrowField<'t, Nested<T, <Target as RefFlatten<'t>>::Output>>
+for Nested<T, Target> {
+    #[inline(always)]
+    fn reborrow_field(&'t mut self) -> Nested<T, <Target as RefFlatten<'t>>::Output> {
+        Nested(self.0, PhantomData)
+    }
+}
+
+impl<'t, T, Target> ReborrowField<'t, Hidden<T>> for Nested<T, Target> {
+    #[inline(always)]
+    fn reborrow_field(&'t mut self) -> Hidden<T> { Hidden(self.0) }
+}
+
+
+// ====================
+// === VisibleField ===
+// ====================
+
+/// Borrow slot that can be accessed, i.e. any slot but [`Hidden`]. Used by the accessors of the
+/// generated view structs, like `ctx.view().geometry()`, to report access to hidden fields, and by
+/// the generated `hide_$field` methods, to report hiding a field twice.
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// // Error: The field is not accessible, as it is not part of this partial borrow.
+/// fn count_nodes(graph: p!(&<edges> Graph)) -> usize {
+///     graph.view().nodes().len()
+/// }
+/// # fn main() {}
+/// ```
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// // Error: The field is not accessible, as it is not part of this partial borrow.
+/// fn hide_nodes(graph: p!(&<edges> Graph)) {
+///     graph.hide_nodes();
+/// }
+/// # fn main() {}
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "The field is not accessible, as it is not part of this partial borrow.",
+    label = "hidden field `{Self}`",
+    note = "Add the field to the `p!` selectors to access it."
+)]
+pub trait VisibleField {}
+
+impl<T> VisibleField for &mut T {}
+impl<T> VisibleField for &T {}
+impl<T> VisibleField for Option<&mut T> {}
+impl<T> VisibleField for Option<&T> {}
+impl<T, Target> VisibleField for Nested<T, Target> {}
+
+
+// ================
+// === MutField ===
+// ================
+
+/// Borrow slot giving mutable access to the field, i.e. `&mut T`. Used by the generated
+/// `$field_mut` accessors, like `graph.nodes_mut()`, to report fields that are borrowed
+/// immutably or hidden:
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// // Error: The field is not borrowed mutably by this partial borrow.
+/// fn clear_nodes(graph: p!(&<nodes, mut edges> Graph)) {
+///     graph.nodes_mut().clear();
+/// }
+/// # fn main() {}
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "The field is not borrowed mutably by this partial borrow.",
+    label = "field borrowed as `{Self}`",
+    note = "Select the field with `mut` in the `p!` selectors to access it mutably."
+)]
+pub trait MutField {
+    type Target: ?Sized;
+    fn field_mut(&mut self) -> &mut Self::Target;
+}
+
+impl<T: ?Sized> MutField for &mut T {
+    type Target = T;
+    #[inline(always)]
+    fn field_mut(&mut self) -> &mut T { self }
+}
+
+/// Borrow slot giving immutable access to the field, i.e. `&T` or `&mut T`. Used by the generated
+/// `$field_ref` accessors, like `graph.nodes_ref()`, to report hidden fields, and by the
+/// `take_$field` methods copying `Copy` fields, like `graph.take_count()`:
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// // Error: The field is not accessible, as it is not part of this partial borrow.
+/// fn node_count(graph: p!(&<mut edges> Graph)) -> usize {
+///     graph.nodes_ref().len()
+/// }
+/// # fn main() {}
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "The field is not accessible, as it is not part of this partial borrow.",
+    label = "field borrowed as `{Self}`",
+    note = "Add the field to the `p!` selectors to access it."
+)]
+pub trait SharedField {
+    type Target: ?Sized;
+    fn field_ref(&self) -> &Self::Target;
+}
+
+impl<T: ?Sized> SharedField for &T {
+    type Target = T;
+    #[inline(always)]
+    fn field_ref(&self) -> &T { self }
+}
+
+impl<T: ?Sized> SharedField for &mut T {
+    type Target = T;
+    #[inline(always)]
+    fn field_ref(&self) -> &T { self }
+}
+
+
+// ==================
+// === LiveFields ===
+// ==================
+
+/// Prepends a flattened borrow slot to the `Rest` list, unless the slot is [`Hidden`].
+pub trait PushLiveField<Rest> {
+    type Output;
+    fn push_live_field(self, rest: Rest) -> Self::Output;
+}
+
+impl<T, Rest> PushLiveField<Rest> for &mut T {
+    type Output = Cons<Self, Rest>;
+    #[inline(always)]
+    fn push_live_field(self, rest: Rest) -> Self::Output { Cons { head: self, tail: rest } }
+}
+
+impl<T, Rest> PushLiveField<Rest> for &T {
+    type Output = Cons<Self, Rest>;
+    #[inline(always)]
+    fn push_live_field(self, rest: Rest) -> Self::Output { Cons { head: self, tail: rest } }
+}
+
+impl<T, Rest> PushLiveField<Rest> for Option<&mut T> {
+    type Output = Cons<Self, Rest>;
+    #[inline(always)]
+    fn push_live_field(self, rest: Rest) -> Self::Output { Cons { head: self, tail: rest } }
+}
+
+impl<T, Rest> PushLiveField<Rest> for Option<&T> {
+    type Output = Cons<Self, Rest>;
+    #[inline(always)]
+    fn push_live_field(self, rest: Rest) -> Self::Output { Cons { head: self, tail: rest } }
+}
+
+impl<T, Rest> PushLiveField<Rest> for Hidden<T> {
+    type Output = Rest;
+    #[inline(always)]
+    fn push_live_field(self, rest: Rest) -> Self::Output { rest }
+}
+
+impl<T, Target, Rest> PushLiveField<Rest> for Nested<T, Target> {
+    type Output = Cons<Self, Rest>;
+    #[inline(always)]
+    fn push_live_field(self, rest: Rest) -> Self::Output { Cons { head: self, tail: rest } }
+}
+
+/// Filters out [`Hidden`] slots from an `HList` of flattened borrow slots, preserving the order
+/// of the remaining ones.
+pub trait LiveFields {
+    type Output;
+    fn live_fields(self) -> Self::Output;
+}
+
+impl LiveFields for Nil {
+    type Output = Self;
+    #[inline(always)]
+    fn live_fields(self) -> Self::Output { self }
+}
+
+impl<H, T> LiveFields for Cons<H, T> where
+    T: LiveFields,
+    H: PushLiveField<<T as LiveFields>::Output> {
+    type Output = <H as PushLiveField<<T as LiveFields>::Output>>::Output;
+    #[inline(always)]
+    fn live_fields(self) -> Self::Output { self.head.push_live_field(self.tail.live_fields()) }
+}
+
+
+/// The only slot of an `HList` of flattened borrow slots that is not [`Hidden`]. Not implemented if
+/// there are no such slots or more than one. Used by the `IntoIterator` impls of ref structs, so
+/// that a borrow of a single collection field can be iterated directly:
+///
+/// ```
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// fn increment_nodes(graph: p!(&<mut nodes> Graph)) {
+///     for node in graph {
+///         *node += 1;
+///     }
+/// }
+/// # fn main() {}
+/// ```
+///
+/// Borrows of several fields can't be iterated, as it would be ambiguous:
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// fn increment_nodes(graph: p!(&<mut nodes, edges> Graph)) {
+///     for node in graph {
+///         *node += 1;
+///     }
+/// }
+/// # fn main() {}
+/// ```
+pub trait SingleLiveField {
+    type Output;
+    fn single_live_field(self) -> Self::Output;
+}
+
+impl<T, F> SingleLiveField for T where T: LiveFields<Output = Cons<F, Nil>> {
+    type Output = F;
+    #[inline(always)]
+    fn single_live_field(self) -> Self::Output { self.live_fields().head }
+}
+
+// ================
+// === FieldPtr ===
+// ================
+
+/// Pointer to a field borrowed by a visible slot. The `Index` and `IndexMut` impls of ref structs
+/// find the single live field among the pointers to their fields, as in contrast to the
+/// references, the pointer types don't depend on the lifetime of the borrow of the ref struct.
+/// Like `IntoIterator`, they are implemented only for borrows of a single field, as indexing
+/// several fields would be ambiguous:
+///
+/// ```compile_fail,E0608
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// fn first_edge(graph: p!(&<nodes, mut edges> Graph)) -> usize {
+///     graph[0]
+/// }
+/// # fn main() {}
+/// ```
+pub struct FieldPtr<T>(*const T);
+
+/// Like [`FieldPtr`], but pointing to a field borrowed mutably.
+pub struct FieldPtrMut<T>(*mut T);
+
+impl<T, Rest> PushLiveField<Rest> for FieldPtr<T> {
+    type Output = Cons<Self, Rest>;
+    #[inline(always)]
+    fn push_live_field(self, rest: Rest) -> Self::Output { Cons { head: self, tail: rest } }
+}
+
+impl<T, Rest> PushLiveField<Rest> for FieldPtrMut<T> {
+    type Output = Cons<Self, Rest>;
+    #[inline(always)]
+    fn push_live_field(self, rest: Rest) -> Self::Output { Cons { head: self, tail: rest } }
+}
+
+/// Pointers to the field borrowed by a slot. Hidden slots stay [`Hidden`]. Optional and nested
+/// slots point to the slot itself, so their fields can't be indexed.
+pub trait SlotPtr {
+    type Ptr;
+    type PtrMut;
+    fn slot_ptr(&self) -> Self::Ptr;
+    fn slot_ptr_mut(&mut self) -> Self::PtrMut;
+}
+
+impl<T> SlotPtr for &T {
+    type Ptr = FieldPtr<T>;
+    type PtrMut = FieldPtr<T>;
+    #[inline(always)]
+    fn slot_ptr(&self) -> Self::Ptr { FieldPtr(*self) }
+    #[inline(always)]
+    fn slot_ptr_mut(&mut self) -> Self::PtrMut { FieldPtr(*self) }
+}
+
+impl<T> SlotPtr for &mut T {
+    type Ptr = FieldPtr<T>;
+    type PtrMut = FieldPtrMut<T>;
+    #[inline(always)]
+    fn slot_ptr(&self) -> Self::Ptr { FieldPtr(&**self) }
+    #[inline(always)]
+    fn slot_ptr_mut(&mut self) -> Self::PtrMut { FieldPtrMut(&mut **self) }
+}
+
+impl<T> SlotPtr for Hidden<T> {
+    type Ptr = Self;
+    type PtrMut = Self;
+    #[inline(always)]
+    fn slot_ptr(&self) -> Self::Ptr { *self }
+    #[inline(always)]
+    fn slot_ptr_mut(&mut self) -> Self::PtrMut { *self }
+}
+
+impl<T> SlotPtr for Option<&T> {
+    type Ptr = FieldPtr<Self>;
+    type PtrMut = FieldPtr<Self>;
+    #[inline(always)]
+    fn slot_ptr(&self) -> Self::Ptr { FieldPtr(self) }
+    #[inline(always)]
+    fn slot_ptr_mut(&mut self) -> Self::PtrMut { FieldPtr(self) }
+}
+
+impl<T> SlotPtr for Option<&mut T> {
+    type Ptr = FieldPtr<Self>;
+    type PtrMut = FieldPtr<Self>;
+    #[inline(always)]
+    fn slot_ptr(&self) -> Self::Ptr { FieldPtr(self) }
+    #[inline(always)]
+    fn slot_ptr_mut(&mut self) -> Self::PtrMut { FieldPtr(self) }
+}
+
+impl<T, Target> SlotPtr for Nested<T, Target> {
+    type Ptr = FieldPtr<Self>;
+    type PtrMut = FieldPtr<Self>;
+    #[inline(always)]
+    fn slot_ptr(&self) -> Self::Ptr { FieldPtr(self) }
+    #[inline(always)]
+    fn slot_ptr_mut(&mut self) -> Self::PtrMut { FieldPtr(self) }
+}
+
+/// Reference to the field of a [`FieldPtr`] or [`FieldPtrMut`].
+pub trait LiveFieldPtr {
+    type Target;
+    /// # Safety
+    /// The pointer must be created by [`SlotPtr::slot_ptr`] from a slot borrowed for `'t`.
+    unsafe fn field_ref<'t>(self) -> &'t Self::Target;
+}
+
+/// Mutable reference to the field of a [`FieldPtrMut`].
+pub trait LiveFieldPtrMut: LiveFieldPtr {
+    /// # Safety
+    /// The pointer must be created by [`SlotPtr::slot_ptr_mut`] from a slot borrowed mutably for
+    /// `'t`.
+    unsafe fn field_mut<'t>(self) -> &'t mut Self::Target;
+}
+
+impl<T> LiveFieldPtr for FieldPtr<T> {
+    type Target = T;
+    #[inline(always)]
+    unsafe fn field_ref<'t>(self) -> &'t T { unsafe { &*self.0 } }
+}
+
+impl<T> LiveFieldPtr for FieldPtrMut<T> {
+    type Target = T;
+    #[inline(always)]
+    unsafe fn field_ref<'t>(self) -> &'t T { unsafe { &*self.0 } }
+}
+
+impl<T> LiveFieldPtrMut for FieldPtrMut<T> {
+    #[inline(always)]
+    unsafe fn field_mut<'t>(self) -> &'t mut T { unsafe { &mut *self.0 } }
+}
+
+// ==================
+// === DebugField ===
+// ==================
+
+/// Formats a borrow slot, showing whether the field is borrowed immutably (`&value`), mutably
+/// (`&mut value`), or is hidden (`<hidden>`). Used by the `Debug` impl of the generated ref struct.
+pub trait DebugField {
+    fn fmt_field(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+impl<T: Debug> DebugField for &T {
+    fn fmt_field(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("&")?;
+        Debug::fmt(*self, f)
+    }
+}
+
+impl<T: Debug> DebugField for &mut T {
+    fn fmt_field(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("&mut ")?;
+        Debug::fmt(*self, f)
+    }
+}
+
+impl<T: Debug> DebugField for Option<&T> {
+    fn fmt_field(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl<T: Debug> DebugField for Option<&mut T> {
+    fn fmt_field(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl<T> DebugField for Hidden<T> {
+    fn fmt_field(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl<T, Target> DebugField for Nested<T, Target> {
+    fn fmt_field(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+/// Adapter implementing `Debug` for any `DebugField`.
+pub struct DebugFieldFmt<'t, T>(pub &'t T);
+
+impl<T: DebugField> Debug for DebugFieldFmt<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_field(f)
+    }
+}
+
+
+
+// ====================
+// === FieldVisitor ===
+// ====================
+
+/// Operation run on every visible field of a partial borrow by the `for_each_visible_field`
+/// method of ref structs. The visitor is called with the name of the field and a reference to its
+/// value, in the order of the ref struct fields. Hidden fields and optional fields that are `None`
+/// are skipped. Nested borrows are reported by `visit_nested` only, as the nested struct is not
+/// borrowed as a whole.
+///
+/// ```
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+/// use borrow::FieldVisitor;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Ctx {
+///     geometry: Vec<f32>,
+///     material: String,
+///     mesh: Vec<usize>,
+/// }
+///
+/// #[derive(Default)]
+/// struct Names(Vec<&'static str>);
+///
+/// impl FieldVisitor for Names {
+///     fn visit<T: ?Sized>(&mut self, name: &'static str, _field: &T) {
+///         self.0.push(name);
+///     }
+/// }
+///
+/// fn visible_names(ctx: p!(&<geometry, mut mesh> Ctx)) -> Vec<&'static str> {
+///     let mut names = Names::default();
+///     ctx.for_each_visible_field(&mut names);
+///     names.0
+/// }
+/// # fn main() {
+/// # let mut ctx = Ctx { geometry: vec![], material: String::new(), mesh: vec![] };
+/// # assert_eq!(visible_names(ctx.as_refs_mut().partial_borrow()), ["geometry", "mesh"]);
+/// # }
+/// ```
+pub trait FieldVisitor {
+    /// Called for every borrowed field, no matter whether it is borrowed mutably or not.
+    fn visit<T: ?Sized>(&mut self, name: &'static str, field: &T);
+
+    /// Called for every nested borrow, like `scene(mut lights)`.
+    #[inline(always)]
+    fn visit_nested(&mut self, name: &'static str) {
+        let _ = name;
+    }
+}
+
+/// A borrow slot that can be passed to a [`FieldVisitor`].
+pub trait VisitSlot {
+    fn visit_slot<V: FieldVisitor>(&self, name: &'static str, visitor: &mut V);
+}
+
+impl<T: ?Sized> VisitSlot for &T {
+    #[inline(always)]
+    fn visit_slot<V: FieldVisitor>(&self, name: &'static str, visitor: &mut V) {
+        visitor.visit(name, *self);
+    }
+}
+
+impl<T: ?Sized> VisitSlot for &mut T {
+    #[inline(always)]
+    fn visit_slot<V: FieldVisitor>(&self, name: &'static str, visitor: &mut V) {
+        visitor.visit(name, &**self);
+    }
+}
+
+impl<T> VisitSlot for Option<&T> {
+    #[inline(always)]
+    fn visit_slot<V: FieldVisitor>(&self, name: &'static str, visitor: &mut V) {
+        if let Some(field) = self { visitor.visit(name, *field) }
+    }
+}
+
+impl<T> VisitSlot for Option<&mut T> {
+    #[inline(always)]
+    fn visit_slot<V: FieldVisitor>(&self, name: &'static str, visitor: &mut V) {
+        if let Some(field) = self { visitor.visit(name, &**field) }
+    }
+}
+
+impl<T> VisitSlot for Hidden<T> {
+    #[inline(always)]
+    fn visit_slot<V: FieldVisitor>(&self, _name: &'static str, _visitor: &mut V) {}
+}
+
+impl<T, Target> VisitSlot for Nested<T, Target> {
+    #[inline(always)]
+    fn visit_slot<V: FieldVisitor>(&self, name: &'static str, visitor: &mut V) {
+        visitor.visit_nested(name);
+    }
+}
+
+/// Walks an `HList` of references to borrow slots, like the [`HasFields::Fields`] of a ref struct,
+/// passing every slot with its name to a [`FieldVisitor`]. The `names` are given in the order of
+/// the slots.
+pub trait VisitFields {
+    fn visit_fields<V: FieldVisitor>(&self, names: &[&'static str], visitor: &mut V);
+}
+
+impl VisitFields for Nil {
+    #[inline(always)]
+    fn visit_fields<V: FieldVisitor>(&self, _names: &[&'static str], _visitor: &mut V) {}
+}
+
+impl<H: VisitSlot, T: VisitFields> VisitFields for Cons<&H, T> {
+    #[inline(always)]
+    fn visit_fields<V: FieldVisitor>(&self, names: &[&'static str], visitor: &mut V) {
+        if let Some((name, rest)) = names.split_first() {
+            self.head.visit_slot(name, visitor);
+            self.tail.visit_fields(rest, visitor);
+        }
+    }
+}
+
+
+// =====================
+// === VisibleFields ===
+// =====================
+
+/// `Debug` output of the visible fields of a partial borrow, returned by the `visible_fields`
+/// method of ref structs. The fields are formatted as a map from their names to their values, in
+/// the order of the ref struct fields. In contrast to the `Debug` impl of the ref struct, hidden
+/// fields are omitted instead of printed as `<hidden>`, so the output depends only on the borrowed
+/// fields, which makes it handy for dumping borrows while debugging. Nested borrows are printed as
+/// `<nested>`, and optional fields as `Some(...)` or `None`.
+///
+/// ```
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Ctx {
+///     frame: u64,
+///     material: String,
+///     log: Vec<String>,
+/// }
+///
+/// fn dump(ctx: p!(&<frame, mut log> Ctx)) -> String {
+///     format!("{:?}", ctx.visible_fields())
+/// }
+/// # fn main() {
+/// # let mut ctx = Ctx { frame: 3, material: String::new(), log: vec!["run".to_string()] };
+/// # assert_eq!(dump(ctx.as_refs_mut().partial_borrow()), r#"{"frame": 3, "log": ["run"]}"#);
+/// # }
+/// ```
+pub struct VisibleFields<F> {
+    fields: F,
+    names: &'static [&'static str],
+}
+
+impl<F> VisibleFields<F> {
+    /// Formats the `fields`, an `HList` of references to borrow slots, with the `names` given in
+    /// the order of the slots.
+    #[inline(always)]
+    pub fn new(fields: F, names: &'static [&'static str]) -> Self {
+        Self { fields, names }
+    }
+}
+
+impl<F: DebugVisibleFields> Debug for VisibleFields<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut map = f.debug_map();
+        self.fields.debug_visible_fields(self.names, &mut map);
+        map.finish()
+    }
+}
+
+/// A borrow slot formatted by [`VisibleFields`]. Hidden slots add no entry.
+pub trait DebugVisibleSlot {
+    fn debug_visible_slot(&self, name: &'static str, map: &mut fmt::DebugMap<'_, '_>);
+}
+
+impl<T: ?Sized + Debug> DebugVisibleSlot for &T {
+    #[inline(always)]
+    fn debug_visible_slot(&self, name: &'static str, map: &mut fmt::DebugMap<'_, '_>) {
+        map.entry(&name, self);
+    }
+}
+
+impl<T: ?Sized + Debug> DebugVisibleSlot for &mut T {
+    #[inline(always)]
+    fn debug_visible_slot(&self, name: &'static str, map: &mut fmt::DebugMap<'_, '_>) {
+        map.entry(&name, self);
+    }
+}
+
+impl<T: Debug> DebugVisibleSlot for Option<&T> {
+    #[inline(always)]
+    fn debug_visible_slot(&self, name: &'static str, map: &mut fmt::DebugMap<'_, '_>) {
+        map.entry(&name, self);
+    }
+}
+
+impl<T: Debug> DebugVisibleSlot for Option<&mut T> {
+    #[inline(always)]
+    fn debug_visible_slot(&self, name: &'static str, map: &mut fmt::DebugMap<'_, '_>) {
+        map.entry(&name, self);
+    }
+}
+
+impl<T> DebugVisibleSlot for Hidden<T> {
+    #[inline(always)]
+    fn debug_visible_slot(&self, _name: &'static str, _map: &mut fmt::DebugMap<'_, '_>) {}
+}
+
+impl<T, Target> DebugVisibleSlot for Nested<T, Target> {
+    #[inline(always)]
+    fn debug_visible_slot(&self, name: &'static str, map: &mut fmt::DebugMap<'_, '_>) {
+        map.entry(&name, self);
+    }
+}
+
+/// Walks an `HList` of references to borrow slots, adding every visible slot with its name to a
+/// debug map. See [`VisibleFields`].
+pub trait DebugVisibleFields {
+    fn debug_visible_fields(&self, names: &[&'static str], map: &mut fmt::DebugMap<'_, '_>);
+}
+
+impl DebugVisibleFields for Nil {
+    #[inline(always)]
+    fn debug_visible_fields(&self, _names: &[&'static str], _map: &mut fmt::DebugMap<'_, '_>) {}
+}
+
+impl<H: DebugVisibleSlot, T: DebugVisibleFields> DebugVisibleFields for Cons<&H, T> {
+    #[inline(always)]
+    fn debug_visible_fields(&self, names: &[&'static str], map: &mut fmt::DebugMap<'_, '_>) {
+        if let Some((name, rest)) = names.split_first() {
+            self.head.debug_visible_slot(name, map);
+            self.tail.debug_visible_fields(rest, map);
+        }
+    }
+}
+
+// ==================
+// === CloneField ===
+// ==================
+
+/// Clones the field a borrow slot refers to. Borrowed fields are cloned to owned values, hidden
+/// fields are excluded from the owned output and stay `Hidden`. Nested borrows are not supported.
+pub trait CloneField {
+    type Owned;
+    fn clone_field(&self) -> Self::Owned;
+}
+
+impl<T: Clone> CloneField for &T {
+    type Owned = T;
+    #[inline(always)]
+    fn clone_field(&self) -> Self::Owned { T::clone(self) }
+}
+
+impl<T: Clone> CloneField for &mut T {
+    type Owned = T;
+    #[inline(always)]
+    fn clone_field(&self) -> Self::Owned { T::clone(self) }
+}
+
+impl<T: Clone> CloneField for Option<&T> {
+    type Owned = Option<T>;
+    #[inline(always)]
+    fn clone_field(&self) -> Self::Owned { self.cloned() }
+}
+
+impl<T: Clone> CloneField for Option<&mut T> {
+    type Owned = Option<T>;
+    #[inline(always)]
+    fn clone_field(&self) -> Self::Owned { self.as_deref().cloned() }
+}
+
+impl<T> CloneField for Hidden<T> {
+    type Owned = Self;
+    #[inline(always)]
+    fn clone_field(&self) -> Self::Owned { *self }
+}
+
+/// Clones the borrowed fields of a ref struct to a ref struct of owned values. See
+/// [`PartialBorrowHelper::clone_fields`].
+pub trait CloneFields {
+    type Owned;
+    fn clone_fields_impl(&self) -> Self::Owned;
+}
+
+pub type OwnedFields<T> = <T as CloneFields>::Owned;
+
+// ===============
+// === RefCast ===
+// ===============
+
+pub trait RefCast<'t, T> {
+    fn ref_cast(&'t mut self) -> T;
+}
+
+impl<'t, T> RefCast<'t, &'t T> for T {
+    #[inline(always)]
+    fn ref_cast(&'t mut self) -> &'t T { self }
+}
+
+impl<'t, T> RefCast<'t, &'t mut T> for T {
+    #[inline(always)]
+    fn ref_cast(&'t mut self) -> &'t mut T { self }
+}
+
+impl<'t, T> RefCast<'t, Hidden<T>> for T {
+    #[inline(always)]
+    fn ref_cast(&'t mut self) -> Hidden<T> { Hidden(self) }
+}
+
+impl<'t, T> RefCast<'t, Option<&'t T>> for Option<T> {
+    #[inline(always)]
+    fn ref_cast(&'t mut self) -> Option<&'t T> { self.as_ref() }
+}
+
+impl<'t, T> RefCast<'t, Option<&'t mut T>> for Option<T> {
+    #[inline(always)]
+    fn ref_cast(&'t mut self) -> Option<&'t mut T> { self.as_mut() }
+}
+
+impl<'t, T> RefCast<'t, Hidden<T>> for Option<T> {
+    #[inline(always)]
+    fn ref_cast(&'t mut self) -> Hidden<T> { Hidden(ptr::null_mut()) }
+}
+
+#[cfg(feature = "std")]
+impl<'t, T: Clone> RefCast<'t, &'t T> for Cow<'_, T> {
+    #[inline(always)]
+    fn ref_cast(&'t mut self) -> &'t T { self }
+}
+
+/// Clones the value if it is borrowed.
+#[cfg(feature = "std")]
+impl<'t, T: Clone> RefCast<'t, &'t mut T> for Cow<'_, T> {
+    #[inline(always)]
+    fn ref_cast(&'t mut self) -> &'t mut T { self.to_mut() }
+}
+
+#[cfg(feature = "std")]
+impl<'t, T: Clone> RefCast<'t, Hidden<T>> for Cow<'_, T> {
+    #[inline(always)]
+    fn ref_cast(&'t mut self) -> Hidden<T> { Hidden(ptr::null_mut()) }
+}
+
+
+/// Fails to compile if the field type can't be borrowed by the ref struct, like unsized slices.
+/// Called by the derive for every field, so that the error points at the field.
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Buffer {
+///     len: usize,
+///     // Error: the size for values of type `[u8]` cannot be known at compilation time.
+///     data: [u8],
+/// }
+/// # fn main() {}
+/// ```
+#[doc(hidden)]
+#[inline(always)]
+pub const fn assert_field_can_be_borrowed<T>() where
+    for<'t> T: RefCast<'t, &'t mut T> + RefCast<'t, &'t T> + RefCast<'t, Hidden<T>> {}
+
+
+// =====================
+// === SharedRefCast ===
+// =====================
+
+pub trait SharedRefCast<'t, T> {
+    fn shared_ref_cast(&'t self) -> T;
+}
+
+impl<'t, T> SharedRefCast<'t, &'t T> for T {
+    #[inline(always)]
+    fn shared_ref_cast(&'t self) -> &'t T { self }
+}
+
+impl<'t, T> SharedRefCast<'t, Hidden<T>> for T {
+    #[inline(always)]
+    fn shared_ref_cast(&'t self) -> Hidden<T> { Hidden(ptr::from_ref(self).cast_mut()) }
+}
+
+impl<'t, T> SharedRefCast<'t, Option<&'t T>> for Option<T> {
+    #[inline(always)]
+    fn shared_ref_cast(&'t self) -> Option<&'t T> { self.as_ref() }
+}
+
+impl<'t, T> SharedRefCast<'t, Hidden<T>> for Option<T> {
+    #[inline(always)]
+    fn shared_ref_cast(&'t self) -> Hidden<T> { Hidden(ptr::null_mut()) }
+}
+
+#[cfg(feature = "std")]
+impl<'t, T: Clone> SharedRefCast<'t, &'t T> for Cow<'_, T> {
+    #[inline(always)]
+    fn shared_ref_cast(&'t self) -> &'t T { self }
+}
+
+#[cfg(feature = "std")]
+impl<'t, T: Clone> SharedRefCast<'t, Hidden<T>> for Cow<'_, T> {
+    #[inline(always)]
+    fn shared_ref_cast(&'t self) -> Hidden<T> { Hidden(ptr::null_mut()) }
+}
+
+
+// ==================
+// === PinRefCast ===
+// ==================
+
+/// Like `RefCast`, but used by `AsPinRefs` to borrow the fields of a pinned struct.
+///
+/// # Safety
+/// Implementations must not provide a way to move out of `Self` unless `Self: Unpin`.
+pub unsafe trait PinRefCast<'t, T> {
+    fn pin_ref_cast(&'t mut self) -> T;
+}
 
+unsafe impl<'t, T> PinRefCast<'t, &'t T> for T {
+    #[inline(always)]
+    fn pin_ref_cast(&'t mut self) -> &'t T { self }
+}
 
-// ==============
-// === Traits ===
-// ==============
+unsafe impl<'t, T: Unpin> PinRefCast<'t, &'t mut T> for T {
+    #[inline(always)]
+    fn pin_ref_cast(&'t mut self) -> &'t mut T { self }
+}
 
-pub mod traits {
-    pub use super::Acquire as _;
-    pub use super::PartialBorrow as _;
-    pub use super::PartialBorrowHelper as _;
-    pub use super::RefCast as _;
-    pub use super::AsRefs as _;
-    pub use super::AsRefsHelper as _;
+unsafe impl<'t, T> PinRefCast<'t, Hidden<T>> for T {
+    #[inline(always)]
+    fn pin_ref_cast(&'t mut self) -> Hidden<T> { Hidden(self) }
 }
 
+unsafe impl<'t, T> PinRefCast<'t, Option<&'t T>> for Option<T> {
+    #[inline(always)]
+    fn pin_ref_cast(&'t mut self) -> Option<&'t T> { self.as_ref() }
+}
 
-// ==============
-// === AsRefs ===
-// ==============
+unsafe impl<'t, T: Unpin> PinRefCast<'t, Option<&'t mut T>> for Option<T> {
+    #[inline(always)]
+    fn pin_ref_cast(&'t mut self) -> Option<&'t mut T> { self.as_mut() }
+}
 
-pub trait AsRefs<'t, T> {
-    fn as_refs_impl(&'t mut self) -> T;
+unsafe impl<'t, T> PinRefCast<'t, Hidden<T>> for Option<T> {
+    #[inline(always)]
+    fn pin_ref_cast(&'t mut self) -> Hidden<T> { Hidden(ptr::null_mut()) }
 }
 
-impl<'t, T> AsRefsHelper<'t> for T {}
-pub trait AsRefsHelper<'t> {
+#[cfg(feature = "std")]
+unsafe impl<'t, T: Clone> PinRefCast<'t, &'t T> for Cow<'_, T> {
     #[inline(always)]
-    fn as_refs<T>(&'t mut self) -> T
-    where Self: AsRefs<'t, T> { self.as_refs_impl() }
+    fn pin_ref_cast(&'t mut self) -> &'t T { self }
 }
 
+#[cfg(feature = "std")]
+unsafe impl<'t, T: Clone + Unpin> PinRefCast<'t, &'t mut T> for Cow<'_, T> {
+    #[inline(always)]
+    fn pin_ref_cast(&'t mut self) -> &'t mut T { self.to_mut() }
+}
 
-// =======================
-// === Struct Generics ===
-// =======================
+#[cfg(feature = "std")]
+unsafe impl<'t, T: Clone> PinRefCast<'t, Hidden<T>> for Cow<'_, T> {
+    #[inline(always)]
+    fn pin_ref_cast(&'t mut self) -> Hidden<T> { Hidden(ptr::null_mut()) }
+}
 
-pub trait HasFields { type Fields; }
-type Fields<T> = <T as HasFields>::Fields;
 
-pub trait FromFields<Fields> { type Result; }
-type WithFields<T, Fields> = <T as FromFields<Fields>>::Result;
+// ==================
+// === RawRefCast ===
+// ==================
 
+/// Like `RefCast`, but borrows the field from a raw pointer. Used to borrow fields of nested
+/// structs without borrowing the whole struct.
+pub trait RawRefCast<'t, T> {
+    /// # Safety
+    /// The pointer must be valid and the field must not be borrowed elsewhere in a conflicting way
+    /// for the lifetime `'t`.
+    unsafe fn raw_ref_cast(this: *mut Self) -> T;
+}
 
-// =========================
-// === No Access Wrapper ===
-// =========================
+impl<'t, T: 't> RawRefCast<'t, &'t T> for T {
+    #[inline(always)]
+    unsafe fn raw_ref_cast(this: *mut Self) -> &'t T { unsafe { &*this } }
+}
 
-#[repr(transparent)]
-#[derive(Debug)]
-pub struct Hidden<T>(*mut T);
+impl<'t, T: 't> RawRefCast<'t, &'t mut T> for T {
+    #[inline(always)]
+    unsafe fn raw_ref_cast(this: *mut Self) -> &'t mut T { unsafe { &mut *this } }
+}
 
-impl<T> Copy for Hidden<T> {}
-impl<T> Clone for Hidden<T> {
-    fn clone(&self) -> Self { Self(self.0) }
+impl<'t, T> RawRefCast<'t, Hidden<T>> for T {
+    #[inline(always)]
+    unsafe fn raw_ref_cast(this: *mut Self) -> Hidden<T> { Hidden(this) }
 }
 
+impl<'t, T: 't> RawRefCast<'t, Option<&'t T>> for Option<T> {
+    #[inline(always)]
+    unsafe fn raw_ref_cast(this: *mut Self) -> Option<&'t T> { unsafe { (*this).as_ref() } }
+}
 
-// ===============
-// === RefCast ===
-// ===============
+impl<'t, T: 't> RawRefCast<'t, Option<&'t mut T>> for Option<T> {
+    #[inline(always)]
+    unsafe fn raw_ref_cast(this: *mut Self) -> Option<&'t mut T> { unsafe { (*this).as_mut() } }
+}
 
-pub trait RefCast<'t, T> {
-    fn ref_cast(&'t mut self) -> T;
+impl<'t, T> RawRefCast<'t, Hidden<T>> for Option<T> {
+    #[inline(always)]
+    unsafe fn raw_ref_cast(_: *mut Self) -> Hidden<T> { Hidden(ptr::null_mut()) }
 }
 
-impl<'t, T> RefCast<'t, &'t T> for T {
+#[cfg(feature = "std")]
+impl<'t, 'a: 't, T: Clone + 't> RawRefCast<'t, &'t T> for Cow<'a, T> {
     #[inline(always)]
-    fn ref_cast(&'t mut self) -> &'t T { self }
+    unsafe fn raw_ref_cast(this: *mut Self) -> &'t T { unsafe { &*this } }
 }
 
-impl<'t, T> RefCast<'t, &'t mut T> for T {
+#[cfg(feature = "std")]
+impl<'t, 'a: 't, T: Clone + 't> RawRefCast<'t, &'t mut T> for Cow<'a, T> {
     #[inline(always)]
-    fn ref_cast(&'t mut self) -> &'t mut T { self }
+    unsafe fn raw_ref_cast(this: *mut Self) -> &'t mut T { unsafe { (*this).to_mut() } }
 }
 
-impl<'t, T> RefCast<'t, Hidden<T>> for T {
+#[cfg(feature = "std")]
+impl<'t, T: Clone> RawRefCast<'t, Hidden<T>> for Cow<'_, T> {
     #[inline(always)]
-    fn ref_cast(&'t mut self) -> Hidden<T> { Hidden(self) }
+    unsafe fn raw_ref_cast(_: *mut Self) -> Hidden<T> { Hidden(ptr::null_mut()) }
+}
+
+/// Like `AsRefs`, but borrows the fields from a raw pointer to the struct.
+pub trait AsRefsRaw<'t, T> {
+    /// # Safety
+    /// The pointer must be valid and the fields borrowed by `T` must not be borrowed elsewhere in
+    /// a conflicting way for the lifetime `'t`.
+    unsafe fn as_refs_raw(this: *mut Self) -> T;
 }
 
 
@@ -90,11 +1753,102 @@ impl<'t, T> RefCast<'t, Hidden<T>> for T {
 // === Acquire ===
 // ===============
 
+/// Borrows the `Target` slot from the `Self` slot, leaving the `Rest` slot behind. These impls are
+/// the core safety invariants of the crate: a field can't be borrowed mutably from a shared or
+/// hidden slot, and a field borrowed mutably is hidden in the rest. Joining borrows with a field
+/// borrowed mutably in both of them is rejected by `JoinField`.
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// // Error: `nodes` can't be upgraded from a shared to a mutable borrow.
+/// fn push_node(graph: p!(&<nodes> Graph)) {
+///     graph.partial_borrow::<p!(<mut nodes> Graph)>().nodes.push(0);
+/// }
+/// # fn main() {}
+/// ```
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// // Error: `nodes` is hidden in the rest after being borrowed mutably.
+/// fn count_nodes(graph: p!(&<mut nodes> Graph)) -> usize {
+///     let (nodes, rest) = graph.split::<p!(<mut nodes> Graph)>();
+///     nodes.nodes.push(0);
+///     rest.partial_borrow::<p!(<nodes> Graph)>().nodes.len()
+/// }
+/// # fn main() {}
+/// ```
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// // Error: `edges` is not part of the borrow.
+/// fn count_edges(graph: p!(&<mut nodes> Graph)) -> usize {
+///     graph.partial_borrow::<p!(<edges> Graph)>().edges.len()
+/// }
+/// # fn main() {}
+/// ```
 pub trait           Acquire<Target>                  { type Rest; }
+#[allow(clippy::extra_unused_lifetimes)]
 impl<'t, T, S>      Acquire<Hidden<T>> for S         { type Rest = S; }
 impl<'t: 's, 's, T> Acquire<&'s mut T> for &'t mut T { type Rest = Hidden<T>; }
 impl<'t: 's, 's, T> Acquire<&'s     T> for &'t mut T { type Rest = &'t T; }
 impl<'t: 's, 's, T> Acquire<&'s     T> for &'t     T { type Rest = &'t T; }
+impl<'t: 's, 's, T> Acquire<Option<&'s mut T>> for Option<&'t mut T> { type Rest = Hidden<T>; }
+impl<'t: 's, 's, T> Acquire<Option<&'s     T>> for Option<&'t mut T> { type Rest = Option<&'t T>; }
+impl<'t: 's, 's, T> Acquire<Option<&'s     T>> for Option<&'t     T> { type Rest = Self; }
+
+
+// === Nested ===
+
+type RefsMut<'t, T> = <T as HasRefs<'t>>::RefsMut;
+type Refs<'t, T> = <T as HasRefs<'t>>::Refs;
+type PartialBorrowRest<T, Target> = <T as PartialBorrow<Target>>::Rest;
+
+impl<'t, T, Target> Acquire<Nested<T, Target>> for &'t mut T where
+    T: HasRefs<'t>,
+    RefsMut<'t, T>: PartialBorrow<Target> {
+    type Rest = Nested<T, PartialBorrowRest<RefsMut<'t, T>, Target>>;
+}
+
+impl<'t, T, Target> Acquire<Nested<T, Target>> for &'t T where
+    T: HasRefs<'t>,
+    Refs<'t, T>: PartialBorrow<Target> {
+    type Rest = Self;
+}
+
+impl<T, Source, Target> Acquire<Nested<T, Target>> for Nested<T, Source> where
+    Source: PartialBorrow<Target> {
+    type Rest = Nested<T, PartialBorrowRest<Source, Target>>;
+}
 
 pub type Acquired<This, Target> = <This as Acquire<Target>>::Rest;
 
@@ -106,6 +1860,7 @@ pub type Acquired<This, Target> = <This as Acquire<Target>>::Rest;
 pub trait SplitFields<Target> { type Rest; }
 type SplitFieldsRest<T, Target> = <T as SplitFields<Target>>::Rest;
 
+#[allow(clippy::use_self)]
 impl SplitFields<Nil> for Nil {
     type Rest = Nil;
 }
@@ -121,7 +1876,55 @@ T: SplitFields<T2> {
 // === PartialBorrow ===
 // =====================
 
+/// Borrows `Target` from `Self` by casting the pointer to the ref struct. This is sound as both are
+/// instantiations of the same `repr(C)` ref struct, in which every slot is a thin pointer to a sized
+/// field, and the bounds of the impl allow only slots borrowing the fields with the same or reduced
+/// access. The derive asserts at compile time that all instantiations of the ref struct have the
+/// same layout.
+///
+/// The structs returned by the generated `map_$field` methods can hold any type in the mapped
+/// field, so they are not ref structs and can't be borrowed from:
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// fn forge_edges(graph: &mut Graph) {
+///     let mut refs = graph.as_refs_mut();
+///     let mut mapped = refs.map_nodes(|n| (n.len(), 0xdead_usize, 0xbeef_usize));
+///     let _edges = mapped.partial_borrow::<p!(<mut edges> Graph)>();
+/// }
+/// # fn main() {}
+/// ```
 pub trait PartialBorrow<Target> {
+    /// The fields left after borrowing `Target`. Fields borrowed mutably by `Target` are hidden in
+    /// it, so they can't be borrowed from the rest again, e.g. by `extract_$field_as`.
+    ///
+    /// ```compile_fail,E0277
+    /// use borrow::PartialBorrow;
+    /// use borrow::partial_borrow as p;
+    /// use borrow::traits::*;
+    ///
+    /// #[derive(PartialBorrow)]
+    /// #[module(crate)]
+    /// struct Graph {
+    ///     nodes: Vec<usize>,
+    ///     edges: Vec<usize>,
+    /// }
+    ///
+    /// fn extract_nodes_twice(graph: p!(&<mut *> Graph)) {
+    ///     let _ = graph.extract_nodes_as::<p!(<nodes, mut edges> Graph)>();
+    /// }
+    /// # fn main() {}
+    /// ```
     type Rest;
 
     #[inline(always)]
@@ -151,18 +1954,171 @@ Target: FromFields<SplitFieldsRest<Fields<Source>, Fields<Target>>> {
 }
 
 impl<T> PartialBorrowHelper for T {}
+/// Borrowing and splitting methods of partial borrows. The borrows they return are `#[must_use]`,
+/// as are the ones returned by the generated `extract_$field` methods, since discarding them is
+/// almost always a bug:
+///
+/// ```compile_fail
+/// #![deny(unused_must_use)]
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// fn detach(graph: p!(&<mut *> Graph)) {
+///     // Error: The returned partial borrow must be used.
+///     graph.extract_nodes();
+/// }
+/// # fn main() {}
+/// ```
 pub trait PartialBorrowHelper {
     #[inline(always)]
+    #[must_use = "the returned partial borrow must be used"]
     fn partial_borrow<Target>(&mut self) -> &mut Target
-    where Self: PartialBorrow<Target> { self.partial_borrow_impl() }
+    where Self: PartialBorrow<Target> {
+        trace::record::<Target>("partial_borrow");
+        self.partial_borrow_impl()
+    }
+
+    /// Borrows the union of fields required by both targets, like
+    /// `ctx.partial_borrow_union::<p!(<mut geometry> Ctx), p!(<mut mesh> Ctx)>()`.
+    #[inline(always)]
+    #[must_use = "the returned partial borrow must be used"]
+    fn partial_borrow_union<Target1, Target2>(&mut self) -> &mut Union<Target1, Target2>
+    where Target1: Unify<Target2>, Self: PartialBorrow<Union<Target1, Target2>> {
+        self.partial_borrow_impl()
+    }
+
+    /// Borrows the same fields with the same or reduced mutability, like
+    /// `graph.downgrade::<p!(<nodes, mut edges> Graph)>()` for `p!(&<mut nodes, mut edges> Graph)`.
+    /// In contrast to `partial_borrow`, it fails to compile if the target borrows a different set
+    /// of fields:
+    ///
+    /// ```compile_fail,E0277
+    /// use borrow::PartialBorrow;
+    /// use borrow::partial_borrow as p;
+    /// use borrow::traits::*;
+    ///
+    /// #[derive(PartialBorrow)]
+    /// #[module(crate)]
+    /// struct Graph {
+    ///     nodes: Vec<usize>,
+    ///     edges: Vec<usize>,
+    /// }
+    ///
+    /// fn downgrade_nodes(graph: p!(&<mut nodes, edges> Graph)) {
+    ///     let _ = graph.downgrade::<p!(<nodes> Graph)>();
+    /// }
+    /// # fn main() {}
+    /// ```
+    #[inline(always)]
+    #[must_use = "the returned partial borrow must be used"]
+    fn downgrade<Target>(&mut self) -> &mut Target
+    where Self: PartialBorrow<Target> + DowngradeFields<Target> { self.partial_borrow_impl() }
+
+    /// Clones the fields borrowed by `Target` to a ref struct of owned values, like
+    /// `graph.clone_fields::<p!(<nodes> Graph)>()`, which returns `GraphRef<Vec<Node>, Hidden<..>>`.
+    /// Hidden fields are excluded from the owned output and stay `Hidden`.
+    #[inline(always)]
+    fn clone_fields<Target>(&mut self) -> OwnedFields<Target>
+    where Self: PartialBorrow<Target>, Target: CloneFields {
+        self.partial_borrow_impl().clone_fields_impl()
+    }
 
     #[inline(always)]
+    #[must_use = "the returned partial borrow must be used"]
     fn partial_borrow_rest<Target>(&mut self) -> &mut Self::Rest
     where Self: PartialBorrow<Target> { self.partial_borrow_rest_impl() }
 
+    /// Borrows the fields of the struct `S` declared with `#[borrow(subset_of = ...)]`, like
+    /// `ctx.project::<RenderCtx>()`. Only the struct needs to be named, the borrow of the superset
+    /// and the returned borrow are inferred from it.
     #[inline(always)]
+    #[must_use = "the returned partial borrow must be used"]
+    fn project<'t, S>(&'t mut self) -> S::Refs
+    where Self: Sized, S: Subset<'t, Self> { S::project(self) }
+
+    #[inline(always)]
+    #[must_use = "the returned partial borrow must be used"]
     fn split<Target>(&mut self) -> (&mut Target, &mut Self::Rest)
-    where Self: PartialBorrow<Target> { self.split_impl() }
+    where Self: PartialBorrow<Target> {
+        trace::record::<Target>("split");
+        self.split_impl()
+    }
+
+    /// Like `split`, but borrows two disjoint sets of fields at once and returns them along with
+    /// the rest of the fields. Fields borrowed mutably by the first target can't be borrowed by
+    /// the second one.
+    ///
+    /// ```compile_fail,E0277
+    /// use borrow::PartialBorrow;
+    /// use borrow::partial_borrow as p;
+    /// use borrow::traits::*;
+    ///
+    /// #[derive(PartialBorrow)]
+    /// #[module(crate)]
+    /// struct Graph {
+    ///     nodes: Vec<usize>,
+    ///     edges: Vec<usize>,
+    /// }
+    ///
+    /// fn split_nodes_twice(graph: p!(&<mut *> Graph)) {
+    ///     let _ = graph.split3::<p!(<mut nodes> Graph), p!(<nodes> Graph)>();
+    /// }
+    /// # fn main() {}
+    /// ```
+    #[inline(always)]
+    #[must_use = "the returned partial borrow must be used"]
+    #[allow(clippy::type_complexity)]
+    fn split3<Target1, Target2>(&mut self) -> (
+        &mut Target1,
+        &mut Target2,
+        &mut <<Self as PartialBorrow<Target1>>::Rest as PartialBorrow<Target2>>::Rest
+    ) where
+        Self: PartialBorrow<Target1>,
+        <Self as PartialBorrow<Target1>>::Rest: PartialBorrow<Target2> {
+        let (target1, rest) = self.split_impl();
+        let (target2, rest) = rest.split_impl();
+        (target1, target2, rest)
+    }
+
+    /// Borrows the same fields for the shorter lifetime `'t`. This is the idiomatic way to pass
+    /// the same borrow to a function called repeatedly, e.g. in a loop.
+    #[inline(always)]
+    #[must_use = "the returned partial borrow must be used"]
+    fn reborrow<'t>(&'t mut self) -> &'t mut <Self as RefFlatten<'t>>::Output
+    where Self: RefFlatten<'t> + PartialBorrow<<Self as RefFlatten<'t>>::Output> {
+        self.partial_borrow_impl()
+    }
+
+    #[inline(always)]
+    #[must_use = "the returned partial borrow must be used"]
+    fn extract_fields<'t, Target>(&'t mut self) -> (<Target as RefFlatten<'t>>::Output, &'t mut Self::Rest)
+    where Self: PartialBorrow<Target>, Target: RefFlatten<'t> + 't {
+        let (a, b) = self.split_impl();
+        (a.ref_flatten(), b)
+    }
+
+    /// Like `split`, but returns both parts by value, borrowing the fields for `'t`. In contrast
+    /// to the references returned by `split`, the parts are independent of each other and of
+    /// `self`, so they can be stored in different structs, or one of them can be returned from a
+    /// function while the caller keeps the other one.
+    #[inline(always)]
+    #[must_use = "the returned partial borrow must be used"]
+    #[allow(clippy::type_complexity)]
+    fn split_with<'t, Target>(&'t mut self) -> (
+        <Target as RefFlatten<'t>>::Output,
+        <Self::Rest as RefFlatten<'t>>::Output
+    ) where Self: PartialBorrow<Target>, Target: RefFlatten<'t> + 't, Self::Rest: RefFlatten<'t> + 't {
+        let (a, b) = self.split_impl();
+        (a.ref_flatten(), b.ref_flatten())
+    }
 }
 
 
@@ -170,19 +2126,35 @@ pub trait PartialBorrowHelper {
 // === UnifyField ===
 // ==================
 
+/// The borrow slot borrowing a field as required by both `Self` and `Other`. If both borrow the
+/// field, the result has the lifetime of `Other`, which `Self` needs to outlive, so that unifying
+/// `&'a T` with `&'b T` yields `&'b T` if `'a: 'b`. As the shorter lifetime can't be computed at the
+/// type level, the shorter-lived borrow needs to be the second one.
 pub trait UnifyField<Other> { type Result; }
 
-impl<'t, T> UnifyField<Hidden<T>> for Hidden<T> { type Result = Hidden<T>; }
-impl<'t, T> UnifyField<&'t     T> for Hidden<T> { type Result = &'t     T; }
-impl<'t, T> UnifyField<&'t mut T> for Hidden<T> { type Result = &'t mut T; }
+#[allow(clippy::use_self)]
+impl<T>             UnifyField<Hidden<T>> for Hidden<T> { type Result = Hidden<T>; }
+impl<'b, T>         UnifyField<&'b     T> for Hidden<T> { type Result = &'b     T; }
+impl<'b, T>         UnifyField<&'b mut T> for Hidden<T> { type Result = &'b mut T; }
+
+impl<'a, T>         UnifyField<Hidden<T>> for &'a T { type Result = &'a     T; }
+impl<'a: 'b, 'b, T> UnifyField<&'b     T> for &'a T { type Result = &'b     T; }
+impl<'a: 'b, 'b, T> UnifyField<&'b mut T> for &'a T { type Result = &'b mut T; }
 
-impl<'t, T> UnifyField<Hidden<T>> for &'t T { type Result = &'t     T; }
-impl<'t, T> UnifyField<&'t     T> for &'t T { type Result = &'t     T; }
-impl<'t, T> UnifyField<&'t mut T> for &'t T { type Result = &'t mut T; }
+impl<'a, T>         UnifyField<Hidden<T>> for &'a mut T { type Result = &'a mut T; }
+impl<'a: 'b, 'b, T> UnifyField<&'b     T> for &'a mut T { type Result = &'b mut T; }
+impl<'a: 'b, 'b, T> UnifyField<&'b mut T> for &'a mut T { type Result = &'b mut T; }
 
-impl<'t, T> UnifyField<Hidden<T>> for &'t mut T { type Result = &'t mut T; }
-impl<'t, T> UnifyField<&'t     T> for &'t mut T { type Result = &'t mut T; }
-impl<'t, T> UnifyField<&'t mut T> for &'t mut T { type Result = &'t mut T; }
+impl<'b, T>         UnifyField<Option<&'b     T>> for Hidden<T> { type Result = Option<&'b     T>; }
+impl<'b, T>         UnifyField<Option<&'b mut T>> for Hidden<T> { type Result = Option<&'b mut T>; }
+
+impl<T>             UnifyField<Hidden<T>>         for Option<&T> { type Result = Self; }
+impl<'a: 'b, 'b, T> UnifyField<Option<&'b     T>> for Option<&'a T> { type Result = Option<&'b     T>; }
+impl<'a: 'b, 'b, T> UnifyField<Option<&'b mut T>> for Option<&'a T> { type Result = Option<&'b mut T>; }
+
+impl<T>             UnifyField<Hidden<T>>         for Option<&mut T> { type Result = Self; }
+impl<'a: 'b, 'b, T> UnifyField<Option<&'b     T>> for Option<&'a mut T> { type Result = Option<&'b mut T>; }
+impl<'a: 'b, 'b, T> UnifyField<Option<&'b mut T>> for Option<&'a mut T> { type Result = Option<&'b mut T>; }
 
 type ConcatenatedField<T, Other> = <T as UnifyField<Other>>::Result;
 
@@ -194,6 +2166,7 @@ type ConcatenatedField<T, Other> = <T as UnifyField<Other>>::Result;
 pub trait UnifyFields<Other> { type Result; }
 type ConcatFieldsResult<T, Other> = <T as UnifyFields<Other>>::Result;
 
+#[allow(clippy::use_self)]
 impl UnifyFields<Nil> for Nil {
     type Result = Nil;
 }
@@ -204,6 +2177,8 @@ impl<H, H2, T, T2> UnifyFields<Cons<H2, T2>> for Cons<H, T> where
     type Result = Cons<ConcatenatedField<H, H2>, <T as UnifyFields<T2>>::Result>;
 }
 
+/// The type-level union of two partial borrows, borrowing every field as required by any of them.
+/// See `UnifyField` for the rules of unifying a single field. Use `Union` to name the result.
 pub trait Unify<Other> {
     type Result;
 }
@@ -216,6 +2191,9 @@ impl<Source, Other> Unify<Other> for Source where
     type Result = WithFields<Other, ConcatFieldsResult<Fields<Source>, Fields<Other>>>;
 }
 
+/// The partial borrow borrowing the union of fields of `T` and `Other`, e.g.,
+/// `Union<p!(<scene> Ctx), p!(<mut mesh> Ctx)>`. This is the type of the value returned by
+/// `a.union(b)` and `a.join(b)` for `a: &mut T` and `b: &mut Other`.
 pub type Union<T, Other> = <T as Unify<Other>>::Result;
 
 
@@ -233,18 +2211,22 @@ pub trait UnifyFieldImpl<'t, Other> {
 
 // === for Hidden<T> ===
 
+#[allow(clippy::use_self)]
 impl<'t, T> UnifyFieldImpl<'t, Hidden<T>> for Hidden<T> {
     type Result = Hidden<T>;
+    #[inline(always)]
     fn unify_field(&'t mut self, _: &'t mut Hidden<T>) -> Self::Result { *self }
 }
 
 impl<'t, 's, T> UnifyFieldImpl<'t, &'s T> for Hidden<T> {
     type Result = &'s T;
+    #[inline(always)]
     fn unify_field(&'t mut self, other: &'t mut &'s T) -> Self::Result { other }
 }
 
 impl<'t, 's, T: 't> UnifyFieldImpl<'t, &'s mut T> for Hidden<T> {
     type Result = &'t mut T;
+    #[inline(always)]
     fn unify_field(&'t mut self, other: &'t mut &'s mut T) -> Self::Result { other }
 }
 
@@ -252,41 +2234,106 @@ impl<'t, 's, T: 't> UnifyFieldImpl<'t, &'s mut T> for Hidden<T> {
 
 impl<'t, 's, T> UnifyFieldImpl<'t, Hidden<T>> for &'s T {
     type Result = &'s T;
+    #[inline(always)]
     fn unify_field(&'t mut self, _: &'t mut Hidden<T>) -> Self::Result { self }
 }
 
 impl<'t, 's, T> UnifyFieldImpl<'t, &'s T> for &'s T {
     type Result = &'s T;
+    #[inline(always)]
     fn unify_field(&'t mut self, _: &'t mut &'s T) -> Self::Result { self }
 }
 
 impl<'t, 's, T: 't> UnifyFieldImpl<'t, &'s mut T> for &'s T {
     type Result = &'t mut T;
+    #[inline(always)]
     fn unify_field(&'t mut self, other: &'t mut &'s mut T) -> Self::Result { other }
 }
 
 // === for &'s mut T ===
 
+#[allow(clippy::needless_lifetimes)]
 impl<'t, 's, T: 't> UnifyFieldImpl<'t, Hidden<T>> for &'s mut T {
     type Result = &'t mut T;
+    #[inline(always)]
     fn unify_field(&'t mut self, _: &'t mut Hidden<T>) -> Self::Result { self }
 }
 
 impl<'t, 's, T: 't> UnifyFieldImpl<'t, &'s T> for &'s mut T {
     type Result = &'t mut T;
+    #[inline(always)]
     fn unify_field(&'t mut self, _: &'t mut &'s T) -> Self::Result { self }
 }
 
 impl<'t, 's, T: 't> UnifyFieldImpl<'t, &'s mut T> for &'s mut T {
     type Result = &'t mut T;
+    #[inline(always)]
     fn unify_field(&'t mut self, _: &'t mut &'s mut T) -> Self::Result { self }
 }
 
+// === for Option<&'s T> ===
+
+impl<'t, 's, T> UnifyFieldImpl<'t, Option<&'s T>> for Hidden<T> {
+    type Result = Option<&'s T>;
+    #[inline(always)]
+    fn unify_field(&'t mut self, other: &'t mut Option<&'s T>) -> Self::Result { *other }
+}
+
+impl<'t, T> UnifyFieldImpl<'t, Hidden<T>> for Option<&T> {
+    type Result = Self;
+    #[inline(always)]
+    fn unify_field(&'t mut self, _: &'t mut Hidden<T>) -> Self::Result { *self }
+}
+
+impl<'t, T> UnifyFieldImpl<'t, Self> for Option<&T> {
+    type Result = Self;
+    #[inline(always)]
+    fn unify_field(&'t mut self, _: &'t mut Self) -> Self::Result { *self }
+}
+
+impl<'t, 's, T: 't> UnifyFieldImpl<'t, Option<&'s mut T>> for Option<&'s T> {
+    type Result = Option<&'t mut T>;
+    #[inline(always)]
+    fn unify_field(&'t mut self, other: &'t mut Option<&'s mut T>) -> Self::Result {
+        other.as_deref_mut()
+    }
+}
+
+// === for Option<&'s mut T> ===
+
+impl<'t, 's, T: 't> UnifyFieldImpl<'t, Option<&'s mut T>> for Hidden<T> {
+    type Result = Option<&'t mut T>;
+    #[inline(always)]
+    fn unify_field(&'t mut self, other: &'t mut Option<&'s mut T>) -> Self::Result {
+        other.as_deref_mut()
+    }
+}
+
+impl<'t, T: 't> UnifyFieldImpl<'t, Hidden<T>> for Option<&mut T> {
+    type Result = Option<&'t mut T>;
+    #[inline(always)]
+    fn unify_field(&'t mut self, _: &'t mut Hidden<T>) -> Self::Result { self.as_deref_mut() }
+}
+
+impl<'t, 's, T: 't> UnifyFieldImpl<'t, Option<&'s T>> for Option<&'s mut T> {
+    type Result = Option<&'t mut T>;
+    #[inline(always)]
+    fn unify_field(&'t mut self, _: &'t mut Option<&'s T>) -> Self::Result { self.as_deref_mut() }
+}
+
+impl<'t, T: 't> UnifyFieldImpl<'t, Self> for Option<&mut T> {
+    type Result = Option<&'t mut T>;
+    #[inline(always)]
+    fn unify_field(&'t mut self, _: &'t mut Self) -> Self::Result { self.as_deref_mut() }
+}
+
 
 // =================
 // === UnifyImpl ===
 // =================
 
+/// The value-level union of two partial borrows. See `Join` for a variant rejecting fields borrowed
+/// mutably in both borrows.
 pub trait UnifyImpl<Other> {
     type Result;
     fn union(self, other: Other) -> Self::Result;
@@ -297,6 +2344,525 @@ pub trait UnifyImpl<Other> {
 pub type UnionImpl<T, Other> = <T as UnifyImpl<Other>>::Result;
 
 
+// =================
+// === JoinField ===
+// =================
+
+/// Checks whether two borrows of the same field can be joined. Joining a field borrowed mutably
+/// on both sides is not allowed.
+#[diagnostic::on_unimplemented(
+    message = "cannot join `{Self}` with `{Other}`",
+    label = "the same field is borrowed mutably in both borrows"
+)]
+pub trait JoinField<Other> {}
+
+#[allow(clippy::use_self)]
+impl<T> JoinField<Hidden<T>> for Hidden<T> {}
+impl<T> JoinField<&T>        for Hidden<T> {}
+impl<T> JoinField<&mut T>    for Hidden<T> {}
+
+impl<T> JoinField<Hidden<T>> for &T {}
+impl<T> JoinField<&T>        for &T {}
+impl<T> JoinField<&mut T>    for &T {}
+
+impl<T> JoinField<Hidden<T>> for &mut T {}
+impl<T> JoinField<&T>        for &mut T {}
+
+impl<T> JoinField<Option<&T>>     for Hidden<T> {}
+impl<T> JoinField<Option<&mut T>> for Hidden<T> {}
+
+impl<T> JoinField<Hidden<T>>      for Option<&T> {}
+impl<T> JoinField<Option<&T>>     for Option<&T> {}
+impl<T> JoinField<Option<&mut T>> for Option<&T> {}
+
+impl<T> JoinField<Hidden<T>>      for Option<&mut T> {}
+impl<T> JoinField<Option<&T>>     for Option<&mut T> {}
+
+pub trait JoinFields<Other> {}
+
+impl JoinFields<Self> for Nil {}
+
+impl<H, H2, T, T2> JoinFields<Cons<H2, T2>> for Cons<H, T> where
+    H: JoinField<H2>,
+    T: JoinFields<T2> {}
+
+
+// ============
+// === Join ===
+// ============
+
+/// Joins two partial borrows into one borrowing the union of their fields. In contrast to
+/// `UnifyImpl::union`, fields borrowed mutably in both borrows are rejected at compile time.
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// fn join_nodes_twice(a: p!(&<mut nodes> Graph), b: p!(&<mut nodes, edges> Graph)) {
+///     let _joined = a.join(b);
+/// }
+/// # fn main() {}
+/// ```
+pub trait Join<Other> {
+    type Result;
+    fn join(self, other: Other) -> Self::Result;
+}
+
+pub type Joined<T, Other> = <T as Join<Other>>::Result;
+
+impl<'t, T, Other> Join<&'t mut Other> for &'t mut T where
+    Self: UnifyImpl<&'t mut Other>,
+    T: HasFields,
+    Other: HasFields,
+    Fields<T>: JoinFields<Fields<Other>> {
+    type Result = UnionImpl<Self, &'t mut Other>;
+    #[inline(always)]
+    fn join(self, other: &'t mut Other) -> Self::Result { self.union(other) }
+}
+
+
+// ==============
+// === Rejoin ===
+// ==============
+
+/// Joins the two parts returned by `split` back into one borrow, the inverse of `split`, like
+/// `nodes.rejoin::<p!(<mut *> Graph)>(rest)`. The check is done on types only: it fails to compile
+/// if the field shapes of the parts do not match a split of `Source`, or if they overlap. Parts
+/// of two different splits with matching shapes are accepted.
+///
+/// ```compile_fail,E0271
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::traits::*;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// fn rejoin_wrong_rest(graph: p!(&<mut *> Graph)) {
+///     let (nodes, _) = graph.split::<p!(<mut nodes> Graph)>();
+///     let mut other = Graph { nodes: vec![], edges: vec![] };
+///     let mut other_refs = other.as_refs_mut();
+///     let other_rest = other_refs.partial_borrow::<p!(<mut nodes> Graph)>();
+///     let _ = nodes.rejoin::<p!(<mut *> Graph)>(other_rest);
+/// }
+/// # fn main() {}
+/// ```
+pub trait Rejoin<'t, Rest>: Sized {
+    #[allow(clippy::type_complexity)]
+    fn rejoin<Source>(&'t mut self, rest: &'t mut Rest) -> Joined<&'t mut Self, &'t mut Rest>
+    where
+        Source: PartialBorrow<Self, Rest = Rest>,
+        &'t mut Self: Join<&'t mut Rest>;
+}
+
+impl<'t, T, Rest> Rejoin<'t, Rest> for T {
+    #[inline(always)]
+    fn rejoin<Source>(&'t mut self, rest: &'t mut Rest) -> Joined<&'t mut Self, &'t mut Rest>
+    where
+        Source: PartialBorrow<Self, Rest = Rest>,
+        &'t mut Self: Join<&'t mut Rest> {
+        self.join(rest)
+    }
+}
+
+
+// ==================
+// === SameFields ===
+// ==================
+
+/// Checks whether two borrows of the same field are of the same kind, ignoring their lifetimes.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` and `{Other}` borrow a field differently",
+    label = "the field is borrowed differently in both borrows"
+)]
+pub trait SameField<Other> {}
+
+#[allow(clippy::use_self)]
+impl<T> SameField<Hidden<T>> for Hidden<T> {}
+impl<T> SameField<&T>        for &T {}
+impl<T> SameField<&mut T>    for &mut T {}
+impl<T> SameField<Option<&T>>     for Option<&T> {}
+impl<T> SameField<Option<&mut T>> for Option<&mut T> {}
+impl<T, Target: SameFields<Other>, Other> SameField<Nested<T, Other>> for Nested<T, Target> {}
+
+pub trait SameFieldList<Other> {}
+
+impl SameFieldList<Self> for Nil {}
+
+impl<H, H2, T, T2> SameFieldList<Cons<H2, T2>> for Cons<H, T> where
+    H: SameField<H2>,
+    T: SameFieldList<T2> {}
+
+/// Implemented if both partial borrows borrow the same fields in the same way, ignoring the
+/// lifetimes. See the `assert_same_fields!` macro.
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// borrow::assert_same_fields!(p!(<mut nodes, edges> Graph), p!(<mut nodes, mut edges> Graph));
+/// # fn main() {}
+/// ```
+pub trait SameFields<Other> {}
+
+impl<T, Other> SameFields<Other> for T where
+    T: HasFields,
+    Other: HasFields,
+    Fields<T>: SameFieldList<Fields<Other>> {}
+
+
+// =======================
+// === DowngradeFields ===
+// =======================
+
+/// Checks whether a borrow of a field can be downgraded to `Other`, i.e. whether `Other` borrows
+/// the same field with the same or reduced mutability, ignoring the lifetimes.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be downgraded to `{Other}`",
+    label = "the field is added, removed, or borrowed mutably in the target"
+)]
+pub trait DowngradeField<Other> {}
+
+#[allow(clippy::use_self)]
+impl<T> DowngradeField<Hidden<T>> for Hidden<T> {}
+impl<T> DowngradeField<&T>        for &T {}
+impl<T> DowngradeField<&T>        for &mut T {}
+impl<T> DowngradeField<&mut T>    for &mut T {}
+impl<T> DowngradeField<Option<&T>>     for Option<&T> {}
+impl<T> DowngradeField<Option<&T>>     for Option<&mut T> {}
+impl<T> DowngradeField<Option<&mut T>> for Option<&mut T> {}
+impl<T, Target: DowngradeFields<Other>, Other> DowngradeField<Nested<T, Other>> for Nested<T, Target> {}
+
+pub trait DowngradeFieldList<Other> {}
+
+impl DowngradeFieldList<Self> for Nil {}
+
+impl<H, H2, T, T2> DowngradeFieldList<Cons<H2, T2>> for Cons<H, T> where
+    H: DowngradeField<H2>,
+    T: DowngradeFieldList<T2> {}
+
+/// Implemented if `Other` borrows the same fields as `Self` with the same or reduced mutability.
+/// See [`PartialBorrowHelper::downgrade`].
+pub trait DowngradeFields<Other> {}
+
+impl<T, Other> DowngradeFields<Other> for T where
+    T: HasFields,
+    Other: HasFields,
+    Fields<T>: DowngradeFieldList<Fields<Other>> {}
+
+
+// ======================
+// === DisjointFields ===
+// ======================
+
+/// Checks whether two borrows of the same field can exist at the same time, i.e. whether the field
+/// is hidden in one of them, or borrowed immutably in both, ignoring the lifetimes.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` and `{Other}` are not disjoint",
+    label = "the field is borrowed mutably in one borrow and borrowed in the other one"
+)]
+pub trait DisjointField<Other> {}
+
+#[allow(clippy::use_self)]
+impl<T> DisjointField<Hidden<T>>         for Hidden<T> {}
+impl<T> DisjointField<&T>                for Hidden<T> {}
+impl<T> DisjointField<&mut T>            for Hidden<T> {}
+impl<T> DisjointField<Option<&T>>        for Hidden<T> {}
+impl<T> DisjointField<Option<&mut T>>    for Hidden<T> {}
+impl<T, Target> DisjointField<Nested<T, Target>> for Hidden<T> {}
+
+impl<T> DisjointField<Hidden<T>>         for &T {}
+impl<T> DisjointField<&T>                for &T {}
+impl<T> DisjointField<Hidden<T>>         for &mut T {}
+impl<T> DisjointField<Hidden<T>>         for Option<&T> {}
+impl<T> DisjointField<Option<&T>>        for Option<&T> {}
+impl<T> DisjointField<Hidden<T>>         for Option<&mut T> {}
+impl<T, Target> DisjointField<Hidden<T>> for Nested<T, Target> {}
+impl<T, Target: DisjointFields<Other>, Other> DisjointField<Nested<T, Other>> for Nested<T, Target> {}
+
+pub trait DisjointFieldList<Other> {}
+
+impl DisjointFieldList<Self> for Nil {}
+
+impl<H, H2, T, T2> DisjointFieldList<Cons<H2, T2>> for Cons<H, T> where
+    H: DisjointField<H2>,
+    T: DisjointFieldList<T2> {}
+
+/// Implemented if both partial borrows can be created from the same struct at the same time, i.e.
+/// every field borrowed mutably by one of them is hidden in the other one. See the
+/// `assert_disjoint!` macro.
+///
+/// ```compile_fail,E0277
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// borrow::assert_disjoint!(p!(<mut nodes, edges> Graph), p!(<mut edges> Graph));
+/// # fn main() {}
+/// ```
+pub trait DisjointFields<Other> {}
+
+impl<T, Other> DisjointFields<Other> for T where
+    T: HasFields,
+    Other: HasFields,
+    Fields<T>: DisjointFieldList<Fields<Other>> {}
+
+// ==============
+// === Subset ===
+// ==============
+
+/// Struct declared with `#[borrow(subset_of = ...)]`, whose fields can be borrowed from `Source`,
+/// a partial borrow of the superset borrowing all fields of this struct mutably. Generated by the
+/// derive. See [`PartialBorrowHelper::project`].
+pub trait Subset<'t, Source> {
+    type Refs;
+    fn project(source: &'t mut Source) -> Self::Refs;
+}
+
+
+// ====================
+// === FieldOffsets ===
+// ====================
+
+/// Byte offsets of the fields of the original struct, in the same order as `HasFieldTypes`.
+/// Generated by the derive for structs without `#[borrow(deref)]` fields.
+///
+/// # Safety
+/// The `N`-th offset must be the offset of a field of type `FieldAt<N, Self>` inside `Self`.
+pub unsafe trait FieldOffsets {
+    const FIELD_OFFSETS: &'static [usize];
+}
+
+
+// ==================
+// === DynRefsMut ===
+// ==================
+
+/// Runtime-checked mutable borrows of the fields of `S`, for cases where the set of required
+/// fields is only known at runtime. Fields are selected by a bitmask of their indices (see
+/// [`FieldIndex`]), and a selection overlapping a live one is refused.
+///
+/// ```
+/// use borrow::DynRefsMut;
+/// use borrow::FieldIndex;
+///
+/// #[derive(borrow::PartialBorrow)]
+/// #[module(crate)]
+/// #[borrow(field_markers = ctx_fields)]
+/// struct Ctx {
+///     a: Vec<usize>,
+///     b: Vec<usize>,
+/// }
+///
+/// let mut ctx = Ctx { a: vec![], b: vec![] };
+/// let refs = DynRefsMut::new(&mut ctx);
+/// let a_bit = 1 << <Ctx as FieldIndex<ctx_fields::a>>::INDEX;
+/// let mut a = refs.borrow_mut(a_bit).unwrap();
+/// assert!(refs.borrow_mut(a_bit).is_none());
+/// assert!(a.get_mut::<ctx_fields::b>().is_none());
+/// a.get_mut::<ctx_fields::a>().unwrap().push(1);
+/// drop(a);
+/// assert!(refs.borrow_mut(a_bit).is_some());
+/// ```
+///
+/// # Safety invariants
+/// - `DynRefsMut` is created from `&'t mut S`, so nothing else accesses the struct for `'t`.
+/// - The `borrowed` mask holds the fields lent to live [`DynBorrowMut`]s. A selection overlapping
+///   it is refused, so the fields lent to different `DynBorrowMut`s are disjoint. Dropping a
+///   `DynBorrowMut` returns its fields; leaking one keeps them borrowed, which is safe.
+/// - The fields are accessed through pointers computed from the pointer to the struct and
+///   [`FieldOffsets`], so no reference to the whole struct is created while fields are lent.
+/// - [`DynBorrowMut::get_mut`] borrows the `DynBorrowMut` mutably, so it lends at most one
+///   reference to each of its fields at a time.
+/// - The mask is not atomic, so `DynRefsMut` is neither `Send` nor `Sync`.
+///
+/// # FFI
+/// Code that can't express the borrowed fields in types, like a C API, can borrow the fields by
+/// name with [`DynRefsMut::get_field_mut`], which returns a type-erased pointer. The pointers are
+/// computed from the pointer to the whole struct, so `DynRefsMut` is created from `&mut S`, not
+/// from a partial borrow, whose field references can't be widened back to the struct:
+///
+/// ```
+/// use borrow::DynRefsMut;
+/// use borrow::PartialBorrow;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Ctx {
+///     a: Vec<usize>,
+///     b: usize,
+/// }
+///
+/// fn export(ctx: &mut Ctx) {
+///     let refs = DynRefsMut::new(ctx);
+///     let b = refs.get_field_mut("b").unwrap();
+///     assert!(refs.get_field_mut("b").is_none());
+///     // SAFETY: The `b` field is a `usize` lent by name, and it is not released yet.
+///     unsafe { *b.cast::<usize>().as_ptr() = 1 };
+///     assert!(refs.release_field("b"));
+/// }
+/// # fn main() {
+/// #     let mut ctx = Ctx { a: vec![], b: 0 };
+/// #     export(&mut ctx);
+/// #     assert_eq!(ctx.b, 1);
+/// # }
+/// ```
+///
+/// The fields lent by name are tracked in the `lent` mask, which is a subset of the `borrowed`
+/// mask. The returned pointer can be used as a mutable reference to the field until
+/// [`DynRefsMut::release_field`] is called for it, or until the `DynRefsMut` is dropped. Only the
+/// fields in the `lent` mask can be released, so releasing a field never invalidates a reference
+/// lent by a [`DynBorrowMut`].
+pub struct DynRefsMut<'t, S> {
+    this: *mut S,
+    borrowed: Cell<u64>,
+    lent: Cell<u64>,
+    _marker: PhantomData<&'t mut S>,
+}
+
+impl<'t, S: FieldOffsets> DynRefsMut<'t, S> {
+    #[inline(always)]
+    pub fn new(this: &'t mut S) -> Self {
+        Self { this: ptr::from_mut(this), borrowed: Cell::new(0), lent: Cell::new(0), _marker: PhantomData }
+    }
+
+    /// Mask of the fields of `S`.
+    #[inline(always)]
+    pub fn fields_mask() -> u64 {
+        let count = S::FIELD_OFFSETS.len();
+        if count >= 64 { u64::MAX } else { (1 << count) - 1 }
+    }
+
+    /// Mask of the fields currently lent to live [`DynBorrowMut`]s.
+    #[inline(always)]
+    pub fn borrowed_mask(&self) -> u64 {
+        self.borrowed.get()
+    }
+
+    /// Borrows the fields selected by `mask` mutably. Returns `None` if any of them is already
+    /// borrowed, or if `mask` selects a non-existing field.
+    #[inline(always)]
+    pub fn borrow_mut(&self, mask: u64) -> Option<DynBorrowMut<'_, 't, S>> {
+        let borrowed = self.borrowed.get();
+        if mask & !Self::fields_mask() != 0 || mask & borrowed != 0 { return None }
+        self.borrowed.set(borrowed | mask);
+        Some(DynBorrowMut { refs: self, mask })
+    }
+
+    /// Lends the field with the given name, returning a pointer to it. Returns `None` if there is
+    /// no such field or if it is already borrowed. The pointer is valid for reads and writes of
+    /// the field until the field is released with [`Self::release_field`] or `self` is dropped.
+    pub fn get_field_mut(&self, name: &str) -> Option<NonNull<c_void>> where S: Reflect {
+        let index = S::FIELD_NAMES.iter().position(|field| *field == name).filter(|i| *i < 64)?;
+        let bit = 1 << index;
+        let borrowed = self.borrowed.get();
+        if borrowed & bit != 0 { return None }
+        self.borrowed.set(borrowed | bit);
+        self.lent.set(self.lent.get() | bit);
+        let offset = S::FIELD_OFFSETS[index];
+        // SAFETY: `offset` is the offset of a field of `S`, so the result stays within the
+        // struct pointed to by `this`.
+        let field = unsafe { self.this.cast::<u8>().add(offset) };
+        NonNull::new(field.cast())
+    }
+
+    /// Returns the field lent by [`Self::get_field_mut`]. Returns `false` if there is no such
+    /// field or it is not lent by name. The pointer to the field must not be used afterwards.
+    pub fn release_field(&self, name: &str) -> bool where S: Reflect {
+        let Some(index) = S::FIELD_NAMES.iter().position(|field| *field == name) else { return false };
+        let bit = if index < 64 { 1 << index } else { 0 };
+        if self.lent.get() & bit == 0 { return false }
+        self.lent.set(self.lent.get() & !bit);
+        self.borrowed.set(self.borrowed.get() & !bit);
+        true
+    }
+}
+
+impl<S> Debug for DynRefsMut<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynRefsMut")
+            .field("borrowed", &self.borrowed.get())
+            .field("lent", &self.lent.get())
+            .finish()
+    }
+}
+
+/// Fields borrowed mutably by [`DynRefsMut::borrow_mut`]. The fields are returned when dropped.
+pub struct DynBorrowMut<'a, 't, S> {
+    refs: &'a DynRefsMut<'t, S>,
+    mask: u64,
+}
+
+impl<S: FieldOffsets> DynBorrowMut<'_, '_, S> {
+    /// Mask of the borrowed fields.
+    #[inline(always)]
+    pub fn mask(&self) -> u64 {
+        self.mask
+    }
+
+    /// Whether the field with the given index is borrowed.
+    #[inline(always)]
+    pub fn contains(&self, index: usize) -> bool {
+        index < 64 && self.mask & (1 << index) != 0
+    }
+
+    /// Mutable reference to the `Field`, like `ctx_fields::geometry`, or `None` if it was not
+    /// selected.
+    #[inline(always)]
+    pub fn get_mut<Field>(&mut self) -> Option<&mut FieldAt<FieldIndexOf<S, Field>, S>> where
+    S: FieldIndex<Field> + HasFieldTypes,
+    S::FieldTypes: hlist::ItemAt<FieldIndexOf<S, Field>> {
+        let index = <S as FieldIndex<Field>>::INDEX;
+        if !self.contains(index) { return None }
+        let offset = S::FIELD_OFFSETS[index];
+        // SAFETY: The field is lent only to this borrow, which is borrowed mutably for the
+        // lifetime of the result. See the `DynRefsMut` invariants.
+        unsafe {
+            let field = self.refs.this.cast::<u8>().add(offset).cast();
+            Some(&mut *field)
+        }
+    }
+}
+
+impl<S> Drop for DynBorrowMut<'_, '_, S> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        self.refs.borrowed.set(self.refs.borrowed.get() & !self.mask);
+    }
+}
+
+impl<S> Debug for DynBorrowMut<'_, '_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynBorrowMut").field("mask", &self.mask).finish()
+    }
+}
+
+
 // ==============
 // === Macros ===
 // ==============
@@ -307,11 +2873,164 @@ macro_rules! lifetime_chooser {
     ($lt1:lifetime $($ts:tt)*) => {& $lt1 $($ts)*};
 }
 
+/// Makes every selector without an explicit access mode mutable, like `a, ref b, @group` to
+/// `mut a, ref b, mut @group`, and passes the result to the `$cb` macro. Used by the `only`
+/// selector prefix and by `mut @List` for field lists.
+#[macro_export]
+macro_rules! mut_by_default {
+    ([$($cb:tt)*] [$($state:tt)*] [$($rest:tt)*] [$($ts:tt)*]) => {
+        $crate::mut_by_default! { @ [$($cb)*] [$($state)*] [$($rest)*] [] [$($ts)*] }
+    };
+    (@ [$($cb:tt)*] [$($state:tt)*] [$($rest:tt)*] [$($out:tt)*] [$(,)*]) => {
+        $($cb)*! { $($state)* [$($out)* $($rest)*] }
+    };
+    (@ $cb:tt $state:tt $rest:tt [$($out:tt)*] [, $($lt:lifetime)? mut @ $n:ident $($ts:tt)*]) => {
+        $crate::mut_by_default! { @ $cb $state $rest [$($out)*, $($lt)? mut @ $n] [$($ts)*] }
+    };
+    (@ $cb:tt $state:tt $rest:tt [$($out:tt)*] [, $($lt:lifetime)? mut $n:tt $($ts:tt)*]) => {
+        $crate::mut_by_default! { @ $cb $state $rest [$($out)*, $($lt)? mut $n] [$($ts)*] }
+    };
+    (@ $cb:tt $state:tt $rest:tt [$($out:tt)*] [, $($lt:lifetime)? ref @ $n:ident $($ts:tt)*]) => {
+        $crate::mut_by_default! { @ $cb $state $rest [$($out)*, $($lt)? ref @ $n] [$($ts)*] }
+    };
+    (@ $cb:tt $state:tt $rest:tt [$($out:tt)*] [, $($lt:lifetime)? ref $n:tt $($ts:tt)*]) => {
+        $crate::mut_by_default! { @ $cb $state $rest [$($out)*, $($lt)? ref $n] [$($ts)*] }
+    };
+    (@ $cb:tt $state:tt $rest:tt [$($out:tt)*] [, ! @ $n:ident $($ts:tt)*]) => {
+        $crate::mut_by_default! { @ $cb $state $rest [$($out)*, ! @ $n] [$($ts)*] }
+    };
+    (@ $cb:tt $state:tt $rest:tt [$($out:tt)*] [, ! $n:tt $($ts:tt)*]) => {
+        $crate::mut_by_default! { @ $cb $state $rest [$($out)*, ! $n] [$($ts)*] }
+    };
+    (@ $cb:tt $state:tt $rest:tt [$($out:tt)*] [, $n:ident ($($sel:tt)*) $($ts:tt)*]) => {
+        $crate::mut_by_default! { @ $cb $state $rest [$($out)*, $n ($($sel)*)] [$($ts)*] }
+    };
+    (@ $cb:tt $state:tt $rest:tt [$($out:tt)*] [, $lt:lifetime @ $n:ident $($ts:tt)*]) => {
+        $crate::mut_by_default! { @ $cb $state $rest [$($out)*, $lt mut @ $n] [$($ts)*] }
+    };
+    (@ $cb:tt $state:tt $rest:tt [$($out:tt)*] [, @ $n:ident $($ts:tt)*]) => {
+        $crate::mut_by_default! { @ $cb $state $rest [$($out)*, mut @ $n] [$($ts)*] }
+    };
+    (@ $cb:tt $state:tt $rest:tt [$($out:tt)*] [, $lt:lifetime $n:tt $($ts:tt)*]) => {
+        $crate::mut_by_default! { @ $cb $state $rest [$($out)*, $lt mut $n] [$($ts)*] }
+    };
+    (@ $cb:tt $state:tt $rest:tt [$($out:tt)*] [, $n:tt $($ts:tt)*]) => {
+        $crate::mut_by_default! { @ $cb $state $rest [$($out)*, mut $n] [$($ts)*] }
+    };
+    // Invalid selectors are passed as-is to be reported by the `$cb` macro.
+    (@ [$($cb:tt)*] [$($state:tt)*] [$($rest:tt)*] [$($out:tt)*] [$($ts:tt)*]) => {
+        $($cb)*! { $($state)* [$($out)* $($ts)* $($rest)*] }
+    };
+}
+
+/// Fails to compile if the partial borrows do not borrow the same fields in the same way, like
+/// `assert_same_fields!(p!(<mut a, b> S), p!(<b, mut a> S))`. Useful for guarding against
+/// accidental changes of function signatures.
+#[macro_export]
+macro_rules! assert_same_fields {
+    ($t1:ty, $t2:ty $(,)?) => {
+        const _: () = {
+            fn assert_same_fields<T1: $crate::SameFields<T2>, T2>() {}
+            fn check() { assert_same_fields::<$t1, $t2>(); }
+        };
+    };
+}
+
+/// Fails to compile if the partial borrows can't exist at the same time, i.e. if a field is
+/// borrowed mutably by one of them and borrowed by the other one, like
+/// `assert_disjoint!(p!(<mut nodes> Graph), p!(<mut edges> Graph))`. Useful for documenting
+/// that functions taking these borrows can run in parallel.
+#[macro_export]
+macro_rules! assert_disjoint {
+    ($t1:ty, $t2:ty $(,)?) => {
+        const _: () = {
+            fn assert_disjoint<T1: $crate::DisjointFields<T2>, T2>() {}
+            fn check() { assert_disjoint::<$t1, $t2>(); }
+        };
+    };
+}
+
+/// Defines a reusable list of field selectors, which can be used in `p!` with `@`, like
+/// `p!(&<@Render, mut scene> Ctx)`. In contrast to groups, it is defined at the use site:
+///
+/// ```ignore
+/// fields! { pub(crate) Render = mut geometry, mut material }
+/// ```
+///
+/// The selectors are pasted in place of `@Render`, so they use the default lifetime of the `p!`
+/// invocation unless they provide their own one. Lists can refer to other lists, and several
+/// lists can be used in a single `p!` invocation.
+#[macro_export]
+macro_rules! fields {
+    (@ ($d:tt) $vis:vis $name:ident [$($ts:tt)*]) => {
+        macro_rules! $name {
+            ([$d($d cb:tt)*] [$d($d state:tt)*] [$d($d xs:tt)*]) => {
+                $d($d cb)*! { $d($d state)* [, $($ts)* $d($d xs)*] }
+            };
+        }
+        #[allow(unused_imports)]
+        $vis use $name;
+    };
+    ($vis:vis $name:ident = $($ts:tt)*) => {
+        $crate::fields! { @ ($) $vis $name [$($ts)*] }
+    };
+}
+
+/// Partial borrow of a struct, like `p!(&<mut geometry, material> Ctx)`. Fields can be borrowed
+/// immutably with an explicit `ref`, like `p!(&<mut geometry, ref material> Ctx)`, which is the same
+/// type. The struct can be given by a path, like `p!(&<mut scene> data::Ctx)`, which resolves the
+/// selector macro exported next to the struct. Unknown fields are reported with the list of valid
+/// fields of the struct.
+///
+/// ```compile_fail
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// // Error: Unknown field `edgez` of `Graph`. Valid fields: nodes, edges.
+/// fn detach(graph: p!(&<mut edgez> Graph)) {}
+/// # fn main() {}
+/// ```
+///
+/// Naming a field more than once is reported as well, as it is most likely a mistake. Fields
+/// selected with `*`, `!`, groups, or field lists can still be overridden by later selectors, like
+/// in `p!(&<mut *, !nodes, nodes> Graph)`.
+///
+/// ```compile_fail
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Graph {
+///     nodes: Vec<usize>,
+///     edges: Vec<usize>,
+/// }
+///
+/// // Error: Field `nodes` of `Graph` is selected more than once.
+/// fn detach(graph: p!(&<mut nodes, edges, nodes> Graph)) {}
+/// # fn main() {}
+/// ```
 #[macro_export]
 macro_rules! partial_borrow {
-    (& $lt:lifetime $($ts:tt)*)       => { & $lt mut $crate::partial_borrow! { $($ts)* } };
-    (& $($ts:tt)*)                    => { &     mut $crate::partial_borrow! { $($ts)* } };
-    (< $($ts:tt)*)                    => {           $crate::partial_borrow! { @ [] $($ts)* } };
-    (@ [$($xs:tt)*] > $t:ident)       => { $t! { $($xs)* } };
-    (@ [$($xs:tt)*] $t:tt $($ts:tt)*) => { $crate::partial_borrow! { @ [$($xs)* $t] $($ts)* } };
+    (& $lt:lifetime $($ts:tt)*)            => { & $lt mut $crate::partial_borrow! { $($ts)* } };
+    (& $($ts:tt)*)                         => { &     mut $crate::partial_borrow! { $($ts)* } };
+    (< $($ts:tt)*)                         => {           $crate::partial_borrow! { @ [] $($ts)* } };
+    (@ [$($xs:tt)*] > $($ts:tt)*)          => { $crate::partial_borrow! { @path [$($xs)*] [] $($ts)* } };
+    (@ [$($xs:tt)*] $t:tt $($ts:tt)*)      => { $crate::partial_borrow! { @ [$($xs)* $t] $($ts)* } };
+    (@path [$($xs:tt)*] [$($p:tt)*] :: $($ts:tt)*) => {
+        $crate::partial_borrow! { @path [$($xs)*] [$($p)* ::] $($ts)* }
+    };
+    (@path [$($xs:tt)*] [$($p:tt)*] $s:ident :: $($ts:tt)*) => {
+        $crate::partial_borrow! { @path [$($xs)*] [$($p)* $s ::] $($ts)* }
+    };
+    (@path [$($xs:tt)*] [$($p:tt)*] $t:ident $($ps:tt)*) => {
+        $($p)* $t! { [$crate] [$($ps)*] $($xs)* }
+    };
 }
\ No newline at end of file
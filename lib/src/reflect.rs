@@ -0,0 +1,95 @@
+use crate::Hidden;
+use crate::Nested;
+
+
+// ==============
+// === Access ===
+// ==============
+
+/// Access to a field of a partial borrow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Access {
+    Hidden,
+    Shared,
+    Mut,
+}
+
+impl Access {
+    #[inline(always)]
+    pub const fn is_hidden(self) -> bool { matches!(self, Self::Hidden) }
+
+    #[inline(always)]
+    pub const fn is_shared(self) -> bool { matches!(self, Self::Shared) }
+
+    #[inline(always)]
+    pub const fn is_mut(self) -> bool { matches!(self, Self::Mut) }
+
+    /// The strongest access in the `mask`, or `Access::Hidden` if the `mask` is empty.
+    pub const fn strongest(mask: &[Self]) -> Self {
+        let mut result = Self::Hidden;
+        let mut i = 0;
+        while i < mask.len() {
+            match mask[i] {
+                Self::Mut => return Self::Mut,
+                Self::Shared => result = Self::Shared,
+                Self::Hidden => {}
+            }
+            i += 1;
+        }
+        result
+    }
+}
+
+
+// ==================
+// === SlotAccess ===
+// ==================
+
+/// Access to the field described by a borrow slot, like `Access::Mut` for `&mut T`. A nested
+/// borrow reports the strongest access to the fields of the nested struct.
+pub trait SlotAccess {
+    const ACCESS: Access;
+}
+
+impl<T> SlotAccess for &T             { const ACCESS: Access = Access::Shared; }
+impl<T> SlotAccess for &mut T         { const ACCESS: Access = Access::Mut; }
+impl<T> SlotAccess for Option<&T>     { const ACCESS: Access = Access::Shared; }
+impl<T> SlotAccess for Option<&mut T> { const ACCESS: Access = Access::Mut; }
+impl<T> SlotAccess for Hidden<T>      { const ACCESS: Access = Access::Hidden; }
+
+impl<T, Target: BorrowShape> SlotAccess for Nested<T, Target> {
+    const ACCESS: Access = Access::strongest(Target::BORROW_MASK);
+}
+
+
+// ===================
+// === BorrowShape ===
+// ===================
+
+/// Access to every field of a partial borrow, like `[Access::Mut, Access::Hidden]` for
+/// `p!(<mut geometry> Ctx)` of a struct with the `geometry` and `mesh` fields. The mask has one
+/// element per field, in the order of [`Reflect::FIELD_NAMES`](crate::Reflect::FIELD_NAMES). It
+/// is implemented by the derive for the generated ref struct, and can be used in const
+/// assertions:
+///
+/// ```
+/// use borrow::PartialBorrow;
+/// use borrow::partial_borrow as p;
+/// use borrow::reflect::Access;
+/// use borrow::reflect::BorrowShape;
+///
+/// #[derive(PartialBorrow)]
+/// #[module(crate)]
+/// struct Ctx {
+///     geometry: Vec<f32>,
+///     mesh: Vec<usize>,
+/// }
+///
+/// const _: () = assert!(<p!(<mut geometry> Ctx)>::BORROW_MASK[0].is_mut());
+/// # fn main() {
+/// assert_eq!(<p!(<mesh> Ctx)>::BORROW_MASK, [Access::Hidden, Access::Shared]);
+/// # }
+/// ```
+pub trait BorrowShape {
+    const BORROW_MASK: &'static [Access];
+}
@@ -0,0 +1,67 @@
+//! Runtime tracing of borrow operations, enabled by the `debug-borrow-trace` feature in debug
+//! builds. Every `partial_borrow`, `split`, and `extract_$field` call reports a [`BorrowEvent`] to
+//! the hook set with [`set_hook`]. Without the feature, or in release builds, [`record`] is empty
+//! and the calls are compiled out. The hook API is available whenever the feature is enabled, so
+//! code setting the hook builds in release as well, where the hook is never called.
+
+#[cfg(all(feature = "debug-borrow-trace", debug_assertions))]
+use std::any::type_name;
+#[cfg(feature = "debug-borrow-trace")]
+use std::mem;
+#[cfg(feature = "debug-borrow-trace")]
+use std::sync::PoisonError;
+#[cfg(feature = "debug-borrow-trace")]
+use std::sync::RwLock;
+
+
+// ===================
+// === BorrowEvent ===
+// ===================
+
+/// A traced borrow operation, like `split` of `GraphRef<&mut Vec<Node>, Hidden<Vec<Edge>>>`.
+#[cfg(feature = "debug-borrow-trace")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BorrowEvent {
+    /// Name of the operation, like `partial_borrow`, `split`, or `extract_nodes`.
+    pub op: &'static str,
+    /// Type name of the borrowed fields. Borrowed fields are `&T` or `&mut T` slots, the other
+    /// ones are `Hidden<T>` slots.
+    pub target: &'static str,
+}
+
+
+// ============
+// === Hook ===
+// ============
+
+/// Function called for every traced borrow operation.
+#[cfg(feature = "debug-borrow-trace")]
+pub type Hook = fn(&BorrowEvent);
+
+#[cfg(feature = "debug-borrow-trace")]
+static HOOK: RwLock<Option<Hook>> = RwLock::new(None);
+
+/// Sets the function called for every traced borrow operation, or disables tracing with `None`.
+/// Returns the previous hook. In release builds the hook is stored but never called.
+#[cfg(feature = "debug-borrow-trace")]
+pub fn set_hook(hook: Option<Hook>) -> Option<Hook> {
+    let mut current = HOOK.write().unwrap_or_else(PoisonError::into_inner);
+    mem::replace(&mut *current, hook)
+}
+
+/// Reports the borrow operation `op` of the `Target` fields to the hook. It is called by the
+/// generated code, so it is always available, but it does nothing without the
+/// `debug-borrow-trace` feature or in release builds.
+#[doc(hidden)]
+#[inline(always)]
+pub fn record<Target: ?Sized>(op: &'static str) {
+    #[cfg(all(feature = "debug-borrow-trace", debug_assertions))]
+    {
+        let hook = *HOOK.read().unwrap_or_else(PoisonError::into_inner);
+        if let Some(hook) = hook {
+            hook(&BorrowEvent { op, target: type_name::<Target>() });
+        }
+    }
+    #[cfg(not(all(feature = "debug-borrow-trace", debug_assertions)))]
+    let _ = op;
+}
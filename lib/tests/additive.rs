@@ -0,0 +1,56 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Ctx {
+    pub log: Vec<String>,
+    pub counter: usize,
+    /// Added after `run` was written. It is not borrowed by `<mut *>` selections.
+    #[borrow(additive)]
+    pub stats: Vec<usize>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn run(ctx: p!(&<mut *> Ctx)) {
+    ctx.log.push("run".to_string());
+    *ctx.counter += 1;
+}
+
+fn run_with_stats(ctx: p!(&<mut *, mut stats> Ctx)) {
+    let (stats, ctx2) = ctx.split::<p!(<mut stats> Ctx)>();
+    run(ctx2.partial_borrow());
+    stats.stats.push(*ctx2.counter);
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_additive_field() {
+    let mut ctx = Ctx::default();
+    let mut refs = ctx.as_refs_mut();
+    // The `stats` field stays available next to a `<mut *>` borrow.
+    let (all, rest) = refs.split::<p!(<mut *> Ctx)>();
+    run(all);
+    rest.stats.push(0);
+    run_with_stats(ctx.as_refs_mut().partial_borrow());
+    assert_eq!(ctx.counter, 2);
+    assert_eq!(ctx.stats, vec![0, 2]);
+    let shared = ctx.as_shared_refs::<p!(<*> Ctx)>();
+    assert_eq!(format!("{shared:?}"), "CtxRef { log: &[\"run\", \"run\"], counter: &2, stats: <hidden> }");
+}
+
+borrow::assert_same_fields!(p!(<mut *> Ctx), p!(<mut log, mut counter> Ctx));
+borrow::assert_disjoint!(p!(<mut *> Ctx), p!(<mut stats> Ctx));
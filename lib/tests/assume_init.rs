@@ -0,0 +1,82 @@
+#![allow(dead_code)]
+
+use std::mem::ManuallyDrop;
+use std::mem::MaybeUninit;
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default)]
+pub struct Block {
+    pub data: Vec<u8>,
+}
+
+/// The `block` field is initialized by `Arena::new`, and it stays initialized until the arena is
+/// dropped.
+#[derive(PartialBorrow)]
+#[module(crate)]
+pub struct Arena {
+    pub log: Vec<String>,
+    #[borrow(unsafe(assume_init))]
+    pub block: MaybeUninit<Block>,
+    /// `ManuallyDrop` implements `Deref`, so it is borrowed with `#[borrow(deref)]`.
+    #[borrow(deref)]
+    pub spare: ManuallyDrop<Block>,
+}
+
+impl Arena {
+    fn new() -> Self {
+        Self {
+            log: vec![],
+            block: MaybeUninit::new(Block::default()),
+            spare: ManuallyDrop::new(Block::default()),
+        }
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        // SAFETY: The block is initialized by `Arena::new`, and it is dropped only once.
+        unsafe { self.block.assume_init_drop() };
+        // SAFETY: The spare block is dropped only once.
+        unsafe { ManuallyDrop::drop(&mut self.spare) };
+    }
+}
+
+// =============
+// === Utils ===
+// =============
+
+// Requires mutable access to `arena.block` and `arena.log`.
+fn alloc(arena: p!(&<mut block, mut log> Arena), size: usize) {
+    arena.block.data.resize(arena.block.data.len() + size, 0);
+    arena.log.push(format!("alloc {size}"));
+}
+
+// Requires immutable access to `arena.block` and mutable access to `arena.spare`.
+fn backup(arena: p!(&<block, mut spare> Arena)) {
+    arena.spare.data.clone_from(&arena.block.data);
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_assume_init() {
+    let mut arena = Arena::new();
+    alloc(arena.as_refs_mut().partial_borrow(), 4);
+    backup(arena.as_refs_mut().partial_borrow());
+    let shared = arena.as_shared_refs::<p!(<block, spare> Arena)>();
+    assert_eq!(shared.block.data.len(), 4);
+    assert_eq!(shared.spare.data.len(), 4);
+    let mut refs = arena.as_refs_mut();
+    let (block, rest) = refs.extract_block();
+    block.data.clear();
+    rest.log.push("clear".to_string());
+    assert_eq!(arena.log, vec!["alloc 4".to_string(), "clear".to_string()]);
+}
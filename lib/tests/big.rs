@@ -0,0 +1,70 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+use borrow::FieldAt;
+use borrow::FieldIndex;
+use borrow::FieldIndexOf;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+#[borrow(field_markers = big_fields)]
+struct Big {
+    f0: Vec<usize>,
+    f1: Vec<usize>,
+    f2: Vec<usize>,
+    f3: Vec<usize>,
+    f4: Vec<usize>,
+    f5: Vec<usize>,
+    f6: Vec<usize>,
+    f7: Vec<usize>,
+    f8: Vec<usize>,
+    f9: Vec<usize>,
+    f10: Vec<usize>,
+    f11: Vec<usize>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn fill(big: p!(&<mut *> Big)) {
+    let (f11, big2) = big.extract_f11();
+    f11.push(11);
+    push_last_fields(big2.partial_borrow(), f11.len());
+}
+
+fn push_last_fields(big: p!(&<mut f9, mut f10> Big), value: usize) {
+    big.f9.push(value);
+    big.f10.push(value);
+}
+
+// Resolves the field type by the field name.
+fn last_field(big: &Big) -> &FieldAt<FieldIndexOf<Big, big_fields::f11>, Big> {
+    &big.f11
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_more_than_ten_fields() {
+    let mut big = Big::default();
+    fill(big.as_refs_mut().partial_borrow());
+    assert_eq!(big.f9, vec![1]);
+    assert_eq!(big.f10, vec![1]);
+    assert_eq!(big.f11, vec![11]);
+}
+
+#[test]
+fn test_field_index() {
+    assert_eq!(<Big as FieldIndex<big_fields::f0>>::INDEX, 0);
+    assert_eq!(<Big as FieldIndex<big_fields::f11>>::INDEX, 11);
+    assert!(last_field(&Big::default()).is_empty());
+}
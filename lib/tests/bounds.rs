@@ -0,0 +1,45 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+// The extra bound is added to the generated impls only, so the struct itself can be used with any
+// `T`, but it can be partially borrowed only if `T: 'static`.
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+#[borrow(bounds(T: 'static))]
+pub struct Registry<T> {
+    pub items: Vec<T>,
+    pub log: Vec<String>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn register<T: 'static>(ctx: p!(&<mut items, mut log> Registry<T>), item: T) {
+    ctx.items.push(item);
+    ctx.log.push("register".to_string());
+}
+
+fn count<T: 'static>(registry: &mut Registry<T>) -> usize {
+    registry.as_refs_mut().extract_items().0.len()
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_extra_bounds() {
+    let mut registry = Registry::<u32>::default();
+    register(registry.as_refs_mut().partial_borrow(), 1);
+    register(registry.as_refs_mut().partial_borrow(), 2);
+    assert_eq!(count(&mut registry), 2);
+    assert_eq!(registry.log.len(), 2);
+}
@@ -0,0 +1,50 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, PartialBorrow)]
+#[module(crate)]
+pub struct Buffers<const N: usize> {
+    pub a: [Vec<u8>; N],
+    pub b: Vec<u8>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn fill_a(buffers: p!(&<mut a> Buffers<4>), value: u8) {
+    for buffer in buffers.a.iter_mut() {
+        buffer.push(value);
+    }
+}
+
+fn copy_to_b<const N: usize>(buffers: p!(&<mut *> Buffers<N>)) {
+    let (a, buffers2) = buffers.extract_a();
+    for buffer in a {
+        copy_to_b_from::<N>(buffers2.partial_borrow(), buffer);
+    }
+}
+
+fn copy_to_b_from<const N: usize>(buffers: p!(&<mut b> Buffers<N>), buffer: &[u8]) {
+    buffers.b.extend_from_slice(buffer);
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_const_generic() {
+    let mut buffers = Buffers { a: Default::default(), b: vec![] };
+    fill_a(buffers.as_refs_mut().partial_borrow(), 1);
+    copy_to_b(buffers.as_refs_mut().partial_borrow());
+    assert_eq!(buffers.a, [vec![1], vec![1], vec![1], vec![1]]);
+    assert_eq!(buffers.b, vec![1, 1, 1, 1]);
+}
@@ -0,0 +1,67 @@
+#![allow(dead_code)]
+
+use std::borrow::Cow;
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Settings {
+    pub scale: usize,
+}
+
+#[derive(Debug, PartialBorrow)]
+#[module(crate)]
+pub struct Ctx<'s> {
+    pub log: Vec<String>,
+    /// Borrowed from shared defaults, and cloned when modified.
+    #[borrow(cow)]
+    pub settings: Cow<'s, Settings>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+// Requires immutable access to `ctx.settings`, which does not clone it.
+fn scale(ctx: p!(&<settings> Ctx<'_>)) -> usize {
+    ctx.settings.scale
+}
+
+// Requires mutable access to `ctx.settings`, which clones it if it is borrowed.
+fn double_scale(ctx: p!(&<mut settings, mut log> Ctx<'_>)) {
+    ctx.settings.scale *= 2;
+    ctx.log.push("double_scale".to_string());
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_cow_field() {
+    let defaults = Settings { scale: 2 };
+    let mut ctx = Ctx { log: vec![], settings: Cow::Borrowed(&defaults) };
+    assert_eq!(scale(&mut ctx.as_shared_refs()), 2);
+    assert_eq!(scale(&mut ctx.as_refs()), 2);
+    assert!(matches!(ctx.settings, Cow::Borrowed(_)));
+    double_scale(ctx.as_refs_mut().partial_borrow());
+    assert!(matches!(ctx.settings, Cow::Owned(_)));
+    assert_eq!(scale(ctx.as_refs_mut().partial_borrow()), 4);
+    assert_eq!(defaults.scale, 2);
+    assert_eq!(ctx.log.len(), 1);
+}
+
+#[test]
+fn test_cow_extract() {
+    let defaults = Settings { scale: 2 };
+    let mut ctx = Ctx { log: vec![], settings: Cow::Borrowed(&defaults) };
+    let mut refs = ctx.as_refs_mut();
+    let (settings, rest) = refs.extract_settings_ref();
+    rest.log.push(format!("scale: {}", settings.scale));
+    assert_eq!(ctx.log, vec!["scale: 2".to_string()]);
+}
@@ -6,12 +6,12 @@ use borrow::PartialBorrow;
 
 #[derive(Debug, Default)]
 pub struct Geometry {
-    label: String,
+    pub label: String,
 }
 
 #[derive(Debug, Default)]
 pub struct Material {
-    label: String,
+    pub label: String,
 }
 
 #[derive(Debug, Default)]
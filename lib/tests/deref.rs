@@ -0,0 +1,55 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default)]
+pub struct Node {
+    pub outputs: Vec<usize>,
+}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Graph {
+    #[borrow(deref)]
+    pub nodes: Box<Vec<Node>>,
+    #[borrow(deref)]
+    pub root: Box<Node>,
+    pub names: Vec<String>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn add_node(graph: p!(&<mut nodes, root> Graph), name: &str) -> usize {
+    let nodes: &mut Vec<Node> = graph.nodes;
+    nodes.push(Node { outputs: graph.root.outputs.clone() });
+    nodes.len() - 1 + name.len()
+}
+
+fn add_named_node(graph: p!(&<mut *> Graph), name: &str) {
+    let (names, graph2) = graph.extract_names();
+    add_node(graph2.partial_borrow(), name);
+    names.push(name.to_string());
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_deref_field() {
+    let mut graph = Graph::default();
+    graph.root.outputs.push(7);
+    add_named_node(graph.as_refs_mut().partial_borrow(), "a");
+    assert_eq!(graph.nodes[0].outputs, vec![7]);
+    assert_eq!(graph.names, vec!["a".to_string()]);
+    let shared = graph.as_shared_refs::<p!(<root> Graph)>();
+    assert_eq!(format!("{shared:?}"), "GraphRef { nodes: <hidden>, root: &Node { outputs: [7] }, names: <hidden> }");
+}
@@ -0,0 +1,115 @@
+#![allow(dead_code)]
+
+use borrow::DynRefsMut;
+use borrow::FieldIndex;
+use borrow::PartialBorrow;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+#[borrow(field_markers = ctx_fields)]
+pub struct Ctx {
+    pub nodes: Vec<usize>,
+    pub edges: Vec<(usize, usize)>,
+    pub log: Vec<String>,
+}
+
+// ===============
+// === Plugins ===
+// ===============
+
+// Plugins are selected at runtime, so the fields they need are only known at runtime as well.
+#[derive(Clone, Copy, Debug)]
+enum Plugin {
+    AddNode,
+    Logger,
+}
+
+fn bit<Field>() -> u64 where Ctx: FieldIndex<Field> {
+    1 << <Ctx as FieldIndex<Field>>::INDEX
+}
+
+impl Plugin {
+    fn fields(self) -> u64 {
+        match self {
+            Self::AddNode => bit::<ctx_fields::nodes>() | bit::<ctx_fields::edges>(),
+            Self::Logger => bit::<ctx_fields::log>(),
+        }
+    }
+}
+
+fn run_plugins(ctx: &mut Ctx) {
+    let refs = DynRefsMut::new(ctx);
+    let add_node = refs.borrow_mut(Plugin::AddNode.fields());
+    let logger = refs.borrow_mut(Plugin::Logger.fields());
+    assert!(refs.borrow_mut(bit::<ctx_fields::nodes>()).is_none());
+    assert_eq!(refs.borrowed_mask(), 0b111);
+
+    if let Some(mut add_node) = add_node {
+        if let Some(nodes) = add_node.get_mut::<ctx_fields::nodes>() {
+            nodes.push(0);
+        }
+        if let Some(edges) = add_node.get_mut::<ctx_fields::edges>() {
+            edges.push((0, 0));
+        }
+        assert!(add_node.get_mut::<ctx_fields::log>().is_none());
+    }
+    assert_eq!(refs.borrowed_mask(), bit::<ctx_fields::log>());
+    assert!(refs.borrow_mut(bit::<ctx_fields::nodes>()).is_some());
+
+    if let Some(mut logger) = logger {
+        if let Some(log) = logger.get_mut::<ctx_fields::log>() {
+            log.push("add_node".to_string());
+        }
+    }
+    assert_eq!(refs.borrowed_mask(), 0);
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_dyn_borrow() {
+    let mut ctx = Ctx::default();
+    run_plugins(&mut ctx);
+    assert_eq!(ctx.nodes, vec![0]);
+    assert_eq!(ctx.edges, vec![(0, 0)]);
+    assert_eq!(ctx.log, vec!["add_node".to_string()]);
+}
+
+#[test]
+fn test_dyn_borrow_invalid_mask() {
+    let mut ctx = Ctx::default();
+    let refs = DynRefsMut::new(&mut ctx);
+    assert_eq!(DynRefsMut::<Ctx>::fields_mask(), 0b111);
+    assert!(refs.borrow_mut(0b1000).is_none());
+    assert_eq!(refs.borrowed_mask(), 0);
+}
+
+#[test]
+fn test_dyn_borrow_by_name() {
+    let mut ctx = Ctx::default();
+    let refs = DynRefsMut::new(&mut ctx);
+    let logger = refs.borrow_mut(bit::<ctx_fields::log>());
+    assert!(refs.get_field_mut("log").is_none());
+    assert!(!refs.release_field("log"));
+    assert!(refs.get_field_mut("unknown").is_none());
+
+    let nodes = refs.get_field_mut("nodes");
+    assert!(refs.get_field_mut("nodes").is_none());
+    assert!(refs.borrow_mut(bit::<ctx_fields::nodes>()).is_none());
+    if let Some(nodes) = nodes {
+        // SAFETY: The `nodes` field is a `Vec<usize>` lent by name, and it is not released yet.
+        unsafe { (*nodes.cast::<Vec<usize>>().as_ptr()).push(1) };
+    }
+    assert!(refs.release_field("nodes"));
+    assert!(!refs.release_field("nodes"));
+    assert!(refs.borrow_mut(bit::<ctx_fields::nodes>()).is_some());
+    drop(logger);
+    assert_eq!(refs.borrowed_mask(), 0);
+    assert_eq!(ctx.nodes, vec![1]);
+}
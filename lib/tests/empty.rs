@@ -0,0 +1,39 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Empty {}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Unit;
+
+// =============
+// === Utils ===
+// =============
+
+fn noop(ctx: p!(&<> Empty)) -> p!(&<> Empty) {
+    let (ctx2, _) = ctx.split::<p!(<> Empty)>();
+    ctx2.partial_borrow()
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_empty() {
+    let mut empty = Empty {};
+    let mut refs: EmptyRef = empty.as_refs_mut();
+    let ctx: p!(&<> Empty) = noop(&mut refs);
+    assert_eq!(format!("{ctx:?}"), "EmptyRef");
+    let _: p!(<*> Unit) = Unit.as_refs_mut();
+}
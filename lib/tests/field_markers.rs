@@ -0,0 +1,41 @@
+#![allow(dead_code)]
+
+use borrow::FieldAt;
+use borrow::FieldIndex;
+use borrow::FieldIndexOf;
+use borrow::PartialBorrow;
+
+// ============
+// === Data ===
+// ============
+
+/// Unrelated module that would collide with a module generated without the attribute.
+pub mod http_ctx_fields {}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct HTTPCtx {
+    pub url: String,
+    pub retries: usize,
+}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+#[borrow(field_markers = markers)]
+pub struct Ctx {
+    pub names: Vec<String>,
+    pub count: usize,
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_field_markers() {
+    assert_eq!(<Ctx as FieldIndex<markers::names>>::INDEX, 0);
+    assert_eq!(<Ctx as FieldIndex<markers::count>>::INDEX, 1);
+    let ctx = Ctx { names: vec![], count: 2 };
+    let count: &FieldAt<FieldIndexOf<Ctx, markers::count>, Ctx> = &ctx.count;
+    assert_eq!(*count, 2);
+}
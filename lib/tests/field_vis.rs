@@ -0,0 +1,62 @@
+#![allow(dead_code)]
+
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+use world::Ctx;
+
+// ============
+// === Data ===
+// ============
+
+// The fields of the ref struct have the same visibility as the fields of `Ctx`, so partial borrows
+// expose them only where `Ctx` does.
+mod world {
+    use borrow::PartialBorrow;
+    use borrow::partial_borrow as p;
+
+    #[derive(Debug, Default, PartialBorrow)]
+    #[module(crate::world)]
+    pub struct Ctx {
+        pub bodies: Vec<usize>,
+        pub(crate) log: Vec<String>,
+        pub(super) frame: usize,
+        counter: usize,
+    }
+
+    pub fn tick(ctx: p!(&<mut counter, mut frame> Ctx)) -> usize {
+        *ctx.counter += 1;
+        *ctx.frame += 1;
+        *ctx.counter
+    }
+
+    pub mod inner {
+        use borrow::partial_borrow as p;
+
+        pub fn count(ctx: p!(&<counter> super::Ctx)) -> usize {
+            *ctx.counter
+        }
+    }
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn step(ctx: p!(&<mut bodies, mut log, frame> Ctx)) {
+    ctx.bodies.push(*ctx.frame);
+    ctx.log.push("step".to_string());
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_field_vis() {
+    let mut ctx = Ctx::default();
+    assert_eq!(world::tick(ctx.as_refs_mut().partial_borrow()), 1);
+    step(ctx.as_refs_mut().partial_borrow());
+    assert_eq!(world::inner::count(ctx.as_refs_mut().partial_borrow()), 1);
+    assert_eq!(ctx.bodies, vec![1]);
+    assert_eq!(ctx.log.len(), 1);
+}
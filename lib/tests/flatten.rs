@@ -0,0 +1,60 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Scene {
+    pub meshes: Vec<usize>,
+    pub lights: Vec<usize>,
+    pub log: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Ctx {
+    #[borrow(flatten)]
+    pub scene: Scene,
+    pub log: Vec<String>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+// Fields of `Scene` can be selected directly. The `log` field refers to `ctx.log`, as fields of
+// `Ctx` take precedence over fields of the flattened `Scene`.
+fn add_light(ctx: p!(&<mut lights, meshes, mut log> Ctx)) {
+    let scene = ctx.scene.get();
+    scene.lights.push(scene.meshes.len());
+    ctx.log.push("add_light".to_string());
+}
+
+// Fields shadowed by `Ctx` are still reachable through the nested syntax.
+fn log_scene(ctx: p!(&<scene(mut log)> Ctx)) {
+    ctx.scene.get().log.push("log_scene".to_string());
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_flatten() {
+    let mut ctx = Ctx::default();
+    ctx.scene.meshes.push(0);
+    add_light(ctx.as_refs_mut().partial_borrow());
+    log_scene(ctx.as_refs_mut().partial_borrow());
+    assert_eq!(ctx.scene.lights, vec![1]);
+    assert_eq!(ctx.log, vec!["add_light".to_string()]);
+    assert_eq!(ctx.scene.log, vec!["log_scene".to_string()]);
+}
+
+borrow::assert_same_fields!(p!(<mut lights, meshes> Ctx), p!(<scene(mut lights, meshes)> Ctx));
+borrow::assert_disjoint!(p!(<mut log> Ctx), p!(<mut lights> Ctx));
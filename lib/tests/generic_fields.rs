@@ -0,0 +1,93 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Widget {
+    pub name: String,
+}
+
+/// The `T` parameter appears in several fields, also nested in other generic types.
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Pool<T: Clone> {
+    pub items: Vec<T>,
+    pub index: HashMap<usize, T>,
+    pub history: Vec<Option<Box<T>>>,
+    pub count: usize,
+}
+
+/// Several parameters appearing in several fields, bounded in the `where` clause.
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Cache<K, V> where K: Debug, V: Debug {
+    pub entries: Vec<(K, V)>,
+    pub keys: Vec<K>,
+    pub values: HashMap<usize, Vec<V>>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn add_item(pool: p!(&<mut items> Pool<Widget>), name: &str) {
+    pool.items.push(Widget { name: name.to_string() });
+}
+
+fn index_items<T: Clone>(pool: p!(&<items, mut index, mut count> Pool<T>)) {
+    for (i, item) in pool.items.iter().enumerate() {
+        pool.index.insert(i, item.clone());
+    }
+    *pool.count = pool.items.len();
+}
+
+fn archive<T: Clone>(pool: p!(&<mut *> Pool<T>)) {
+    let (items, rest) = pool.extract_items();
+    let (history, _) = rest.extract_history();
+    history.extend(items.drain(..).map(|item| Some(Box::new(item))));
+}
+
+fn insert<K: Debug + Clone, V: Debug + Clone>(cache: p!(&<mut *> Cache<K, V>), key: K, value: V) {
+    let (keys, rest) = cache.extract_keys_ref();
+    let (entries, rest) = rest.extract_entries();
+    entries.push((key.clone(), value.clone()));
+    rest.values.entry(keys.len()).or_default().push(value);
+    cache.keys.push(key);
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_param_in_several_fields() {
+    let mut pool = Pool::<Widget>::default();
+    add_item(pool.as_refs_mut().partial_borrow(), "a");
+    add_item(pool.as_refs_mut().partial_borrow(), "b");
+    index_items(pool.as_refs_mut().partial_borrow());
+    assert_eq!(pool.index[&1].name, "b");
+    assert_eq!(pool.count, 2);
+    archive(pool.as_refs_mut().partial_borrow());
+    assert!(pool.items.is_empty());
+    assert_eq!(pool.history.len(), 2);
+}
+
+#[test]
+fn test_several_params_in_several_fields() {
+    let mut cache = Cache::<&str, u32>::default();
+    insert(cache.as_refs_mut().partial_borrow(), "a", 1);
+    insert(cache.as_refs_mut().partial_borrow(), "b", 2);
+    assert_eq!(cache.entries, vec![("a", 1), ("b", 2)]);
+    assert_eq!(cache.keys, vec!["a", "b"]);
+    assert_eq!(cache.values[&1], vec![2]);
+}
+
+borrow::assert_disjoint!(p!(<mut items> Pool<Widget>), p!(<mut index> Pool<Widget>));
@@ -1,8 +1,14 @@
 #![allow(dead_code)]
 
+use std::mem;
+use std::thread;
 use std::vec::Vec;
 use borrow::PartialBorrow;
+use borrow::Reflect;
+use borrow::Union;
 use borrow::partial_borrow as p;
+use borrow::reflect::Access;
+use borrow::reflect::BorrowShape;
 use borrow::traits::*;
 
 // ============
@@ -12,13 +18,13 @@ use borrow::traits::*;
 type NodeId = usize;
 type EdgeId = usize;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct Node {
     outputs: Vec<EdgeId>,
     inputs: Vec<EdgeId>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct Edge {
     from: Option<NodeId>,
     to: Option<NodeId>,
@@ -36,6 +42,7 @@ struct Graph {
 // =============
 
 // Requires mutable access to the `graph.edges` field.
+#[allow(clippy::absolute_paths)]
 fn detach_node(graph: p!(&<mut edges> Graph), node: &mut Node) {
     for edge_id in std::mem::take(&mut node.outputs) {
         graph.edges[edge_id].from = None;
@@ -55,11 +62,169 @@ fn detach_all_nodes(graph: p!(&<mut *> Graph)) {
     }
 }
 
+// Requires mutable access to all `graph` fields.
+fn reverse_all_edges(graph: p!(&<mut *> Graph)) {
+    let (GraphRef { nodes, edges }, _) = graph.extract_fields::<p!(<mut nodes, mut edges> Graph)>();
+    for node in nodes {
+        mem::swap(&mut node.inputs, &mut node.outputs);
+    }
+    for edge in edges {
+        mem::swap(&mut edge.from, &mut edge.to);
+    }
+}
+
+// Requires mutable access to the `graph.edges` field, which is updated on another thread.
+fn detach_all_edges_in_thread(graph: p!(&<mut edges> Graph)) {
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            for edge in graph.edges.iter_mut() {
+                edge.from = None;
+                edge.to = None;
+            }
+        });
+    });
+}
+
+// Requires immutable access to the `graph.nodes` and `graph.edges` fields, which are read on two
+// other threads. Immutable partial borrows are `Send` and `Sync` if the borrowed fields are `Sync`.
+fn count_in_threads(nodes: p!(<nodes> Graph), edges: p!(<edges> Graph)) -> (usize, usize) {
+    let (mut outputs, mut connected) = (0, 0);
+    thread::scope(|scope| {
+        scope.spawn(|| outputs = nodes.nodes.iter().map(|node| node.outputs.len()).sum());
+        scope.spawn(|| connected = edges.edges.iter().filter(|edge| edge.to.is_some()).count());
+    });
+    (outputs, connected)
+}
+
+// Requires immutable access to all `graph` fields.
+fn count_connections(graph: p!(&<nodes, edges> Graph)) -> usize {
+    let outputs = graph.nodes.iter().map(|node| node.outputs.len()).sum::<usize>();
+    let edges = graph.edges.iter().filter(|edge| edge.from.is_some()).count();
+    outputs + edges
+}
+
+// Requires immutable access to all `graph` fields.
+fn count_connected_outputs(graph: p!(&<nodes, edges> Graph)) -> usize {
+    let (nodes, graph2) = graph.extract_nodes_ref();
+    nodes.iter().map(|node| count_connected_edges(graph2.partial_borrow(), &node.outputs)).sum()
+}
+
+// The `ref` keyword spells out an immutable borrow, so the types are the same.
+fn explicit_ref<'t>(
+    graph: p!(&'t <'t, ref nodes, mut edges> Graph)
+) -> p!(&'t <'t, nodes, mut edges> Graph) {
+    graph
+}
+
+// Requires immutable access to the `graph.edges` field.
+fn count_connected_edges(graph: p!(&<edges> Graph), edge_ids: &[EdgeId]) -> usize {
+    edge_ids.iter().filter(|edge_id| graph.edges[**edge_id].to.is_some()).count()
+}
+
+// Requires mutable access to the `graph.edges` field for the lifetime `'t`.
+fn push_edge<'t>(graph: p!(&'t <mut edges> Graph), from: NodeId, to: NodeId) {
+    graph.edges.push(Edge { from: Some(from), to: Some(to) });
+}
+
+// Passes the same borrow to a function called in a loop.
+fn push_edges<'t>(graph: p!(&'t <mut edges> Graph), count: usize) {
+    for i in 0..count {
+        push_edge(graph.reborrow(), i, i + 1);
+    }
+}
+
+// Requires mutable access to all `graph` fields.
+fn detach_all_nodes_scoped(graph: p!(&<mut *> Graph)) -> usize {
+    let detached = graph.with_nodes(|nodes, graph2| {
+        for node in nodes.iter_mut() {
+            detach_node(graph2.partial_borrow(), node);
+        }
+        nodes.len()
+    });
+    // The `graph` can be used again after the closure returns.
+    detached + graph.edges.len()
+}
+
+// Requires mutable access to all `graph` fields.
+fn detach_all_nodes_for_each(graph: p!(&<mut *> Graph)) {
+    graph.for_each_in_nodes(|node, graph2| detach_node(graph2.partial_borrow(), node));
+}
+
+// Requires mutable access to all `graph` fields, but only reads the `graph.nodes` field.
+fn count_detached_edges(graph: p!(&<mut nodes, mut edges> Graph)) -> usize {
+    let graph2 = graph.downgrade::<p!(<nodes, mut edges> Graph)>();
+    let detached = graph2.edges.iter().filter(|edge| edge.from.is_none()).count();
+    detached + count_connections(graph2.downgrade())
+}
+
+// Requires mutable access to the `graph.edges` field, which is passed on as a slice.
+fn detach_edges_as_slice(graph: p!(&<mut edges> Graph)) {
+    let GraphRefMapped { edges, .. } = graph.map_edges(|edges| &mut edges[..]);
+    detach_edges(edges);
+}
+
+fn detach_edges(edges: &mut [Edge]) {
+    for edge in edges {
+        edge.from = None;
+        edge.to = None;
+    }
+}
+
+// Extracted fields are borrowed for the lifetime of the call only, so fields can be extracted in
+// sequence, as long as the previous extraction is no longer used.
+fn clear_sequentially(graph: p!(&<mut *> Graph)) {
+    let (nodes, _) = graph.extract_nodes();
+    nodes.clear();
+    let (edges, rest) = graph.extract_edges();
+    edges.clear();
+    rest.nodes.push(Node { outputs: vec![], inputs: vec![] });
+}
+
+impl p!(<mut *> Graph) {
+    fn clear_sequentially(&mut self) {
+        let (edges, _) = self.extract_edges();
+        edges.clear();
+        let (nodes, _) = self.extract_nodes();
+        nodes.clear();
+    }
+}
+
+type NodesCtx<'a> = p!(<'a, nodes> Graph);
+type NodesEdgesCtx<'b> = p!(<'b, nodes, mut edges> Graph);
+
+// Both borrows include `nodes`, with different lifetimes. The union borrows it for the shorter
+// lifetime `'b`.
+fn add_edges_per_node<'a: 'b, 'b>(graph: &mut Union<NodesCtx<'a>, NodesEdgesCtx<'b>>) {
+    let nodes: &'b Vec<Node> = graph.nodes;
+    for _ in nodes {
+        graph.edges.push(Edge { from: None, to: None });
+    }
+}
+
+// The `'a` lifetime applies to the `edges` field only, so the returned reference can outlive the
+// ref struct.
+impl<'a> p!(<'a mut edges> Graph) {
+    fn last_edge(self) -> Option<&'a mut Edge> {
+        self.edges.last_mut()
+    }
+}
+
+// Selects a new set of the borrowed fields with fresh lifetimes. The returned borrow can be passed
+// by value, e.g. to `last_edge`.
+fn last_edge_from<'t>(graph: p!(&'t <mut *> Graph)) -> Option<&'t mut Edge> {
+    graph.as_refs::<p!(<mut edges> Graph)>().last_edge()
+}
+
+// Borrows that can be created from the same graph at the same time, e.g., for parallel passes.
+borrow::assert_disjoint!(p!(<mut edges> Graph), p!(<nodes> Graph));
+borrow::assert_disjoint!(p!(<nodes> Graph), p!(<nodes, mut edges> Graph));
+
 // =============
 // === Tests ===
 // =============
 
 #[test]
+#[allow(clippy::needless_borrow)]
 fn test() {
     // 0 -> 1 -> 2 -> 0
     let mut graph = Graph {
@@ -75,6 +240,25 @@ fn test() {
         ],
     };
 
+    let shared_graph = &graph;
+    assert_eq!(count_connections(&mut shared_graph.as_shared_refs()), 6);
+    assert_eq!(count_connected_outputs(&mut shared_graph.as_shared_refs()), 3);
+
+    // Immutable partial borrows are `Copy`.
+    let shared_refs: p!(<nodes, edges> Graph) = shared_graph.as_shared_refs();
+    let mut shared_refs_copy = shared_refs;
+    assert_eq!(count_connections(&mut shared_refs_copy), 6);
+    assert_eq!(count_connected_outputs(&mut { shared_refs }), 3);
+    let counts = count_in_threads(shared_graph.as_shared_refs(), shared_graph.as_shared_refs());
+    assert_eq!(counts, (3, 3));
+
+    reverse_all_edges(graph.as_refs_mut().partial_borrow());
+    assert_eq!(graph.nodes[0].inputs, vec![0]);
+    assert_eq!(graph.edges[0].from, Some(1));
+
+    detach_all_edges_in_thread(graph.as_refs_mut().partial_borrow());
+    assert!(graph.edges.iter().all(|edge| edge.from.is_none()));
+
     detach_all_nodes(&mut graph.as_refs_mut().partial_borrow());
 
     for node in &graph.nodes {
@@ -86,3 +270,431 @@ fn test() {
         assert!(edge.to.is_none());
     }
 }
+
+#[test]
+fn test_debug() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![], inputs: vec![] }],
+        edges: vec![],
+    };
+    let shared = graph.as_shared_refs::<p!(<nodes> Graph)>();
+    assert_eq!(format!("{shared:?}"), "GraphRef { nodes: &[Node { outputs: [], inputs: [] }], edges: <hidden> }");
+    let mut refs = graph.as_refs_mut();
+    let edges = refs.partial_borrow::<p!(<mut edges> Graph)>();
+    assert_eq!(format!("{edges:?}"), "GraphRef { nodes: <hidden>, edges: &mut [] }");
+}
+
+#[test]
+fn test_reborrow() {
+    let mut graph = Graph { nodes: vec![], edges: vec![] };
+    push_edges(graph.as_refs_mut().partial_borrow(), 3);
+    assert_eq!(graph.edges.len(), 3);
+    assert_eq!(graph.edges[2].to, Some(3));
+}
+
+#[test]
+fn test_identity_partial_borrow() {
+    let mut graph = Graph { nodes: vec![], edges: vec![] };
+    let mut refs = graph.as_refs_mut();
+    let graph2 = refs.partial_borrow::<p!(<mut nodes, mut edges> Graph)>();
+    push_edges(graph2.partial_borrow::<p!(<mut nodes, mut edges> Graph)>().partial_borrow(), 2);
+    assert_eq!(graph2.edges.len(), 2);
+    assert_eq!(graph.edges[1].to, Some(2));
+}
+
+#[test]
+fn test_map_field() {
+    let mut graph = Graph {
+        nodes: vec![],
+        edges: vec![Edge { from: Some(0), to: Some(1) }],
+    };
+    detach_edges_as_slice(graph.as_refs_mut().partial_borrow());
+    assert!(graph.edges[0].from.is_none());
+    assert!(graph.edges[0].to.is_none());
+}
+
+#[test]
+fn test_with_field() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![0], inputs: vec![] }, Node { outputs: vec![], inputs: vec![0] }],
+        edges: vec![Edge { from: Some(0), to: Some(1) }],
+    };
+    assert_eq!(detach_all_nodes_scoped(graph.as_refs_mut().partial_borrow()), 3);
+    assert!(graph.nodes.iter().all(|node| node.outputs.is_empty() && node.inputs.is_empty()));
+    assert!(graph.edges[0].from.is_none());
+}
+
+#[test]
+fn test_extract_as() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![0], inputs: vec![] }],
+        edges: vec![Edge { from: Some(0), to: None }],
+    };
+    let mut refs = graph.as_refs_mut();
+    let (nodes, rest) = refs.extract_nodes_as::<p!(<mut edges> Graph)>();
+    nodes[0].outputs.clear();
+    rest.edges[0].from = None;
+    assert!(graph.nodes[0].outputs.is_empty());
+    assert!(graph.edges[0].from.is_none());
+}
+
+fn swap_nodes(graph: p!(&<mut nodes> Graph), other: p!(&<mut nodes> Graph)) {
+    graph.swap_nodes(other);
+}
+
+#[test]
+fn test_swap_field() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![], inputs: vec![] }],
+        edges: vec![Edge { from: None, to: None }],
+    };
+    let mut other = Graph { nodes: vec![], edges: vec![] };
+    swap_nodes(graph.as_refs_mut().partial_borrow(), other.as_refs_mut().partial_borrow());
+    assert!(graph.nodes.is_empty());
+    assert_eq!(other.nodes.len(), 1);
+    assert_eq!(graph.edges.len(), 1);
+}
+
+#[test]
+fn test_replace_field() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![], inputs: vec![] }],
+        edges: vec![Edge { from: None, to: None }],
+    };
+    let mut refs = graph.as_refs_mut();
+    let ctx = refs.partial_borrow::<p!(<mut nodes, edges> Graph)>();
+    let nodes = ctx.replace_nodes(vec![]);
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(ctx.edges.len(), 1);
+    assert!(graph.nodes.is_empty());
+}
+
+struct NodesView<'t> {
+    ctx: p!(<'t, mut nodes> Graph),
+}
+
+struct EdgesView<'t> {
+    ctx: p!(<'t, mut edges> Graph),
+}
+
+#[test]
+fn test_split_with() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![0], inputs: vec![] }],
+        edges: vec![Edge { from: Some(0), to: None }],
+    };
+    let mut refs = graph.as_refs_mut();
+    let (nodes, edges) = refs.split_with::<p!(<mut nodes> Graph)>();
+    let nodes_view = NodesView { ctx: nodes };
+    let edges_view = EdgesView { ctx: edges };
+    nodes_view.ctx.nodes[0].outputs.clear();
+    edges_view.ctx.edges[0].from = None;
+    assert!(graph.nodes[0].outputs.is_empty());
+    assert!(graph.edges[0].from.is_none());
+}
+
+fn clear_edges(graph: p!(&<mut edges> Graph)) {
+    graph.edges.clear();
+}
+
+// The target of `hide_nodes` is the current set of fields without `nodes`.
+fn hide_and_clear_edges(graph: p!(&<mut nodes, mut edges> Graph)) {
+    clear_edges(graph.hide_nodes());
+    graph.nodes.clear();
+}
+
+#[test]
+fn test_hide_field() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![], inputs: vec![] }],
+        edges: vec![Edge { from: None, to: None }],
+    };
+    hide_and_clear_edges(graph.as_refs_mut().partial_borrow());
+    assert!(graph.nodes.is_empty());
+    assert!(graph.edges.is_empty());
+}
+
+fn clear_all(graph: p!(&<mut *> Graph)) {
+    graph.nodes.clear();
+    graph.edges.clear();
+}
+
+fn split_and_rejoin(graph: p!(&<mut *> Graph)) {
+    let (nodes, rest) = graph.split::<p!(<mut nodes> Graph)>();
+    nodes.nodes.push(Node { outputs: vec![], inputs: vec![] });
+    rest.edges.push(Edge { from: None, to: None });
+    let mut graph2 = nodes.rejoin::<p!(<mut *> Graph)>(rest);
+    clear_all(&mut graph2);
+}
+
+#[test]
+fn test_rejoin() {
+    let mut graph = Graph { nodes: vec![], edges: vec![] };
+    split_and_rejoin(graph.as_refs_mut().partial_borrow());
+    assert!(graph.nodes.is_empty());
+    assert!(graph.edges.is_empty());
+}
+
+#[test]
+fn test_as_refs_each() {
+    let mut graphs = [
+        Graph { nodes: vec![], edges: vec![Edge { from: None, to: None }] },
+        Graph { nodes: vec![], edges: vec![] },
+    ];
+    let mut refs = graphs.as_refs_each::<p!(<mut nodes, edges> Graph)>().collect::<Vec<_>>();
+    for graph in &mut refs {
+        graph.nodes.push(Node { outputs: vec![], inputs: vec![] });
+    }
+    assert_eq!(refs.iter().map(|graph| graph.edges.len()).sum::<usize>(), 1);
+    assert!(graphs.iter().all(|graph| graph.nodes.len() == 1));
+}
+
+#[test]
+fn test_into_tuple() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![0], inputs: vec![] }],
+        edges: vec![Edge { from: Some(0), to: None }],
+    };
+    let mut refs = graph.as_refs_mut();
+
+    // Fields are returned in the declaration order, regardless of the selector order.
+    let mixed = refs.partial_borrow::<p!(<mut edges, nodes> Graph)>();
+    let (nodes, edges): (&Vec<Node>, &mut Vec<Edge>) = mixed.into_tuple();
+    edges[0].to = Some(nodes.len());
+
+    // Hidden fields are skipped.
+    let only_edges = refs.partial_borrow::<p!(<edges> Graph)>();
+    let (edges,): (&Vec<Edge>,) = only_edges.into_tuple();
+    assert_eq!(edges[0].to, Some(1));
+
+    let none = refs.partial_borrow::<p!(<> Graph)>();
+    let (): () = none.into_tuple();
+}
+
+#[test]
+fn test_view() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![], inputs: vec![] }],
+        edges: vec![Edge { from: Some(0), to: None }],
+    };
+    let mut refs = graph.as_refs_mut();
+    let edges_ctx = refs.partial_borrow::<p!(<mut edges> Graph)>();
+    let mut view = edges_ctx.view();
+    view.edges().push(Edge { from: None, to: Some(0) });
+    assert_eq!(view.edges().len(), 2);
+}
+
+#[test]
+fn test_for_each_in_field() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![0], inputs: vec![] }, Node { outputs: vec![], inputs: vec![0] }],
+        edges: vec![Edge { from: Some(0), to: Some(1) }],
+    };
+    detach_all_nodes_for_each(graph.as_refs_mut().partial_borrow());
+    assert!(graph.nodes.iter().all(|node| node.outputs.is_empty() && node.inputs.is_empty()));
+    assert!(graph.edges[0].from.is_none());
+    assert!(graph.edges[0].to.is_none());
+}
+
+#[test]
+fn test_downgrade() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![0], inputs: vec![] }],
+        edges: vec![Edge { from: None, to: Some(0) }],
+    };
+    assert_eq!(count_detached_edges(graph.as_refs_mut().partial_borrow()), 2);
+}
+
+#[test]
+fn test_clone_fields() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![0], inputs: vec![] }],
+        edges: vec![Edge { from: Some(0), to: None }],
+    };
+    let mut refs = graph.as_refs_mut();
+    // Hidden fields are excluded from the snapshot.
+    let snapshot: GraphRef<Vec<Node>, borrow::Hidden<Vec<Edge>>> =
+        refs.clone_fields::<p!(<mut nodes> Graph)>();
+    refs.nodes.clear();
+    assert_eq!(snapshot.nodes[0].outputs, vec![0]);
+    assert!(graph.nodes.is_empty());
+}
+
+#[test]
+fn test_ref_struct_layout() {
+    use borrow::Hidden;
+    use core::mem::align_of;
+    use core::mem::size_of;
+    type NodesMut<'t> = GraphRef<&'t mut Vec<Node>, Hidden<Vec<Edge>>>;
+    type EdgesMut<'t> = GraphRef<Hidden<Vec<Node>>, &'t mut Vec<Edge>>;
+    assert_eq!(size_of::<NodesMut<'_>>(), size_of::<EdgesMut<'_>>());
+    assert_eq!(align_of::<NodesMut<'_>>(), align_of::<EdgesMut<'_>>());
+    assert_eq!(core::mem::offset_of!(NodesMut<'_>, edges), core::mem::offset_of!(EdgesMut<'_>, edges));
+}
+
+#[test]
+fn test_zero_size_overhead() {
+    use core::mem::size_of;
+    // A partial borrow is as big as the references to the borrowed fields passed separately.
+    assert_eq!(size_of::<p!(<mut nodes, edges> Graph)>(), 2 * size_of::<&Graph>());
+}
+
+#[test]
+fn test_named_field_lifetime() {
+    let mut graph = Graph {
+        nodes: vec![],
+        edges: vec![Edge { from: Some(0), to: Some(1) }],
+    };
+    // The ref struct is a temporary, while the edge borrows `graph.edges`.
+    let edge = graph.as_refs::<p!(<mut edges> Graph)>().last_edge();
+    if let Some(edge) = edge {
+        edge.to = None;
+    }
+    assert!(graph.edges[0].to.is_none());
+}
+
+#[test]
+fn test_union_lifetimes() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![], inputs: vec![] }],
+        edges: vec![],
+    };
+    let mut refs = graph.as_refs_mut();
+    add_edges_per_node(refs.partial_borrow());
+    add_edges_per_node(refs.partial_borrow_union::<NodesCtx<'_>, NodesEdgesCtx<'_>>());
+    assert_eq!(graph.edges.len(), 2);
+}
+
+#[test]
+fn test_sequential_extract() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![0], inputs: vec![] }],
+        edges: vec![Edge { from: Some(0), to: None }],
+    };
+    clear_sequentially(&mut graph.as_refs_mut());
+    assert_eq!(graph.nodes.len(), 1);
+    assert!(graph.edges.is_empty());
+    graph.as_refs_mut().clear_sequentially();
+    assert!(graph.nodes.is_empty());
+}
+
+// Generic over any struct deriving `PartialBorrow`.
+fn describe<T: Reflect>() -> String {
+    format!("{} fields: {}", T::FIELD_COUNT, T::FIELD_NAMES.join(", "))
+}
+
+#[test]
+fn test_reflect() {
+    assert_eq!(describe::<Graph>(), "2 fields: nodes, edges");
+}
+
+const _: () = assert!(<p!(<mut nodes> Graph)>::BORROW_MASK[0].is_mut());
+
+#[test]
+fn test_borrow_shape() {
+    assert_eq!(<p!(<mut nodes, edges> Graph)>::BORROW_MASK, [Access::Mut, Access::Shared]);
+    assert_eq!(<p!(<edges> Graph)>::BORROW_MASK, [Access::Hidden, Access::Shared]);
+    assert_eq!(format!("{:?}", <p!(<mut edges> Graph)>::BORROW_MASK), "[Hidden, Mut]");
+}
+
+#[test]
+fn test_ref_as_refs() {
+    let mut graph = Graph { nodes: vec![], edges: vec![Edge { from: None, to: None }] };
+    if let Some(edge) = last_edge_from(&mut graph.as_refs_mut()) {
+        edge.from = Some(1);
+    }
+    assert_eq!(graph.edges[0].from, Some(1));
+
+    let mut refs = graph.as_refs_mut();
+    let edges = refs.partial_borrow::<p!(<mut edges> Graph)>();
+    // The `nodes` field is hidden in `edges`, so it stays hidden in the new borrow.
+    let view = edges.as_refs::<p!(<edges> Graph)>();
+    assert_eq!(format!("{view:?}"), "GraphRef { nodes: <hidden>, edges: &[Edge { from: Some(1), to: None }] }");
+}
+
+// Generic over anything convertible to a mutable borrow of all `graph` fields.
+fn clear_all_from<'t>(graph: impl Into<p!(<'t, mut *> Graph)>) {
+    let graph = graph.into();
+    graph.nodes.clear();
+    graph.edges.clear();
+}
+
+#[test]
+fn test_from_mut_ref() {
+    let mut graph = Graph { nodes: vec![Node { outputs: vec![], inputs: vec![] }], edges: vec![] };
+    let refs: p!(<mut *> Graph) = (&mut graph).into();
+    refs.edges.push(Edge { from: None, to: None });
+    clear_all_from(&mut graph);
+    assert!(graph.nodes.is_empty());
+    assert!(graph.edges.is_empty());
+}
+
+#[test]
+fn test_downgrade_all_fields() {
+    let mut graph = Graph { nodes: vec![], edges: vec![Edge { from: Some(0), to: None }] };
+    let mut refs = graph.as_refs_mut();
+    // All fields are borrowed immutably at once.
+    let shared = refs.partial_borrow::<p!(<*> Graph)>();
+    assert_eq!(count_connections(shared), 1);
+    let (shared, rest) = refs.split::<p!(<*> Graph)>();
+    assert_eq!(count_connections(shared), 1);
+    assert_eq!(format!("{rest:?}"), "GraphRef { nodes: &[], edges: &[Edge { from: Some(0), to: None }] }");
+}
+
+// Requires mutable access to the `graph.edges` field and immutable access to `graph.nodes`.
+fn detach_edges_of_empty_nodes(graph: p!(&<nodes, mut edges> Graph)) {
+    let empty = graph.nodes_ref().iter().filter(|node| node.outputs.is_empty()).count();
+    for edge in graph.edges_mut().iter_mut().take(empty) {
+        edge.from = None;
+    }
+}
+
+#[test]
+fn test_field_accessors() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![], inputs: vec![] }],
+        edges: vec![Edge { from: Some(0), to: None }, Edge { from: Some(0), to: None }],
+    };
+    detach_edges_of_empty_nodes(graph.as_refs_mut().partial_borrow());
+    assert_eq!(graph.edges[0].from, None);
+    assert_eq!(graph.edges[1].from, Some(0));
+    assert_eq!(graph.as_refs_mut().edges_ref().len(), 2);
+}
+
+#[test]
+fn test_into_iterator() {
+    let mut graph = Graph { nodes: vec![], edges: vec![Edge { from: Some(0), to: Some(1) }] };
+    let mut refs = graph.as_refs_mut();
+    let edges = refs.partial_borrow::<p!(<mut edges> Graph)>();
+    for edge in &mut *edges {
+        edge.from = None;
+    }
+    let mut count = 0;
+    for edge in graph.as_shared_refs::<p!(<edges> Graph)>() {
+        assert_eq!(edge.from, None);
+        count += 1;
+    }
+    assert_eq!(count, 1);
+}
+
+// Requires mutable access to the `graph.edges` field, which is indexed directly.
+fn edge_mut<'t>(graph: p!(&'t <'t, mut edges> Graph), edge_id: EdgeId) -> &'t mut Edge {
+    &mut graph[edge_id]
+}
+
+#[test]
+fn test_index() {
+    let mut graph = Graph { nodes: vec![], edges: vec![Edge { from: Some(0), to: Some(1) }] };
+    edge_mut(graph.as_refs_mut().partial_borrow(), 0).from = None;
+    let edges = graph.as_shared_refs::<p!(<edges> Graph)>();
+    assert_eq!(edges[0].from, None);
+    assert_eq!(edges[0].to, Some(1));
+}
+
+borrow::assert_same_fields!(p!(<ref nodes, mut edges> Graph), p!(<nodes, mut edges> Graph));
+borrow::assert_same_fields!(p!(<mut *, ref nodes> Graph), p!(<mut *, nodes> Graph));
+borrow::assert_same_fields!(p!(<ref *> Graph), p!(<*> Graph));
+// Fields selected with `*` or hidden with `!` can be selected again, as opposed to fields named
+// twice, which are reported.
+borrow::assert_same_fields!(p!(<mut *, !nodes, nodes> Graph), p!(<nodes, mut edges> Graph));
+borrow::assert_same_fields!(p!(<*, !edges, mut edges, !edges> Graph), p!(<nodes> Graph));
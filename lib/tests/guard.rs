@@ -0,0 +1,57 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::sync::RwLock;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Ctx {
+    pub scene: Vec<usize>,
+    pub log: Vec<String>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+// Requires mutable access to `ctx.scene`.
+fn add_mesh(ctx: p!(&<mut scene> Ctx)) {
+    ctx.scene.push(ctx.scene.len());
+}
+
+// Requires immutable access to `ctx.scene`.
+fn count_meshes(ctx: p!(<scene> Ctx)) -> usize {
+    ctx.scene.len()
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_borrow_from_mutex_guard() {
+    let ctx = Mutex::new(Ctx::default());
+    let mut guard = ctx.lock().unwrap_or_else(PoisonError::into_inner);
+    add_mesh(guard.as_refs_mut().partial_borrow());
+    let mut scene = guard.as_refs::<p!(<mut scene> Ctx)>();
+    add_mesh(&mut scene);
+    assert_eq!(count_meshes(guard.as_shared_refs()), 2);
+    drop(guard);
+    assert_eq!(ctx.into_inner().unwrap_or_else(PoisonError::into_inner).scene, vec![0, 1]);
+}
+
+#[test]
+fn test_borrow_from_rw_lock_guards() {
+    let ctx = RwLock::new(Ctx::default());
+    add_mesh(&mut ctx.write().unwrap_or_else(PoisonError::into_inner).as_refs());
+    let guard = ctx.read().unwrap_or_else(PoisonError::into_inner);
+    assert_eq!(count_meshes(guard.as_shared_refs()), 1);
+}
@@ -0,0 +1,59 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Graph {
+    pub nodes: Vec<usize>,
+    pub edges: Vec<(usize, usize)>,
+    pub log: Vec<String>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+// Requires mutable access to `graph.nodes` and `graph.edges`, and immutable access to `graph.log`.
+fn connect(graph: p!(&<mut nodes, mut edges, log> Graph)) {
+    let count = graph.nodes.len();
+    graph.nodes.push(count);
+    graph.edges.push((count, count + graph.log.len()));
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_hidden_refs_type() {
+    let mut graph = Graph::default();
+    let hidden: p!(<!*> Graph) = graph.as_hidden_refs();
+    assert_eq!(format!("{hidden:?}"), "GraphRef { nodes: <hidden>, edges: <hidden>, log: <hidden> }");
+    // The hidden borrow doesn't keep the graph borrowed.
+    graph.log.push("init".to_string());
+}
+
+#[test]
+fn test_hidden_refs_as_join_seed() {
+    let mut graph = Graph { log: vec!["init".to_string()], ..Default::default() };
+    let mut seed = graph.as_hidden_refs();
+    let mut refs = graph.as_refs_mut();
+    let (nodes, rest) = refs.split::<p!(<mut nodes> Graph)>();
+    let (edges, rest) = rest.split::<p!(<mut edges> Graph)>();
+    let log = rest.partial_borrow::<p!(<log> Graph)>();
+    // Joining the seed with a borrow gives that borrow back.
+    let mut acc: p!(<mut nodes> Graph) = seed.join(nodes);
+    let mut acc: p!(<mut nodes, mut edges> Graph) = acc.join(edges);
+    let mut acc: p!(<mut nodes, mut edges, log> Graph) = acc.join(log);
+    connect(&mut acc);
+    connect(&mut acc);
+    assert_eq!(graph.nodes, vec![0, 1]);
+    assert_eq!(graph.edges, vec![(0, 1), (1, 2)]);
+}
@@ -0,0 +1,75 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Scene {
+    pub meshes: Vec<usize>,
+    pub lights: Vec<usize>,
+}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+#[group(topology = nodes, edges)]
+pub struct Graph {
+    pub nodes: Vec<usize>,
+    pub edges: Vec<(usize, usize)>,
+    pub scene: Scene,
+    pub log: Vec<String>,
+}
+
+// The leading lifetime is used by every field without its own one, so these aliases can be
+// stored in structs, where `'_` is not allowed.
+type PathFind<'t, 'm> = p!(<'t, nodes, edges, 'm mut log> Graph);
+type Topology<'t> = p!(<'t, mut @topology, scene(lights)> Graph);
+
+struct Pass<'t> {
+    name: &'static str,
+    ctx: PathFind<'t, 't>,
+}
+
+struct Rebuild<'t, 's> {
+    ctx: &'s mut Topology<'t>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+impl<'t> Pass<'t> {
+    fn run(self) -> usize {
+        let count = self.ctx.edges.iter().filter(|(a, b)| self.ctx.nodes.contains(a) && self.ctx.nodes.contains(b)).count();
+        self.ctx.log.push(format!("{}: {count}", self.name));
+        count
+    }
+}
+
+impl<'t, 's> Rebuild<'t, 's> {
+    fn run(self) {
+        let lights = self.ctx.scene.get().lights.len();
+        self.ctx.nodes.extend(0..lights);
+        self.ctx.edges.extend((1..lights).map(|i| (i - 1, i)));
+    }
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_lifetime_in_struct_field() {
+    let mut graph = Graph { scene: Scene { lights: vec![0; 3], ..Default::default() }, ..Default::default() };
+    let mut refs = graph.as_refs_mut();
+    Rebuild { ctx: refs.partial_borrow() }.run();
+    let ctx: PathFind = graph.as_refs();
+    assert_eq!(Pass { name: "connected", ctx }.run(), 2);
+    assert_eq!(graph.nodes, vec![0, 1, 2]);
+    assert_eq!(graph.log, vec!["connected: 2".to_string()]);
+}
@@ -0,0 +1,61 @@
+#![allow(dead_code)]
+
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+use physics::Ctx as PhysicsCtx;
+use render::Ctx as RenderCtx;
+
+// ============
+// === Data ===
+// ============
+
+// Both structs are named `Ctx`. Their selector macros are not `#[macro_export]`ed, so they don't
+// collide in the crate root.
+mod render {
+    use borrow::PartialBorrow;
+
+    #[derive(Debug, Default, PartialBorrow)]
+    #[module(crate::render)]
+    pub(crate) struct Ctx {
+        pub frames: Vec<usize>,
+        pub log: Vec<String>,
+    }
+}
+
+mod physics {
+    use borrow::PartialBorrow;
+
+    #[derive(Debug, Default, PartialBorrow)]
+    #[module(crate::physics)]
+    #[borrow(macro_vis = pub(crate))]
+    pub struct Ctx {
+        pub bodies: Vec<usize>,
+        pub log: Vec<String>,
+    }
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn render_frame(ctx: p!(&<mut frames, log> RenderCtx)) {
+    ctx.frames.push(ctx.log.len());
+}
+
+fn add_body(ctx: p!(&<mut bodies> PhysicsCtx)) {
+    ctx.bodies.push(0);
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_macro_vis() {
+    let mut render = RenderCtx::default();
+    let mut physics = PhysicsCtx::default();
+    render_frame(render.as_refs_mut().partial_borrow());
+    add_body(physics.as_refs_mut().partial_borrow());
+    assert_eq!(render.frames, vec![0]);
+    assert_eq!(physics.bodies, vec![0]);
+}
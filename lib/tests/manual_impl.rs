@@ -0,0 +1,94 @@
+#![allow(dead_code)]
+
+use borrow::AsRefs;
+use borrow::AsRefsSource;
+use borrow::AsSharedRefs;
+use borrow::FromFields;
+use borrow::HList;
+use borrow::HasFields;
+use borrow::Hidden;
+use borrow::traits::*;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+// ============
+// === Data ===
+// ============
+
+// Stands for a handle that can't be part of a struct with derived partial borrows.
+#[derive(Debug, Default)]
+pub struct Handle {
+    pub id: usize,
+}
+
+// A manually written ref struct, following the contract of `HasFields`.
+#[repr(C)]
+pub struct DeviceRef<Input, Output> {
+    pub input: Input,
+    pub output: Output,
+}
+
+unsafe impl<Input, Output> HasFields for DeviceRef<Input, Output> {
+    type Fields = HList![Input, Output];
+}
+
+unsafe impl<Input, Output, InputTarget, OutputTarget>
+FromFields<HList![InputTarget, OutputTarget]> for DeviceRef<Input, Output> {
+    type Result = DeviceRef<InputTarget, OutputTarget>;
+}
+
+// A struct owning the fields, creating the manual ref struct with `as_refs` and `as_shared_refs`.
+#[derive(Debug, Default)]
+pub struct Device {
+    pub input: Handle,
+    pub output: Handle,
+}
+
+impl<'t> AsRefs<'t, DeviceRef<&'t mut Handle, &'t Handle>> for Device {
+    fn as_refs_impl(&'t mut self) -> DeviceRef<&'t mut Handle, &'t Handle> {
+        DeviceRef { input: &mut self.input, output: &self.output }
+    }
+}
+
+impl<'t> AsSharedRefs<'t, DeviceRef<&'t Handle, &'t Handle>> for Device {
+    fn as_shared_refs_impl(&'t self) -> DeviceRef<&'t Handle, &'t Handle> {
+        DeviceRef { input: &self.input, output: &self.output }
+    }
+}
+
+impl AsRefsSource for Device {}
+
+// =============
+// === Utils ===
+// =============
+
+fn connect(device: &mut DeviceRef<&mut Handle, &Handle>) {
+    device.input.id = device.output.id;
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_manual_impl() {
+    let mut input = Handle { id: 0 };
+    let mut output = Handle { id: 1 };
+    let mut device = DeviceRef { input: &mut input, output: &mut output };
+    connect(device.partial_borrow());
+
+    let (input_ref, rest) = device.split::<DeviceRef<&mut Handle, Hidden<Handle>>>();
+    rest.output.id = 2;
+    input_ref.input.id += 10;
+    assert_eq!(input.id, 11);
+    assert_eq!(output.id, 2);
+}
+
+#[test]
+fn test_manual_as_refs() {
+    let device = Mutex::new(Device { input: Handle { id: 0 }, output: Handle { id: 1 } });
+    let mut guard = device.lock().unwrap_or_else(PoisonError::into_inner);
+    connect(&mut guard.as_refs());
+    let refs: DeviceRef<&Handle, &Handle> = guard.as_shared_refs();
+    assert_eq!(refs.input.id, 1);
+}
@@ -0,0 +1,76 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Scene {
+    pub meshes: Vec<usize>,
+    pub lights: Vec<usize>,
+}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Ctx {
+    pub scene: Scene,
+    pub log: Vec<String>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+// Requires mutable access to `ctx.log` and `ctx.scene.lights`, and immutable access to
+// `ctx.scene.meshes`.
+fn add_light(ctx: p!(&<mut log, scene(mut lights, meshes)> Ctx)) {
+    let scene = ctx.scene.get();
+    scene.lights.push(scene.meshes.len());
+    ctx.log.push("add_light".to_string());
+}
+
+// Requires mutable access to `ctx.scene.meshes`.
+fn add_mesh(ctx: p!(&<scene(mut meshes)> Ctx)) {
+    ctx.scene.get().meshes.push(0);
+}
+
+// Requires mutable access to all `ctx` fields.
+fn update(ctx: p!(&<mut *> Ctx)) {
+    add_mesh(ctx.partial_borrow());
+    // The `ctx2` variable has `ctx.scene.meshes` hidden.
+    let (lights_ctx, ctx2) = ctx.split::<p!(<scene(mut lights)> Ctx)>();
+    lights_ctx.scene.get().lights.clear();
+    ctx2.log.push("clear_lights".to_string());
+    add_light(ctx.partial_borrow());
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_nested() {
+    let mut ctx = Ctx::default();
+    update(ctx.as_refs_mut().partial_borrow());
+    update(ctx.as_refs_mut().partial_borrow());
+    assert_eq!(ctx.scene.meshes, vec![0, 0]);
+    assert_eq!(ctx.scene.lights, vec![2]);
+    assert_eq!(ctx.log.len(), 4);
+}
+
+#[test]
+fn test_nested_debug() {
+    let mut ctx = Ctx::default();
+    let mut refs = ctx.as_refs_mut();
+    let nested = refs.partial_borrow::<p!(<scene(lights)> Ctx)>();
+    assert_eq!(format!("{nested:?}"), "CtxRef { scene: <nested>, log: <hidden> }");
+}
+
+borrow::assert_same_fields!(p!(<scene(mut lights, meshes)> Ctx), p!(<scene(meshes, mut lights)> Ctx));
+borrow::assert_disjoint!(p!(<mut log> Ctx), p!(<scene(mut lights)> Ctx));
+borrow::assert_disjoint!(p!(<scene(mut lights, meshes)> Ctx), p!(<scene(meshes)> Ctx));
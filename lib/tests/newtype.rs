@@ -0,0 +1,50 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Ctx {
+    pub scene: Vec<usize>,
+    pub log: Vec<String>,
+}
+
+// A domain-specific wrapper forwarding partial borrows to `Ctx`.
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+#[borrow(deref)]
+pub struct App(Ctx);
+
+// =============
+// === Utils ===
+// =============
+
+fn add_to_scene(app: p!(&<mut scene, log> App)) {
+    app.scene.push(app.log.len());
+}
+
+fn log(ctx: p!(&<mut log> Ctx), msg: &str) {
+    ctx.log.push(msg.to_string());
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_newtype() {
+    let mut app = App::default();
+    let mut refs = app.as_refs_mut();
+    log(refs.partial_borrow(), "init");
+    add_to_scene(refs.partial_borrow());
+    add_to_scene(&mut app.as_refs());
+    let shared = app.as_shared_refs::<p!(<scene> App)>();
+    assert_eq!(*shared.scene, vec![1, 1]);
+    assert_eq!(app.0.log, vec!["init".to_string()]);
+}
@@ -0,0 +1,47 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Clone, Debug, Default, PartialBorrow)]
+#[module(crate)]
+#[borrow(no_clone)]
+pub struct Ctx {
+    pub names: Vec<String>,
+    pub count: usize,
+}
+
+/// Would conflict with the derived `Clone` impl without `#[borrow(no_clone)]`.
+impl<'t> Clone for CtxRef<&'t Vec<String>, &'t usize> {
+    fn clone(&self) -> Self {
+        Self { names: self.names, count: self.count }
+    }
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn add_name(ctx: p!(&<mut names, mut count> Ctx), name: &str) {
+    ctx.names.push(name.to_string());
+    *ctx.count += 1;
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_manual_clone() {
+    let mut ctx = Ctx::default();
+    add_name(ctx.as_refs_mut().partial_borrow(), "a");
+    let refs = ctx.as_refs();
+    let copy = refs.clone();
+    assert_eq!(*copy.names, vec!["a".to_string()]);
+    assert_eq!(*refs.count, 1);
+}
@@ -0,0 +1,44 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[group(stats = hits, misses)]
+pub struct Cache {
+    pub entries: Vec<(usize, String)>,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn lookup(cache: p!(&<mut *> Cache), key: usize) -> Option<String> {
+    let (entries, cache2) = cache.extract_entries();
+    let value = entries.iter().find(|(k, _)| *k == key).map(|(_, v)| v.clone());
+    record(cache2.partial_borrow(), value.is_some());
+    value
+}
+
+fn record(cache: p!(&<mut @stats> Cache), hit: bool) {
+    if hit { *cache.hits += 1 } else { *cache.misses += 1 }
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_derive_without_module_attr() {
+    let mut cache = Cache { entries: vec![(1, "one".to_string())], ..Default::default() };
+    assert_eq!(lookup(cache.as_refs_mut().partial_borrow(), 1), Some("one".to_string()));
+    assert_eq!(lookup(cache.as_refs_mut().partial_borrow(), 2), None);
+    assert_eq!((cache.hits, cache.misses), (1, 1));
+}
@@ -0,0 +1,40 @@
+//! The generated code must not refer to `std`, so that it can be used in `no_std` crates. The test
+//! harness links `std` anyway, but it is not available by name in this crate.
+
+#![no_std]
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+struct Particle {
+    position: [f32; 2],
+    velocity: [f32; 2],
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn step(particle: p!(&<mut position, velocity> Particle)) {
+    particle.position[0] += particle.velocity[0];
+    particle.position[1] += particle.velocity[1];
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_no_std() {
+    let mut particle = Particle { position: [0.0, 0.0], velocity: [1.0, 2.0] };
+    step(particle.as_refs_mut().partial_borrow());
+    assert_eq!(particle.position, [1.0, 2.0]);
+}
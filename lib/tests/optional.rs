@@ -0,0 +1,88 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Clone, Debug, Default)]
+pub struct Physics {
+    pub steps: usize,
+}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Ctx {
+    pub log: Vec<String>,
+    #[borrow(optional)]
+    pub physics: Option<Physics>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+// Requires mutable access to the optional `physics` field, borrowed as `Option<&mut Physics>`.
+fn step(ctx: p!(&<mut physics> Ctx)) {
+    if let Some(physics) = ctx.physics.as_deref_mut() {
+        physics.steps += 1;
+    }
+}
+
+fn update(ctx: p!(&<mut *> Ctx)) {
+    step(ctx.partial_borrow());
+    let (physics, ctx2) = ctx.extract_physics();
+    let steps = physics.map_or(0, |physics| physics.steps);
+    ctx2.log.push(format!("steps: {steps}"));
+}
+
+fn steps(ctx: p!(&<physics> Ctx)) -> Option<usize> {
+    ctx.physics.map(|physics| physics.steps)
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_optional_some() {
+    let mut ctx = Ctx { log: vec![], physics: Some(Physics::default()) };
+    update(ctx.as_refs_mut().partial_borrow());
+    update(ctx.as_refs_mut().partial_borrow());
+    assert_eq!(steps(ctx.as_refs_mut().partial_borrow()), Some(2));
+    assert_eq!(ctx.log, vec!["steps: 1", "steps: 2"]);
+}
+
+#[test]
+fn test_optional_none() {
+    let mut ctx = Ctx::default();
+    update(ctx.as_refs_mut().partial_borrow());
+    assert_eq!(steps(ctx.as_refs_mut().partial_borrow()), None);
+    assert_eq!(ctx.log, vec!["steps: 0"]);
+}
+
+#[test]
+fn test_optional_debug_and_clone() {
+    let mut ctx = Ctx { log: vec![], physics: Some(Physics { steps: 3 }) };
+    let mut refs = ctx.as_refs_mut();
+    let borrow = refs.partial_borrow::<p!(<physics> Ctx)>();
+    assert_eq!(format!("{borrow:?}"), "CtxRef { log: <hidden>, physics: Some(Physics { steps: 3 }) }");
+    let owned = borrow.clone_fields::<p!(<physics> Ctx)>();
+    assert_eq!(owned.physics.map(|physics| physics.steps), Some(3));
+    let shared = ctx.as_shared_refs::<p!(<*> Ctx)>();
+    assert_eq!(shared.physics.map(|physics| physics.steps), Some(3));
+}
+
+#[test]
+fn test_optional_union() {
+    let mut ctx = Ctx { log: vec![], physics: Some(Physics { steps: 1 }) };
+    let mut refs = ctx.as_refs_mut();
+    let (physics, log) = refs.split::<p!(<mut physics> Ctx)>();
+    let mut joined = log.join(physics);
+    step(joined.partial_borrow());
+    joined.log.push("joined".to_string());
+    assert_eq!(ctx.physics.map(|physics| physics.steps), Some(2));
+}
@@ -0,0 +1,49 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+use core::marker::PhantomPinned;
+use core::pin::Pin;
+use core::pin::pin;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default)]
+pub struct Task {
+    pub step: usize,
+    _pinned: PhantomPinned,
+}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Ctx {
+    pub task: Task,
+    pub log: Vec<String>,
+    pub steps: usize,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn log_step(ctx: p!(&<mut log, mut steps, task> Ctx)) {
+    *ctx.steps += 1;
+    ctx.log.push(format!("step {}", ctx.task.step));
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_as_refs_pin_mut() {
+    let mut ctx: Pin<&mut Ctx> = pin!(Ctx::default());
+    log_step(&mut ctx.as_mut().as_refs_pin_mut());
+    log_step(&mut ctx.as_mut().as_refs_pin_mut::<p!(<mut log, mut steps, task> Ctx)>());
+    let refs = ctx.as_mut().as_refs_pin_mut::<p!(<steps> Ctx)>();
+    assert_eq!(*refs.steps, 2);
+    assert_eq!(ctx.log, vec!["step 0".to_string(), "step 0".to_string()]);
+}
@@ -0,0 +1,72 @@
+#![allow(dead_code)]
+
+use std::fmt::Debug;
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default)]
+pub struct GeometryCtx {
+    pub data: Vec<String>,
+}
+
+#[derive(Debug, PartialBorrow)]
+#[module(crate)]
+pub struct Ctx<'v, V: Debug> {
+    pub version: &'v V,
+    pub geometry: GeometryCtx,
+    pub history: Vec<V>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn pass1<'v, V: Debug + Clone>(ctx: p!(&<mut *> Ctx<'v, V>)) -> usize {
+    let (version, ctx2) = ctx.extract_version();
+    pass2(ctx2.partial_borrow(), version)
+}
+
+fn pass2<V: Debug + Clone>(ctx: p!(&<mut history, geometry> Ctx<'_, V>), version: &V) -> usize {
+    ctx.history.push(version.clone());
+    ctx.geometry.data.len()
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_extract_generic_field() {
+    let version = 7;
+    let mut ctx = Ctx {
+        version: &version,
+        geometry: GeometryCtx { data: vec!["geo1".to_string()] },
+        history: vec![],
+    };
+    let count = pass1(ctx.as_refs_mut().partial_borrow());
+    assert_eq!(count, 1);
+    assert_eq!(ctx.history, vec![7]);
+}
+
+fn count_geometry<'t, 'v, V: Debug + Clone>(ctx: &'t mut Ctx<'v, V>) -> usize {
+    let mut refs = ctx.as_refs_mut();
+    pass1(refs.partial_borrow())
+}
+
+fn count_geometry_shared<'t, 'v, V: Debug + Clone>(ctx: &'t mut Ctx<'v, V>) -> &'t GeometryCtx {
+    let refs: p!(<version, geometry> Ctx<'v, V>) = ctx.as_refs();
+    refs.geometry
+}
+
+#[test]
+fn test_tighter_lifetime() {
+    let version = 7;
+    let mut ctx = Ctx { version: &version, geometry: GeometryCtx::default(), history: vec![] };
+    assert_eq!(count_geometry(&mut ctx), 0);
+    assert!(count_geometry_shared(&mut ctx).data.is_empty());
+}
@@ -0,0 +1,58 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+#[group(kinds = r#type, r#match)]
+pub struct Token {
+    pub r#type: Vec<u8>,
+    pub r#match: Vec<u8>,
+    pub text: String,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn push_type(token: p!(&<mut r#type> Token), value: u8) {
+    token.r#type.push(value);
+}
+
+fn copy_type_to_match(token: p!(&<mut *> Token)) {
+    let (ty, token2) = token.extract_type();
+    token2.r#match.extend_from_slice(ty);
+}
+
+fn clear_kinds(token: p!(&<mut @kinds> Token)) {
+    token.view().r#type().clear();
+    token.map_match(|m| &mut m[..]).r#match.fill(0);
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_raw_ident() {
+    let mut token = Token::default();
+    push_type(token.as_refs_mut().partial_borrow(), 1);
+    copy_type_to_match(token.as_refs_mut().partial_borrow());
+    assert_eq!(token.r#match, vec![1]);
+    clear_kinds(token.as_refs_mut().partial_borrow());
+    assert!(token.r#type.is_empty());
+    assert_eq!(token.r#match, vec![0]);
+    assert_eq!(format!("{:?}", token.as_shared_refs::<p!(<r#type> Token)>()), "TokenRef { type: &[], match: <hidden>, text: <hidden> }");
+}
+
+#[test]
+fn test_raw_ident_reflect() {
+    use borrow::Reflect;
+    assert_eq!(Token::FIELD_NAMES, ["type", "match", "text"]);
+}
@@ -0,0 +1,46 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+/// Unrelated type that would collide with the default name of the generated ref struct.
+pub struct CtxRef;
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+#[borrow(ref_name = CtxView)]
+pub struct Ctx {
+    pub names: Vec<String>,
+    pub count: usize,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn add_name(ctx: p!(&<mut names, mut count> Ctx), name: &str) {
+    ctx.names.push(name.to_string());
+    *ctx.count += 1;
+}
+
+fn names_view<'t>(ctx: p!(&'t <'t, names> Ctx)) -> &'t mut CtxView<&'t Vec<String>, borrow::Hidden<usize>> {
+    ctx
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_custom_ref_name() {
+    let mut ctx = Ctx::default();
+    add_name(ctx.as_refs_mut().partial_borrow(), "a");
+    let CtxView { names, count } = ctx.as_refs_mut();
+    assert_eq!(*names, vec!["a".to_string()]);
+    assert_eq!(*count, 1);
+}
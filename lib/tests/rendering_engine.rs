@@ -3,11 +3,18 @@
 mod data;
 
 use data::Ctx;
+use data::CtxRef;
+use data::GeometryCtx;
+use data::MaterialCtx;
+use data::MeshCtx;
+use data::SceneCtx;
+use borrow::Hidden;
+use borrow::PartialBorrow;
 use borrow::partial_borrow as p;
 
 use borrow::traits::*;
-use borrow::UnifyImpl;
 use borrow::Union;
+use borrow::Joined;
 
 // =============
 // === Tests ===
@@ -18,6 +25,27 @@ fn test_types() {
     let mut ctx = Ctx::mock();
     render_pass1(ctx.as_refs_mut().partial_borrow());
     render_pass1_explicit(ctx.as_refs_mut().partial_borrow());
+    render_pass1_alt(ctx.as_refs_mut().partial_borrow());
+    render_pass_joined(ctx.as_refs_mut().partial_borrow());
+    render_pass_extracted(ctx.as_refs_mut().partial_borrow());
+    render_pass_split3(ctx.as_refs_mut().partial_borrow());
+    render_pass_scoped(ctx.as_refs_mut().partial_borrow());
+    render_pass_union(ctx.as_refs_mut().partial_borrow());
+    let mut refs = ctx.as_refs_mut();
+    let (scene_ctx, rest) = refs.split::<RenderCtx>();
+    assert_eq!(glyph_render_pass(scene_ctx, rest.partial_borrow()), 3);
+    assert!(ctx.geometry.data[0].label.ends_with('+'));
+    assert_eq!(count_scenes(ctx.as_refs_mut().partial_borrow()), 1);
+    clear_scenes(ctx.as_refs_mut().partial_borrow());
+    assert!(ctx.scene.data.is_empty());
+}
+
+#[test]
+fn test_groups() {
+    let mut ctx = GroupedCtx::from(Ctx::mock());
+    render_pass_group(ctx.as_refs_mut().partial_borrow());
+    assert!(ctx.geometry.data.iter().all(|geometry| geometry.label.ends_with('@')));
+    assert!(ctx.material.data.iter().all(|material| material.label.ends_with('@')));
 }
 
 fn render_pass1(ctx: p!(&<mut *> Ctx)) {
@@ -42,14 +70,205 @@ fn render_pass1_explicit(ctx: p!(&<mut *> Ctx)) {
     render_pass2(&mut merged_ctx);
 }
 
+// Like `render_pass1_explicit`, but joins the borrows, so that borrowing the same field mutably in
+// both of them fails to compile. The joined borrow is owned and borrows all fields mutably.
+fn render_pass1_alt(ctx: p!(&<mut *> Ctx)) {
+    let (scene_ctx, ctx2) = ctx.split::<p!(<mut scene> Ctx)>();
+    for scene in &scene_ctx.scene.data {
+        for mesh in &scene.meshes {
+            render_scene(ctx2.partial_borrow(), *mesh)
+        }
+    }
+    let mut merged_ctx = ctx2.join(scene_ctx);
+    render_pass2(&mut merged_ctx);
+}
+
+fn render_pass_joined(ctx: p!(&<mut *> Ctx)) {
+    let (scene_ctx, ctx2) = ctx.split::<p!(<mut scene> Ctx)>();
+    let geometry_ctx = ctx2.partial_borrow::<p!(<mut geometry> Ctx)>();
+    let mut joined: SceneGeometryCtx = scene_ctx.join(geometry_ctx);
+    update_scene_geometry(&mut joined);
+}
+
+// The type-level `Union` of two borrows is the type of their value-level `join`.
+fn glyph_render_pass<'t>(scene_ctx: &'t mut RenderCtx<'t>, glyph_ctx: &'t mut GlyphCtx<'t>) -> usize {
+    let joined: GlyphRenderCtx<'t> = scene_ctx.join(glyph_ctx);
+    joined.scene.data.len() + joined.mesh.data.len()
+}
+
+// The struct can be given by a path, without importing its selector macro.
+fn count_scenes(ctx: p!(&<scene> data::Ctx)) -> usize {
+    ctx.scene.data.len()
+}
+
+fn clear_scenes(ctx: p!(&<mut scene> crate::data::Ctx)) {
+    ctx.scene.data.clear();
+}
+
+fn update_scene_geometry(ctx: p!(&<mut scene, mut geometry> Ctx)) {
+    let meshes = ctx.scene.data.iter().map(|scene| scene.meshes.len()).sum::<usize>();
+    ctx.geometry.data.truncate(meshes);
+    assert_eq!(ctx.geometry.data.len(), 2);
+}
+
+fn render_pass_extracted(ctx: p!(&<mut *> Ctx)) {
+    let (CtxRef { geometry, material, mesh, .. }, ctx2) = ctx.extract_fields::<p!(<mut *, !scene> Ctx)>();
+    for scene in &ctx2.scene.data {
+        for mesh_ix in &scene.meshes {
+            let mesh = &mut mesh.data[*mesh_ix];
+            geometry.data[mesh.geometry].label.push('!');
+            material.data[mesh.material].label.push('!');
+        }
+    }
+    assert!(geometry.data.iter().all(|geometry| geometry.label.ends_with('!')));
+    assert!(material.data.iter().all(|material| material.label.ends_with('!')));
+}
+
+fn render_pass_split3(ctx: p!(&<mut *> Ctx)) {
+    let (geometry_ctx, material_ctx, ctx2) = ctx.split3::<p!(<mut geometry> Ctx), p!(<mut material> Ctx)>();
+    for scene in &ctx2.scene.data {
+        for mesh_ix in &scene.meshes {
+            let mesh = &mut ctx2.mesh.data[*mesh_ix];
+            geometry_ctx.geometry.data[mesh.geometry].label.push('?');
+            material_ctx.material.data[mesh.material].label.push('?');
+        }
+    }
+    assert!(geometry_ctx.geometry.data.iter().all(|geometry| geometry.label.ends_with('?')));
+}
+
+fn render_pass_scoped(ctx: p!(&<mut *> Ctx)) {
+    borrow::scoped_split!(ctx => mut geometry, mut material, ref mesh, rest);
+    for scene in &rest.scene.data {
+        for mesh_ix in &scene.meshes {
+            let mesh = &mesh.data[*mesh_ix];
+            geometry.data[mesh.geometry].label.push('#');
+            material.data[mesh.material].label.push('#');
+        }
+    }
+    assert!(material.data.iter().all(|material| material.label.ends_with('#')));
+}
+
+fn render_pass_union(ctx: p!(&<mut *> Ctx)) {
+    let ctx2 = ctx.partial_borrow_union::<p!(<mut geometry, mesh> Ctx), p!(<mut material> Ctx)>();
+    render_scene(ctx2.partial_borrow(), 0);
+    update_labels(ctx2.partial_borrow());
+}
+
+fn update_labels(ctx: p!(&<mut geometry, mut material> Ctx)) {
+    ctx.geometry.data[0].label.push('+');
+    ctx.material.data[0].label.push('+');
+}
+
 fn render_pass2(_ctx: p!(&<mut *> Ctx)) {}
 fn render_pass3(_ctx: &mut GlyphRenderCtx) {}
 fn render_scene(_ctx: p!(&<mesh, mut geometry, mut material> Ctx), _mesh: usize) {
     // ...
 }
 
+// === Groups ===
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+#[group(rendering = geometry, material, mesh)]
+pub struct GroupedCtx {
+    pub geometry: GeometryCtx,
+    pub material: MaterialCtx,
+    pub mesh: MeshCtx,
+    pub scene: SceneCtx,
+}
+
+impl From<Ctx> for GroupedCtx {
+    fn from(ctx: Ctx) -> Self {
+        let Ctx { geometry, material, mesh, scene } = ctx;
+        Self { geometry, material, mesh, scene }
+    }
+}
+
+fn render_pass_group(ctx: p!(&<mut @rendering, scene> GroupedCtx)) {
+    let (rendering_ctx, scene_ctx) = ctx.split::<p!(<mut @rendering> GroupedCtx)>();
+    for scene in &scene_ctx.scene.data {
+        for mesh_ix in &scene.meshes {
+            let mesh = &rendering_ctx.mesh.data[*mesh_ix];
+            rendering_ctx.geometry.data[mesh.geometry].label.push('@');
+            rendering_ctx.material.data[mesh.material].label.push('@');
+        }
+    }
+}
+
+fn group_selects_fields<'t>(
+    ctx: p!(&'t <'t, mut @rendering> GroupedCtx)
+) -> p!(&'t <'t, mut geometry, mut material, mut mesh> GroupedCtx) {
+    ctx
+}
+
+fn group_hides_fields<'t>(
+    ctx: p!(&'t <'t, mut *, !@rendering> GroupedCtx)
+) -> p!(&'t <'t, mut scene> GroupedCtx) {
+    ctx
+}
+
+fn group_is_overridden<'t>(
+    ctx: p!(&'t <'t, mut @rendering, mesh> GroupedCtx)
+) -> p!(&'t <'t, mut geometry, mut material, mesh> GroupedCtx) {
+    ctx
+}
+
+// Selectors are applied from left to right, so `mesh` is borrowed again after hiding the group.
+fn group_exclusion_is_overridden<'t>(
+    ctx: p!(&'t <'t, mut *, !@rendering, mesh> GroupedCtx)
+) -> &'t mut GroupedCtxRef<Hidden<GeometryCtx>, Hidden<MaterialCtx>, &'t MeshCtx, &'t mut SceneCtx> {
+    ctx
+}
+
+borrow::assert_same_fields!(p!(<mut @rendering> GroupedCtx), p!(<mut mesh, mut geometry, mut material> GroupedCtx));
+borrow::assert_same_fields!(p!(<mut *, !@rendering> GroupedCtx), p!(<mut scene> GroupedCtx));
+borrow::assert_same_fields!(p!(<mut *, !@rendering, mesh> GroupedCtx), p!(<mesh, mut scene> GroupedCtx));
+
+// === Only ===
+
+borrow::assert_same_fields!(p!(<only geometry, material> Ctx), p!(<mut geometry, mut material> Ctx));
+borrow::assert_same_fields!(p!(<only mut geometry, ref mesh> Ctx), p!(<mut geometry, mesh> Ctx));
+borrow::assert_same_fields!(p!(<only @rendering, ref mesh> GroupedCtx), p!(<mut geometry, mut material, mesh> GroupedCtx));
+borrow::assert_same_fields!(p!(<only *, !scene> Ctx), p!(<mut *, !scene> Ctx));
+borrow::assert_same_fields!(p!(<only> Ctx), p!(<> Ctx));
+
+fn only_selects_fields<'t>(
+    ctx: p!(&'t <'t, only geometry, 't material, ref mesh> Ctx)
+) -> p!(&'t <'t, mut geometry, mut material, mesh> Ctx) {
+    ctx
+}
+
+// === Field Lists ===
+
+borrow::fields! { Surface = mut geometry, mut material }
+borrow::fields! { Placement = mesh, scene }
+borrow::fields! { All = @Surface, @Placement }
+
+borrow::assert_same_fields!(p!(<mut @Placement> Ctx), p!(<mut mesh, mut scene> Ctx));
+borrow::assert_same_fields!(p!(<only @Placement, material> Ctx), p!(<mut material, mut mesh, mut scene> Ctx));
+borrow::assert_same_fields!(p!(<mut @Surface, mesh> Ctx), p!(<mut geometry, mut material, mesh> Ctx));
+
+fn field_list_selects_fields<'t>(
+    ctx: p!(&'t <'t, @Surface, mut scene> Ctx)
+) -> p!(&'t <'t, mut geometry, mut material, mut scene> Ctx) {
+    ctx
+}
+
+fn field_lists_are_concatenated<'t>(
+    ctx: p!(&'t <'t, @Surface, @Placement> Ctx)
+) -> p!(&'t <'t, mut geometry, mut material, mesh, scene> Ctx) {
+    ctx
+}
+
+fn field_lists_are_nested<'t>(
+    ctx: p!(&'t <'t, @All, mut mesh> Ctx)
+) -> p!(&'t <'t, mut geometry, mut material, mut mesh, scene> Ctx) {
+    ctx
+}
+
 // === Type Aliases ===
 
 type RenderCtx<'t> = p!(<'t, scene> Ctx);
 type GlyphCtx<'t> = p!(<'t, geometry, material, mesh> Ctx);
-type GlyphRenderCtx<'t> = Union<RenderCtx<'t>, GlyphCtx<'t>>;
\ No newline at end of file
+type GlyphRenderCtx<'t> = Union<RenderCtx<'t>, GlyphCtx<'t>>;
+type SceneGeometryCtx<'t> = Joined<&'t mut p!(<'t, mut scene> Ctx), &'t mut p!(<'t, mut geometry> Ctx)>;
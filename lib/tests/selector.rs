@@ -0,0 +1,52 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+/// Unrelated macro that would collide with the default name of the selector macro.
+#[allow(unused_macros)]
+macro_rules! Ctx {
+    () => {};
+}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+#[borrow(selector = ctx_view)]
+pub struct Ctx {
+    pub names: Vec<String>,
+    pub count: usize,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn add_name(ctx: p!(&<mut names, mut count> ctx_view), name: &str) {
+    ctx.names.push(name.to_string());
+    *ctx.count += 1;
+}
+
+fn names_view<'t>(ctx: p!(&'t <'t, names> ctx_view)) -> &'t mut CtxRef<&'t Vec<String>, borrow::Hidden<usize>> {
+    ctx
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_custom_selector() {
+    let mut ctx = Ctx::default();
+    add_name(ctx.as_refs_mut().partial_borrow(), "a");
+    let mut refs = ctx.as_refs_mut();
+    let view = names_view(refs.partial_borrow());
+    assert_eq!(*view.names, vec!["a".to_string()]);
+    assert_eq!(ctx.count, 1);
+}
+
+borrow::assert_disjoint!(p!(<mut names> ctx_view), p!(<mut count> ctx_view));
@@ -0,0 +1,82 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+/// Internal allocator that implements no traits and must never be borrowed partially.
+pub struct Arena {
+    pub chunks: Vec<Vec<u8>>,
+}
+
+#[derive(PartialBorrow)]
+#[module(crate)]
+pub struct Ctx {
+    pub log: Vec<String>,
+    #[borrow(skip)]
+    pub arena: Arena,
+    pub counter: usize,
+}
+
+/// The `text_ref` and `text_as` fields would clash with the `extract_text_ref` and
+/// `extract_text_as` methods generated for `text` if they were borrowed.
+#[derive(PartialBorrow)]
+#[module(crate)]
+pub struct Line {
+    pub text: String,
+    #[borrow(skip)]
+    pub text_ref: usize,
+    #[borrow(skip)]
+    pub text_as: usize,
+    pub width: usize,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn run(ctx: p!(&<mut *> Ctx)) {
+    let (log, ctx2) = ctx.extract_log();
+    log.push("run".to_string());
+    increment(ctx2.partial_borrow());
+}
+
+fn increment(ctx: p!(&<mut counter> Ctx)) {
+    *ctx.counter += 1;
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_skipped_field() {
+    let mut ctx = Ctx { log: vec![], arena: Arena { chunks: vec![vec![0]] }, counter: 0 };
+    run(ctx.as_refs_mut().partial_borrow());
+    let CtxRef { log, counter } = ctx.as_refs_mut();
+    assert_eq!(*log, vec!["run".to_string()]);
+    assert_eq!(*counter, 1);
+    assert_eq!(ctx.arena.chunks.len(), 1);
+}
+
+#[test]
+fn test_reflect_skips_fields() {
+    use borrow::Reflect;
+    assert_eq!(Ctx::FIELD_NAMES, ["log", "counter"]);
+}
+
+#[test]
+fn test_skipped_field_named_like_method() {
+    let mut line = Line { text: "edge".to_string(), text_ref: 0, text_as: 0, width: 4 };
+    let mut refs = line.as_refs_mut();
+    let (text, _) = refs.extract_text_ref();
+    assert_eq!(text, "edge");
+    let (text, rest) = refs.extract_text_as::<p!(<width> Line)>();
+    text.push('s');
+    assert_eq!(*rest.width, 4);
+    assert_eq!(line.text, "edges");
+}
@@ -0,0 +1,72 @@
+#![allow(dead_code)]
+
+use std::mem;
+use borrow::FieldIndex;
+use borrow::PartialBorrow;
+use borrow::Reflect;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+// Two versions of the same struct, with the fields declared in a different order.
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+#[borrow(sorted, field_markers = ctx_v1_fields)]
+pub struct CtxV1 {
+    pub scene: Vec<usize>,
+    pub mesh: Vec<u32>,
+    pub geometry: Vec<u8>,
+}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+#[borrow(sorted, field_markers = ctx_v2_fields)]
+pub struct CtxV2 {
+    pub geometry: Vec<u8>,
+    pub scene: Vec<usize>,
+    pub mesh: Vec<u32>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn add_mesh(ctx: p!(&<mut mesh, scene> CtxV1)) {
+    ctx.mesh.push(0);
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_sorted_field_order() {
+    assert_eq!(CtxV1::FIELD_NAMES, ["geometry", "mesh", "scene"]);
+    assert_eq!(CtxV1::FIELD_NAMES, CtxV2::FIELD_NAMES);
+    assert_eq!(<CtxV1 as FieldIndex<ctx_v1_fields::mesh>>::INDEX, 1);
+    assert_eq!(<CtxV2 as FieldIndex<ctx_v2_fields::mesh>>::INDEX, 1);
+}
+
+#[test]
+fn test_sorted_layout() {
+    type RefV1<'t> = CtxV1Ref<&'t mut Vec<u8>, &'t mut Vec<u32>, &'t mut Vec<usize>>;
+    type RefV2<'t> = CtxV2Ref<&'t mut Vec<u8>, &'t mut Vec<u32>, &'t mut Vec<usize>>;
+    assert_eq!(mem::offset_of!(RefV1, geometry), mem::offset_of!(RefV2, geometry));
+    assert_eq!(mem::offset_of!(RefV1, mesh), mem::offset_of!(RefV2, mesh));
+    assert_eq!(mem::offset_of!(RefV1, scene), mem::offset_of!(RefV2, scene));
+}
+
+#[test]
+fn test_sorted_borrow() {
+    let mut ctx = CtxV1::default();
+    add_mesh(ctx.as_refs_mut().partial_borrow());
+    ctx.geometry.push(1);
+    ctx.scene.push(2);
+    let mut refs = ctx.as_refs_mut();
+    assert_eq!(refs.into_tuple(), (&mut vec![1_u8], &mut vec![0_u32], &mut vec![2_usize]));
+    assert_eq!(format!("{ctx:?}"), "CtxV1 { scene: [2], mesh: [0], geometry: [1] }");
+}
@@ -0,0 +1,68 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Ctx {
+    pub geometry: Vec<usize>,
+    pub material: Vec<usize>,
+    pub mesh: Vec<usize>,
+    pub log: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+#[borrow(subset_of = Ctx)]
+pub struct RenderCtx {
+    pub mesh: Vec<usize>,
+    pub geometry: Vec<usize>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn render(ctx: p!(&<mut mesh, geometry> RenderCtx)) {
+    ctx.mesh.push(ctx.geometry.len());
+}
+
+fn update(ctx: p!(&<mut geometry, mut mesh, mut log> Ctx)) {
+    ctx.geometry.push(0);
+    let mut render_ctx = RenderCtxRef::from_ctx(ctx.partial_borrow());
+    render(render_ctx.partial_borrow());
+    ctx.log.push("render".to_string());
+}
+
+// The borrow of `Ctx` and the borrow of `RenderCtx` are inferred from the subset declaration.
+fn update_projected(ctx: p!(&<mut geometry, mut mesh, mut log> Ctx)) {
+    render(ctx.project::<RenderCtx>().partial_borrow());
+    ctx.log.push("render".to_string());
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_subset_of() {
+    let mut ctx = Ctx::default();
+    update(ctx.as_refs_mut().partial_borrow());
+    update(ctx.as_refs_mut().partial_borrow());
+    assert_eq!(ctx.mesh, vec![1, 2]);
+    assert_eq!(ctx.log.len(), 2);
+}
+
+#[test]
+fn test_project() {
+    let mut ctx = Ctx { geometry: vec![0, 0], ..Ctx::default() };
+    update_projected(ctx.as_refs_mut().partial_borrow());
+    assert_eq!(ctx.mesh, vec![2]);
+    assert_eq!(ctx.log.len(), 1);
+}
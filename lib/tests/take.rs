@@ -0,0 +1,64 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Ctx {
+    pub log: Vec<String>,
+    pub frame: usize,
+    pub scale: f32,
+}
+
+// =============
+// === Utils ===
+// =============
+
+// Requires immutable access to `ctx.frame` and `ctx.scale`, and mutable access to `ctx.log`.
+fn log_frame(ctx: p!(&<frame, scale, mut log> Ctx)) {
+    let frame = ctx.take_frame();
+    let scale = ctx.take_scale();
+    ctx.log.push(format!("frame {frame} at {scale}"));
+}
+
+// Requires mutable access to all `ctx` fields.
+fn next_frame(ctx: p!(&<mut *> Ctx)) -> usize {
+    *ctx.frame += 1;
+    let frame = ctx.take_frame();
+    log_frame(ctx.partial_borrow());
+    frame
+}
+
+// Requires immutable access to `ctx.log` and `ctx.frame`. The `log` field is not `Copy`, so it
+// can't be copied out with `take_log`, and is borrowed with `log_ref` instead.
+fn last_entry(ctx: p!(&<log, frame> Ctx)) -> Option<(usize, String)> {
+    ctx.log_ref().last().map(|entry| (ctx.take_frame(), entry.clone()))
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_take_copy_fields() {
+    let mut ctx = Ctx { scale: 0.5, ..Default::default() };
+    assert_eq!(next_frame(ctx.as_refs_mut().partial_borrow()), 1);
+    assert_eq!(next_frame(ctx.as_refs_mut().partial_borrow()), 2);
+    assert_eq!(ctx.frame, 2);
+    assert_eq!(ctx.log, vec!["frame 1 at 0.5".to_string(), "frame 2 at 0.5".to_string()]);
+}
+
+#[test]
+fn test_take_leaves_field_unchanged() {
+    let mut ctx = Ctx::default();
+    assert_eq!(last_entry(ctx.as_refs_mut().partial_borrow()), None);
+    next_frame(ctx.as_refs_mut().partial_borrow());
+    assert_eq!(last_entry(ctx.as_refs_mut().partial_borrow()), Some((1, "frame 1 at 0".to_string())));
+    assert_eq!(ctx.frame, 1);
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "debug-borrow-trace")]
+
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::trace;
+use borrow::trace::BorrowEvent;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Graph {
+    pub nodes: Vec<usize>,
+    pub edges: Vec<usize>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+static EVENTS: Mutex<Vec<BorrowEvent>> = Mutex::new(Vec::new());
+
+fn record(event: &BorrowEvent) {
+    EVENTS.lock().unwrap_or_else(PoisonError::into_inner).push(*event);
+}
+
+fn connect(graph: p!(&<mut *> Graph)) {
+    let (nodes, rest) = graph.extract_nodes();
+    nodes.push(0);
+    let (edges, _) = rest.split::<p!(<mut edges> Graph)>();
+    edges.edges.push(0);
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_trace() {
+    let mut graph = Graph::default();
+    trace::set_hook(Some(record));
+    connect(graph.as_refs_mut().partial_borrow());
+    trace::set_hook(None);
+    connect(graph.as_refs_mut().partial_borrow());
+    let events = EVENTS.lock().unwrap_or_else(PoisonError::into_inner);
+    let ops = events.iter().map(|e| e.op).collect::<Vec<_>>();
+    if cfg!(debug_assertions) {
+        assert_eq!(ops, ["partial_borrow", "extract_nodes", "split"]);
+        assert!(events[1].target.contains("Hidden"));
+        assert!(events[2].target.contains("&mut alloc::vec::Vec<usize>"));
+    } else {
+        // The tracing is compiled out in release builds.
+        assert!(ops.is_empty());
+    }
+}
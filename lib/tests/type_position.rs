@@ -0,0 +1,104 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+struct Graph {
+    nodes: Vec<usize>,
+    edges: Vec<usize>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+// The `p!` macro in a `where` bound.
+fn push_node<'a, T>(graph: T) where T: Into<p!(<'a mut nodes> Graph)> {
+    let graph = graph.into();
+    graph.nodes.push(graph.nodes.len());
+}
+
+// The `p!` macro in a closure bound, with the lifetime elided by the `Fn` sugar.
+fn with_nodes<F: FnOnce(p!(&<mut nodes> Graph))>(graph: &mut Graph, f: F) {
+    f(graph.as_refs_mut().partial_borrow());
+}
+
+trait Visitor<T> {
+    fn visit(&mut self, graph: T);
+}
+
+struct EdgeCounter(usize);
+
+impl<'a> Visitor<p!(<'a edges> Graph)> for EdgeCounter {
+    fn visit(&mut self, graph: p!(<'a edges> Graph)) {
+        self.0 += graph.edges.len();
+    }
+}
+
+// The `p!` macro as a generic argument of a trait object.
+#[allow(clippy::type_complexity)]
+fn visit_edges<'a>(visitor: &mut dyn Visitor<p!(<'a edges> Graph)>, graph: p!(<'a edges> Graph)) {
+    visitor.visit(graph);
+}
+
+// The `p!` macro in associated type bindings.
+fn edge_counts<'a>(
+    graphs: impl Iterator<Item = p!(<'a edges> Graph)>
+) -> Vec<usize> {
+    graphs.map(|graph| graph.edges.len()).collect()
+}
+
+trait HasView<'a> {
+    type View;
+    fn view(&'a mut self) -> Self::View;
+}
+
+impl<'a> HasView<'a> for Graph {
+    // The `p!` macro in associated type position.
+    type View = p!(<'a mut edges> Graph);
+    fn view(&'a mut self) -> Self::View {
+        self.as_refs()
+    }
+}
+
+fn push_edge<'a, T: HasView<'a, View = p!(<'a mut edges> Graph)>>(source: &'a mut T) {
+    let view = source.view();
+    view.edges.push(0);
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_where_clause() {
+    let mut graph = Graph::default();
+    push_node(graph.as_refs::<p!(<mut nodes> Graph)>());
+    with_nodes(&mut graph, |graph| graph.nodes.push(1));
+    assert_eq!(graph.nodes, vec![0, 1]);
+}
+
+#[test]
+fn test_associated_type() {
+    let mut graph = Graph::default();
+    push_edge(&mut graph);
+    push_edge(&mut graph);
+    assert_eq!(graph.edges, vec![0, 0]);
+}
+
+#[test]
+fn test_dyn() {
+    let graph = Graph { nodes: vec![], edges: vec![1, 2, 3] };
+    let mut counter = EdgeCounter(0);
+    visit_edges(&mut counter, graph.as_shared_refs());
+    assert_eq!(counter.0, 3);
+    let counts = edge_counts([graph.as_shared_refs(), graph.as_shared_refs()].into_iter());
+    assert_eq!(counts, vec![3, 3]);
+}
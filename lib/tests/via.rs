@@ -0,0 +1,70 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default)]
+pub struct Scene {
+    pub meshes: Vec<usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct SceneCtx {
+    pub scenes: Vec<Scene>,
+    pub active: usize,
+}
+
+impl SceneCtx {
+    pub fn active(&mut self) -> &mut Scene {
+        &mut self.scenes[self.active]
+    }
+}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Ctx {
+    #[borrow(via = active -> Scene)]
+    pub scene: SceneCtx,
+    pub log: Vec<String>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+fn add_mesh(ctx: p!(&<mut scene, mut log> Ctx), mesh: usize) {
+    let scene: &mut Scene = ctx.scene;
+    scene.meshes.push(mesh);
+    ctx.log.push(format!("add_mesh {mesh}"));
+}
+
+fn count_meshes(ctx: p!(&<scene> Ctx)) -> usize {
+    ctx.scene.meshes.len()
+}
+
+fn update(ctx: p!(&<mut *> Ctx)) {
+    let (log, ctx2) = ctx.extract_log();
+    log.push("update".to_string());
+    log.push(format!("meshes {}", count_meshes(ctx2.partial_borrow())));
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_via_field() {
+    let mut ctx = Ctx::default();
+    ctx.scene.scenes = vec![Scene::default(), Scene::default()];
+    ctx.scene.active = 1;
+    add_mesh(ctx.as_refs_mut().partial_borrow(), 3);
+    update(ctx.as_refs_mut().partial_borrow());
+    assert!(ctx.scene.scenes[0].meshes.is_empty());
+    assert_eq!(ctx.scene.scenes[1].meshes, vec![3]);
+    assert_eq!(ctx.log, vec!["add_mesh 3".to_string(), "update".to_string(), "meshes 1".to_string()]);
+}
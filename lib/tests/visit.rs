@@ -0,0 +1,98 @@
+#![allow(dead_code)]
+
+use borrow::PartialBorrow;
+use borrow::partial_borrow as p;
+use borrow::traits::*;
+use borrow::FieldVisitor;
+use std::any::type_name;
+
+// ============
+// === Data ===
+// ============
+
+#[derive(Debug, Default)]
+pub struct Physics {
+    pub steps: usize,
+}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Scene {
+    pub meshes: Vec<usize>,
+    pub lights: Vec<usize>,
+}
+
+#[derive(Debug, Default, PartialBorrow)]
+#[module(crate)]
+pub struct Ctx {
+    pub frame: u64,
+    pub log: Vec<String>,
+    pub scene: Scene,
+    #[borrow(optional)]
+    pub physics: Option<Physics>,
+}
+
+// =============
+// === Utils ===
+// =============
+
+// Records the name, type, and size of every visited field.
+#[derive(Debug, Default)]
+struct Summary {
+    fields: Vec<String>,
+    nested: Vec<&'static str>,
+    size: usize,
+}
+
+impl FieldVisitor for Summary {
+    fn visit<T: ?Sized>(&mut self, name: &'static str, field: &T) {
+        self.fields.push(format!("{name}: {}", type_name::<T>()));
+        self.size += size_of_val(field);
+    }
+
+    fn visit_nested(&mut self, name: &'static str) {
+        self.nested.push(name);
+    }
+}
+
+fn summary(ctx: p!(&<frame, mut log, scene(lights), physics> Ctx)) -> Summary {
+    let mut summary = Summary::default();
+    ctx.for_each_visible_field(&mut summary);
+    summary
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_visit_visible_fields() {
+    let mut ctx = Ctx { physics: Some(Physics::default()), ..Default::default() };
+    let summary = summary(ctx.as_refs_mut().partial_borrow());
+    assert_eq!(summary.fields, vec![
+        "frame: u64".to_string(),
+        "log: alloc::vec::Vec<alloc::string::String>".to_string(),
+        format!("physics: {}", type_name::<Physics>()),
+    ]);
+    assert_eq!(summary.nested, vec!["scene"]);
+    assert_eq!(summary.size, size_of::<u64>() + size_of::<Vec<String>>() + size_of::<Physics>());
+}
+
+#[test]
+fn test_visit_skips_hidden_and_none() {
+    let mut ctx = Ctx::default();
+    let mut summary = Summary::default();
+    ctx.as_refs_mut().partial_borrow::<p!(<log, physics> Ctx)>().for_each_visible_field(&mut summary);
+    assert_eq!(summary.fields, vec!["log: alloc::vec::Vec<alloc::string::String>".to_string()]);
+    assert!(summary.nested.is_empty());
+}
+
+#[test]
+fn test_visible_fields_debug() {
+    let mut ctx = Ctx { frame: 3, log: vec!["run".to_string()], ..Default::default() };
+    let mut refs = ctx.as_refs_mut();
+    let borrow = refs.partial_borrow::<p!(<frame, mut log, scene(lights), physics> Ctx)>();
+    assert_eq!(format!("{:?}", borrow.visible_fields()), r#"{"frame": 3, "log": ["run"], "scene": <nested>, "physics": None}"#);
+    let borrow = refs.partial_borrow::<p!(<log> Ctx)>();
+    assert_eq!(format!("{:?}", borrow.visible_fields()), r#"{"log": ["run"]}"#);
+}
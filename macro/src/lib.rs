@@ -1,9 +1,16 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput, Ident, Data, Fields, Path};
+use quote::quote_spanned;
+use syn::{parse_macro_input, DeriveInput, Ident, Data, Fields, Path, GenericParam, Token};
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::parse::Parse;
+use syn::parse::ParseStream;
+use syn::punctuated::Punctuated;
 use itertools::Itertools;
 use proc_macro2::{Span};
 use proc_macro2 as pm;
+use std::mem;
 
 
 // =============
@@ -13,26 +20,654 @@ use proc_macro2 as pm;
 /// Get the current crate name;
 fn crate_name() -> Ident {
     let macro_lib = env!("CARGO_PKG_NAME");
-    let suffix = "-macro";
-    if !macro_lib.ends_with(suffix) { panic!("Internal error.") }
-    let crate_name = &macro_lib[..macro_lib.len() - suffix.len()].replace('-',"_");
-    Ident::new(crate_name, Span::call_site())
+    let crate_name = macro_lib.strip_suffix("-macro").unwrap_or(macro_lib).replace('-',"_");
+    Ident::new(&crate_name, Span::call_site())
 }
 
-/// Extract the module macro attribute.
-fn extract_module_attr(input: &DeriveInput) -> Path {
+/// Extract the optional module macro attribute.
+fn extract_module_attr(input: &DeriveInput) -> syn::Result<Option<Path>> {
     let mut module: Option<Path> = None;
     for attr in &input.attrs {
         if attr.path().is_ident("module") {
-            let tokens = attr.meta.require_list().unwrap().tokens.clone();
-            if let Ok(path) = syn::parse2::<Path>(tokens) {
-                module = Some(path);
+            module = Some(attr.parse_args::<Path>()?);
+        }
+    }
+    Ok(module)
+}
+
+/// Extract the group attributes, like `#[group(rendering = geometry, material, mesh)]`.
+fn extract_group_attrs(input: &DeriveInput) -> syn::Result<Vec<Group>> {
+    let mut groups = Vec::new();
+    for attr in &input.attrs {
+        if attr.path().is_ident("group") {
+            groups.push(attr.parse_args_with(|stream: ParseStream| {
+                let ident = stream.parse::<Ident>()?;
+                stream.parse::<Token![=]>()?;
+                let fields = Punctuated::<Ident, Token![,]>::parse_separated_nonempty(stream)?;
+                Ok(Group { ident, fields: fields.into_iter().collect() })
+            })?);
+        }
+    }
+    Ok(groups)
+}
+
+/// Struct options from the `#[borrow(...)]` attribute.
+#[derive(Default)]
+struct StructAttrs {
+    /// `#[borrow(ref_name = CtxView)]`: custom name of the ref struct.
+    ref_name: Option<Ident>,
+    /// `#[borrow(subset_of = Ctx)]`: a struct containing all fields of this one, which can be
+    /// projected to this one.
+    subset_of: Option<Path>,
+    /// `#[borrow(macro_vis = pub(crate))]`: visibility of the selector macro.
+    macro_vis: Option<syn::Visibility>,
+    /// `#[borrow(deref)]`: the struct is a newtype forwarding partial borrows to the wrapped type.
+    deref: bool,
+    /// `#[borrow(sorted)]`: the fields of the ref struct are sorted by name instead of following
+    /// the declaration order.
+    sorted: bool,
+    /// `#[borrow(bounds(T: 'static))]`: extra predicates appended to the `where` clause of every
+    /// generated impl of the struct.
+    bounds: Vec<syn::WherePredicate>,
+    /// `#[borrow(debug)]`: the generated code is reported as a compilation error.
+    debug: bool,
+    /// `#[borrow(selector = ctx_view)]`: custom name of the selector macro.
+    selector: Option<Ident>,
+    /// `#[borrow(no_clone)]`: the ref struct does not derive `Clone` and `Copy`.
+    no_clone: bool,
+    /// `#[borrow(field_markers = ctx_fields)]`: name of the module with the field marker types.
+    field_markers: Option<Ident>,
+}
+
+/// Extract the struct options from the `#[borrow(ref_name = CtxView)]`,
+/// `#[borrow(subset_of = Ctx)]`, `#[borrow(macro_vis = pub(crate))]`, `#[borrow(deref)]`,
+/// `#[borrow(sorted)]`, `#[borrow(bounds(T: 'static))]`, `#[borrow(debug)]`,
+/// `#[borrow(selector = ctx_view)]`, `#[borrow(no_clone)]`, and
+/// `#[borrow(field_markers = ctx_fields)]` attributes.
+fn extract_struct_attrs(input: &DeriveInput) -> syn::Result<StructAttrs> {
+    let mut attrs = StructAttrs::default();
+    for attr in &input.attrs {
+        if attr.path().is_ident("borrow") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("ref_name") {
+                    attrs.ref_name = Some(meta.value()?.parse::<Ident>()?);
+                    Ok(())
+                } else if meta.path.is_ident("subset_of") {
+                    attrs.subset_of = Some(meta.value()?.parse::<Path>()?);
+                    Ok(())
+                } else if meta.path.is_ident("macro_vis") {
+                    attrs.macro_vis = Some(meta.value()?.parse::<syn::Visibility>()?);
+                    Ok(())
+                } else if meta.path.is_ident("deref") {
+                    attrs.deref = true;
+                    Ok(())
+                } else if meta.path.is_ident("sorted") {
+                    attrs.sorted = true;
+                    Ok(())
+                } else if meta.path.is_ident("bounds") {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    let bounds = Punctuated::<syn::WherePredicate, Token![,]>::parse_terminated(&content)?;
+                    attrs.bounds.extend(bounds);
+                    Ok(())
+                } else if meta.path.is_ident("debug") {
+                    attrs.debug = true;
+                    Ok(())
+                } else if meta.path.is_ident("selector") {
+                    attrs.selector = Some(meta.value()?.parse::<Ident>()?);
+                    Ok(())
+                } else if meta.path.is_ident("no_clone") {
+                    attrs.no_clone = true;
+                    Ok(())
+                } else if meta.path.is_ident("field_markers") {
+                    attrs.field_markers = Some(meta.value()?.parse::<Ident>()?);
+                    Ok(())
+                } else {
+                    Err(meta.error(
+                        "Unsupported 'borrow' attribute, expected 'ref_name', 'subset_of', 'macro_vis', \
+                        'deref', 'sorted', 'bounds', 'debug', 'selector', 'no_clone', or 'field_markers'."
+                    ))
+                }
+            })?;
+        }
+    }
+    Ok(attrs)
+}
+
+/// Field options from the `#[borrow(...)]` attribute.
+#[derive(Default)]
+struct FieldAttrs {
+    /// `#[borrow(skip)]`: the field is not borrowed at all.
+    skip: bool,
+    /// `#[borrow(deref)]`: the field is borrowed through `Deref` and `DerefMut`, like `T` for
+    /// `Box<T>`.
+    deref: bool,
+    /// `#[borrow(optional)]`: the field of type `Option<T>` is borrowed as `Option<&mut T>` or
+    /// `Option<&T>`.
+    optional: bool,
+    /// `#[borrow(flatten)]`: the fields of the field type, which derives `PartialBorrow` as well,
+    /// can be selected as if they were fields of the struct.
+    flatten: bool,
+    /// `#[borrow(additive)]`: the field is not selected by `*`, only when named explicitly.
+    additive: bool,
+    /// `#[borrow(via = active -> Scene)]`: the field is borrowed through the given method of the
+    /// field, returning a reference to the given type.
+    via: Option<(Ident, syn::Type)>,
+    /// `#[borrow(cow)]`: the field of type `Cow<'a, T>` is borrowed as `&T` or `&mut T`, cloning
+    /// the value on mutable borrows if it is borrowed.
+    cow: bool,
+    /// `#[borrow(unsafe(assume_init))]`: the field of type `MaybeUninit<T>` is borrowed as `&T` or
+    /// `&mut T`, assuming it is initialized.
+    assume_init: bool,
+}
+
+/// Extract the field options from the `#[borrow(skip)]`, `#[borrow(deref)]`,
+/// `#[borrow(optional)]`, `#[borrow(flatten)]`, `#[borrow(additive)]`,
+/// `#[borrow(via = active -> Scene)]`, `#[borrow(cow)]`, and `#[borrow(unsafe(assume_init))]`
+/// attributes.
+fn extract_field_attrs(field: &syn::Field) -> syn::Result<FieldAttrs> {
+    let mut attrs = FieldAttrs::default();
+    for attr in &field.attrs {
+        if attr.path().is_ident("borrow") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    attrs.skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("deref") {
+                    attrs.deref = true;
+                    Ok(())
+                } else if meta.path.is_ident("optional") {
+                    attrs.optional = true;
+                    Ok(())
+                } else if meta.path.is_ident("flatten") {
+                    attrs.flatten = true;
+                    Ok(())
+                } else if meta.path.is_ident("additive") {
+                    attrs.additive = true;
+                    Ok(())
+                } else if meta.path.is_ident("via") {
+                    let input = meta.value()?;
+                    let method = input.parse::<Ident>()?;
+                    input.parse::<Token![->]>()?;
+                    attrs.via = Some((method, input.parse::<syn::Type>()?));
+                    Ok(())
+                } else if meta.path.is_ident("cow") {
+                    attrs.cow = true;
+                    Ok(())
+                } else if meta.path.is_ident("unsafe") {
+                    meta.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("assume_init") {
+                            attrs.assume_init = true;
+                            Ok(())
+                        } else {
+                            Err(meta.error("Unsupported unsafe 'borrow' attribute, expected 'assume_init'."))
+                        }
+                    })
+                } else if meta.path.is_ident("assume_init") {
+                    Err(meta.error(
+                        "'assume_init' is unsafe, as the field must be initialized whenever the struct \
+                        is borrowed. Use '#[borrow(unsafe(assume_init))]' to uphold this contract."
+                    ))
+                } else {
+                    Err(meta.error(
+                        "Unsupported 'borrow' attribute, expected 'skip', 'deref', 'optional', 'flatten', \
+                        'additive', 'via', 'cow', or 'unsafe(assume_init)'."
+                    ))
+                }
+            })?;
+            if attrs.assume_init && (attrs.deref || attrs.optional || attrs.flatten || attrs.via.is_some() || attrs.cow) {
+                return Err(syn::Error::new_spanned(
+                    attr, "'assume_init' can't be used together with 'deref', 'optional', 'flatten', 'via', or 'cow'."
+                ));
+            }
+            if attrs.cow && (attrs.deref || attrs.optional || attrs.flatten || attrs.via.is_some()) {
+                return Err(syn::Error::new_spanned(
+                    attr, "'cow' can't be used together with 'deref', 'optional', 'flatten', or 'via'."
+                ));
+            }
+            if attrs.deref && attrs.optional {
+                return Err(syn::Error::new_spanned(attr, "'deref' and 'optional' can't be used together."));
+            }
+            if attrs.via.is_some() && (attrs.deref || attrs.optional || attrs.flatten) {
+                return Err(syn::Error::new_spanned(
+                    attr, "'via' can't be used together with 'deref', 'optional', or 'flatten'."
+                ));
+            }
+            if attrs.flatten && (attrs.deref || attrs.optional) {
+                return Err(syn::Error::new_spanned(
+                    attr, "'flatten' can't be used together with 'deref' or 'optional'."
+                ));
             }
         }
     }
-    module.expect("The 'module' attribute is required.")
+    Ok(attrs)
 }
 
+/// Convert a `CamelCase` identifier to `snake_case`.
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i != 0 { out.push('_'); }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Path to be used in generated macros, with the leading `crate` replaced by `$crate`.
+fn macro_path(path: &Path) -> pm::TokenStream {
+    match path.segments.first() {
+        Some(first) if first.ident == "crate" && path.leading_colon.is_none() => {
+            let rest = path.segments.iter().skip(1);
+            quote! {$crate #(::#rest)*}
+        }
+        _ => quote! {#path},
+    }
+}
+
+/// Prefix of paths used by the generated macro for the `#[module(...)]` path, like
+/// `$crate::data::`. See `Struct::module_prefix`.
+fn module_prefix(module: Option<&Path>) -> pm::TokenStream {
+    module.map(|module| {
+        let module = macro_path(module);
+        quote! {#module::}
+    }).unwrap_or_default()
+}
+
+/// Build a type-level natural number, like `Succ<Succ<Zero>>` for `2`.
+fn nat(lib: &impl quote::ToTokens, n: usize) -> pm::TokenStream {
+    (0..n).fold(quote!{#lib::hlist::Zero}, |t, _| quote!{#lib::hlist::Succ<#t>})
+}
+
+
+// =============
+// === Group ===
+// =============
+
+/// Named group of fields that can be selected at once, like `p!(&<mut @rendering> Ctx)`.
+struct Group {
+    ident: Ident,
+    fields: Vec<Ident>,
+}
+
+
+// ==============
+// === Struct ===
+// ==============
+
+/// All the information about the derive input required to generate the code.
+struct Struct {
+    lib: Ident,
+    /// Path from the `#[module(...)]` attribute. If not provided, the generated macro refers to
+    /// the struct and its ref struct by their names, so they need to be in scope where `p!` is used.
+    module: Option<Path>,
+    vis: syn::Visibility,
+    /// Visibility of the selector macro. Only `pub` macros are `#[macro_export]`ed, others are
+    /// re-exported in the module of the struct with this visibility.
+    macro_vis: syn::Visibility,
+    ident: Ident,
+    ref_ident: Ident,
+    macro_ident: Ident,
+    /// Name of the selector macro, which is the struct name unless set with
+    /// `#[borrow(selector = ...)]`.
+    selector_ident: Ident,
+    /// Whether the ref struct derives `Clone` and `Copy`, which is disabled with
+    /// `#[borrow(no_clone)]`.
+    derive_clone: bool,
+    /// Name of the module with the field marker types, set with
+    /// `#[borrow(field_markers = ...)]`. The markers are not generated if not provided.
+    field_markers: Option<Ident>,
+    /// Struct generic parameters with their bounds, like `'v, V: Debug`.
+    generics_decl: Vec<pm::TokenStream>,
+    /// Struct generic parameters as used in the struct type, like `'v, V`.
+    generics_args: Vec<pm::TokenStream>,
+    /// Struct `where` clause predicates, followed by the ones from `#[borrow(bounds(...))]`.
+    bounds: Vec<pm::TokenStream>,
+    /// Struct lifetime parameters, like `'v`.
+    lifetimes: Vec<syn::Lifetime>,
+    /// Fields not marked with `#[borrow(skip)]`, in the declaration order, or sorted by name for
+    /// `#[borrow(sorted)]` structs. This is the order of the ref struct fields.
+    field_idents: Vec<Ident>,
+    /// Visibility of the fields, used for the fields of the ref struct, so that partial borrows
+    /// expose the fields only where the struct does.
+    field_vis: Vec<syn::Visibility>,
+    /// Borrowed types of the fields, like `<Box<T> as Deref>::Target` for `#[borrow(deref)]`
+    /// fields of type `Box<T>`, or `<Option<T> as OptionalField>::Inner` for
+    /// `#[borrow(optional)]` fields of type `Option<T>`.
+    field_types: Vec<syn::Type>,
+    /// Types of the field places, like `<Box<T> as Deref>::Target` for `#[borrow(deref)]` fields
+    /// of type `Box<T>`, or `Option<T>` for `#[borrow(optional)]` fields of type `Option<T>`.
+    place_types: Vec<syn::Type>,
+    /// Whether the field is marked with `#[borrow(deref)]`.
+    deref_fields: Vec<bool>,
+    /// The method of fields marked with `#[borrow(via = active -> Scene)]`, like `active`.
+    via_methods: Vec<Option<Ident>>,
+    /// Whether the field is marked with `#[borrow(optional)]`.
+    optional_fields: Vec<bool>,
+    /// Index of the field marked with `#[borrow(flatten)]`.
+    flatten_field: Option<usize>,
+    /// Whether the field is marked with `#[borrow(additive)]`.
+    additive_fields: Vec<bool>,
+    /// Whether the field is marked with `#[borrow(cow)]`.
+    cow_fields: Vec<bool>,
+    /// Whether the field is marked with `#[borrow(unsafe(assume_init))]`.
+    assume_init_fields: Vec<bool>,
+    /// Type parameters of the ref struct, one per field.
+    params: Vec<Ident>,
+    groups: Vec<Group>,
+    /// Path from the `#[borrow(subset_of = ...)]` attribute.
+    subset_of: Option<Path>,
+}
+
+impl Struct {
+    fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        let lib = crate_name();
+        let module = extract_module_attr(input)?;
+        let groups = extract_group_attrs(input)?;
+        let vis = input.vis.clone();
+        let ident = input.ident.clone();
+        let StructAttrs { ref_name, subset_of, macro_vis, deref, sorted, bounds: extra_bounds, selector, no_clone,
+            field_markers, .. } = extract_struct_attrs(input)?;
+        if deref {
+            return Err(syn::Error::new_spanned(
+                &input.ident, "'#[borrow(deref)]' can only be used on single-field tuple structs."
+            ));
+        }
+        let macro_vis = macro_vis.unwrap_or_else(|| input.vis.clone());
+        let ref_ident = ref_name
+            .unwrap_or_else(|| Ident::new(&format!("{ident}Ref"), ident.span()));
+        let macro_ident = Ident::new(&format!("_{ident}"), ident.span());
+        let selector_ident = selector.unwrap_or_else(|| ident.clone());
+
+        let fields = match &input.data {
+            Data::Struct(data) => match &data.fields {
+                Fields::Named(fields) => fields.named.iter().collect_vec(),
+                Fields::Unit => Vec::new(),
+                Fields::Unnamed(_) => return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "PartialBorrow cannot be derived for tuple structs, except for single-field \
+                    newtypes marked with '#[borrow(deref)]'."
+                )),
+            },
+            _ => return Err(syn::Error::new_spanned(
+                &input.ident, "PartialBorrow can only be derived for structs."
+            )),
+        };
+
+        let mut fields_to_borrow = Vec::new();
+        for field in &fields {
+            let attrs = extract_field_attrs(field)?;
+            if !attrs.skip {
+                fields_to_borrow.push((*field, attrs));
+            }
+        }
+        if sorted {
+            fields_to_borrow.sort_by_key(|(f, _)| f.ident.as_ref().map(|i| i.unraw().to_string()));
+        }
+        let deref_fields = fields_to_borrow.iter().map(|(_, attrs)| attrs.deref).collect_vec();
+        let via_fields = fields_to_borrow.iter().map(|(_, attrs)| attrs.via.clone()).collect_vec();
+        let via_methods = via_fields.iter().map(|via| via.as_ref().map(|(method, _)| method.clone())).collect_vec();
+        let optional_fields = fields_to_borrow.iter().map(|(_, attrs)| attrs.optional).collect_vec();
+        let additive_fields = fields_to_borrow.iter().map(|(_, attrs)| attrs.additive).collect_vec();
+        let cow_fields = fields_to_borrow.iter().map(|(_, attrs)| attrs.cow).collect_vec();
+        let assume_init_fields = fields_to_borrow.iter().map(|(_, attrs)| attrs.assume_init).collect_vec();
+        let mut flattened = fields_to_borrow.iter().enumerate().filter(|(_, (_, attrs))| attrs.flatten);
+        let flatten_field = flattened.next().map(|(i, _)| i);
+        if let Some((_, (field, _))) = flattened.next() {
+            return Err(syn::Error::new_spanned(
+                field, "Only one field can be marked with '#[borrow(flatten)]'."
+            ));
+        }
+        let fields = fields_to_borrow.into_iter().map(|(f, _)| f).collect_vec();
+        if let Some(field) = flatten_field.map(|i| fields[i]).filter(|f| !is_nested_type(&f.ty)) {
+            return Err(syn::Error::new_spanned(
+                &field.ty, "Fields marked with '#[borrow(flatten)]' need a type that is a path \
+                without generic arguments, like 'RenderingCtx'."
+            ));
+        }
+
+        let field_idents = fields.iter().filter_map(|f| f.ident.clone()).collect_vec();
+        // The `extract_$field_ref` and `extract_$field_as` methods of a field clash with the
+        // `extract_$field` method of a field named with the `_ref` or `_as` suffix.
+        let field_names = field_idents.iter().map(|f| f.unraw().to_string()).collect_vec();
+        for (field, name) in field_idents.iter().zip(field_names.iter()) {
+            for suffix in ["_ref", "_as"] {
+                let Some(other) = name.strip_suffix(suffix) else { continue };
+                if field_names.iter().any(|f| f == other) {
+                    return Err(syn::Error::new_spanned(field, format!(
+                        "Fields '{other}' and '{name}' can't be borrowed together, as both generate \
+                        a method named 'extract_{name}'. Rename one of them or mark it with \
+                        '#[borrow(skip)]'."
+                    )));
+                }
+            }
+        }
+        let field_vis = fields.iter().map(|f| f.vis.clone()).collect_vec();
+        let place_types = fields.iter().zip(deref_fields.iter()).zip(via_fields.iter()).zip(assume_init_fields.iter())
+            .map(|(((f, deref), via), assume_init)| {
+                let ty = &f.ty;
+                if let Some((_, via_ty)) = via { return via_ty.clone() }
+                if *assume_init { return syn::parse_quote! {<#ty as #lib::MaybeUninitField>::Inner} }
+                if *deref { syn::parse_quote! {<#ty as ::core::ops::Deref>::Target} } else { ty.clone() }
+            }).collect_vec();
+        let field_types = place_types.iter().zip(optional_fields.iter()).zip(cow_fields.iter())
+            .map(|((ty, optional), cow)| {
+                if *optional { return syn::parse_quote! {<#ty as #lib::OptionalField>::Inner} }
+                if *cow { syn::parse_quote! {<#ty as #lib::CowField>::Inner} } else { ty.clone() }
+            }).collect_vec();
+        let params = field_idents.clone();
+
+        let mut generics_decl = Vec::new();
+        let mut generics_args = Vec::new();
+        let mut lifetimes = Vec::new();
+        for param in &input.generics.params {
+            match param {
+                GenericParam::Lifetime(p) => {
+                    let lt = &p.lifetime;
+                    let bounds = &p.bounds;
+                    generics_decl.push(quote! {#lt: #bounds});
+                    generics_args.push(quote! {#lt});
+                    lifetimes.push(lt.clone());
+                }
+                GenericParam::Type(p) => {
+                    let ident = &p.ident;
+                    let bounds = &p.bounds;
+                    generics_decl.push(quote! {#ident: #bounds});
+                    generics_args.push(quote! {#ident});
+                }
+                GenericParam::Const(p) => {
+                    let ident = &p.ident;
+                    let ty = &p.ty;
+                    generics_decl.push(quote! {const #ident: #ty});
+                    generics_args.push(quote! {#ident});
+                }
+            }
+        }
+        for group in &groups {
+            for field in &group.fields {
+                if !field_idents.contains(field) {
+                    return Err(syn::Error::new_spanned(field, format!("Unknown field '{field}'.")));
+                }
+            }
+        }
+
+        let bounds = input.generics.where_clause.iter()
+            .flat_map(|w| w.predicates.iter())
+            .chain(extra_bounds.iter())
+            .map(|p| quote! {#p})
+            .collect_vec();
+
+        Ok(Self {
+            lib,
+            module,
+            vis,
+            macro_vis,
+            ident,
+            ref_ident,
+            macro_ident,
+            selector_ident,
+            derive_clone: !no_clone,
+            field_markers,
+            generics_decl,
+            generics_args,
+            lifetimes,
+            bounds,
+            field_idents,
+            field_types,
+            place_types,
+            deref_fields,
+            via_methods,
+            optional_fields,
+            flatten_field,
+            additive_fields,
+            cow_fields,
+            assume_init_fields,
+            field_vis,
+            params,
+            groups,
+            subset_of,
+        })
+    }
+
+    /// Prefix of paths used by the generated macro, like `$crate::data::`. The leading `crate` is
+    /// replaced by `$crate`, so that the macro refers to the crate of the struct when used in
+    /// other crates.
+    fn module_prefix(&self) -> pm::TokenStream {
+        module_prefix(self.module.as_ref())
+    }
+
+    /// Places of the borrowed fields, like `self.geometry`, `(*self.geometry)` for
+    /// `#[borrow(deref)]` fields, `(*self.scene.active())` for `#[borrow(via = active -> Scene)]`
+    /// fields, or `(*unsafe { self.cache.assume_init_mut() })` for
+    /// `#[borrow(unsafe(assume_init))]` fields.
+    fn field_places(&self) -> Vec<pm::TokenStream> {
+        self.field_places_of(&quote! {self})
+    }
+
+    /// Like `field_places`, but for the struct bound to `this`.
+    fn field_places_of(&self, this: &pm::TokenStream) -> Vec<pm::TokenStream> {
+        self.field_places_with(this, &quote! {assume_init_mut})
+    }
+
+    /// Like `field_places`, but borrowing the fields immutably, so that `#[borrow(unsafe(assume_init))]`
+    /// fields are accessed with `assume_init_ref`.
+    fn shared_field_places(&self) -> Vec<pm::TokenStream> {
+        self.field_places_with(&quote! {self}, &quote! {assume_init_ref})
+    }
+
+    fn field_places_with(&self, this: &pm::TokenStream, assume_init: &pm::TokenStream) -> Vec<pm::TokenStream> {
+        self.field_idents.iter().zip(self.deref_fields.iter()).zip(self.via_methods.iter())
+            .zip(self.assume_init_fields.iter())
+            .map(|(((field, deref), via), init)| match via {
+                Some(method) => quote! {(*#this.#field.#method())},
+                // SAFETY: The user guarantees that the field is initialized whenever the struct is
+                // borrowed, see `#[borrow(unsafe(assume_init))]`.
+                None if *init => quote! {(*unsafe { #this.#field.#assume_init() })},
+                None if *deref => quote! {(*#this.#field)},
+                None => quote! {#this.#field},
+            }).collect_vec()
+    }
+
+    /// Whether any field is borrowed through a reference returned by `Deref`, by a method, or by
+    /// `MaybeUninit::assume_init_mut`, so that the borrowed place is not a field of the struct.
+    fn has_projected_fields(&self) -> bool {
+        self.deref_fields.contains(&true)
+            || self.via_methods.iter().any(Option::is_some)
+            || self.assume_init_fields.contains(&true)
+    }
+
+    /// Whether any field is borrowed as the contents of its type, i.e. it is marked with
+    /// `#[borrow(optional)]` or `#[borrow(cow)]`.
+    fn has_wrapped_fields(&self) -> bool {
+        self.optional_fields.contains(&true) || self.cow_fields.contains(&true)
+    }
+
+    /// Borrow slots of all fields with the given lifetime and mutability, like `&'t mut SceneCtx`,
+    /// or `Option<&'t mut SceneCtx>` for `#[borrow(optional)]` fields.
+    fn slot_types(&self, lt: &pm::TokenStream, mutability: &pm::TokenStream) -> Vec<pm::TokenStream> {
+        self.field_types.iter().zip(self.optional_fields.iter()).map(|(ty, optional)| {
+            optional_slot(*optional, quote! {& #lt #mutability #ty})
+        }).collect_vec()
+    }
+
+    /// Bounds requiring the struct lifetime parameters to outlive the given lifetime, like
+    /// `'v: 't`. Borrowing a field of type `&'v V` for `'t` is possible only if `'v` outlives
+    /// `'t`, so the bounds are emitted explicitly in the impls borrowing the fields for `'t`.
+    fn outlives_bounds(&self, lt: &pm::TokenStream) -> Vec<pm::TokenStream> {
+        self.lifetimes.iter().map(|l| quote! {#l: #lt}).collect_vec()
+    }
+
+    /// The struct type with its generic parameters, like `Ctx<'v, V>`.
+    fn struct_type(&self) -> pm::TokenStream {
+        let ident = &self.ident;
+        let args = &self.generics_args;
+        quote! {#ident<#(#args),*>}
+    }
+}
+
+
+
+// ===============
+// === Newtype ===
+// ===============
+
+/// Single-field tuple struct marked with `#[borrow(deref)]`, like `struct App(Ctx);`, forwarding
+/// partial borrows to the wrapped type.
+struct Newtype {
+    lib: Ident,
+    macro_vis: syn::Visibility,
+    ident: Ident,
+    macro_ident: Ident,
+    /// The wrapped type, like `Ctx<u32>`.
+    inner: syn::Type,
+    /// Path of the selector macro of the wrapped type, like `$crate::data::Ctx`.
+    inner_macro: pm::TokenStream,
+    /// Generic arguments of the wrapped type, like `<u32>`.
+    inner_args: syn::PathArguments,
+}
+
+impl Newtype {
+    /// Returns `None` if the struct is not marked with `#[borrow(deref)]`.
+    fn parse(input: &DeriveInput) -> syn::Result<Option<Self>> {
+        let StructAttrs { macro_vis, deref, .. } = extract_struct_attrs(input)?;
+        let Data::Struct(data) = &input.data else { return Ok(None) };
+        let Fields::Unnamed(fields) = &data.fields else { return Ok(None) };
+        if !deref { return Ok(None) }
+        let error = |tokens: &dyn quote::ToTokens, msg: &str| Err(syn::Error::new_spanned(tokens, msg));
+        if fields.unnamed.len() != 1 {
+            return error(&input.ident, "'#[borrow(deref)]' can only be used on single-field tuple structs.");
+        }
+        if !input.generics.params.is_empty() {
+            return error(&input.generics, "'#[borrow(deref)]' newtypes can't have generic parameters.");
+        }
+        let inner = fields.unnamed[0].ty.clone();
+        let syn::Type::Path(syn::TypePath { qself: None, path }) = &inner else {
+            return error(&inner, "The wrapped type must be a path to a struct deriving 'PartialBorrow'.");
+        };
+        let mut path = path.clone();
+        let Some(last) = path.segments.last_mut() else {
+            return error(&inner, "The wrapped type must be a path to a struct deriving 'PartialBorrow'.");
+        };
+        let inner_args = mem::replace(&mut last.arguments, syn::PathArguments::None);
+        let is_absolute = path.leading_colon.is_some()
+            || path.segments.first().is_some_and(|segment| segment.ident == "crate");
+        let module = if is_absolute { quote! {} } else { module_prefix(extract_module_attr(input)?.as_ref()) };
+        let inner_path = macro_path(&path);
+        let ident = input.ident.clone();
+        Ok(Some(Self {
+            lib: crate_name(),
+            macro_vis: macro_vis.unwrap_or_else(|| input.vis.clone()),
+            macro_ident: Ident::new(&format!("_{ident}"), ident.span()),
+            ident,
+            inner,
+            inner_macro: quote! {#module #inner_path},
+            inner_args,
+        }))
+    }
+}
 
 // =============
 // === Macro ===
@@ -47,361 +682,2211 @@ fn extract_module_attr(input: &DeriveInput) -> Path {
 ///     scene: SceneCtx,
 /// }
 /// ```
-#[proc_macro_derive(PartialBorrow, attributes(module))]
+#[proc_macro_derive(PartialBorrow, attributes(module, group, borrow))]
 pub fn partial_borrow_derive(input: TokenStream) -> TokenStream {
-    let lib = crate_name();
     let input = parse_macro_input!(input as DeriveInput);
-    let module = extract_module_attr(&input);
+    let out = match Newtype::parse(&input) {
+        Ok(Some(newtype)) => derive_newtype(&newtype),
+        Ok(None) => Struct::parse(&input).map_or_else(syn::Error::into_compile_error, |s| derive(&s)),
+        Err(err) => err.into_compile_error(),
+    };
+    let debug = extract_struct_attrs(&input).is_ok_and(|attrs| attrs.debug);
+    let out = if debug { gen_debug_output(&input.ident, out) } else { out };
+    TokenStream::from(out)
+}
 
-    let struct_ident = input.ident;
-    let ref_struct_ident = Ident::new(&format!("{struct_ident}Ref"), struct_ident.span());
+// Generates, for `#[borrow(debug)]`:
+// compile_error!("Code generated by 'PartialBorrow' for 'Ctx':\n\n...");
+fn gen_debug_output(ident: &Ident, out: pm::TokenStream) -> pm::TokenStream {
+    let msg = format!("Code generated by 'PartialBorrow' for '{ident}':\n\n{out}");
+    quote! {
+        #out
+        compile_error!(#msg);
+    }
+}
 
-    let fields = if let Data::Struct(data) = &input.data {
-        if let Fields::Named(fields) = &data.fields {
-            fields.named.iter().collect::<Vec<_>>()
-        } else {
-            Vec::new()
+fn derive(s: &Struct) -> pm::TokenStream {
+    let ref_struct = gen_ref_struct(s);
+    let field_assertions = gen_field_assertions(s);
+    let layout_assertions = gen_layout_assertions(s);
+    let impl_has_field_types = gen_impl_has_field_types(s);
+    let impl_reflect = gen_impl_reflect(s);
+    let impl_as_refs = gen_impl_as_refs(s);
+    let impl_ref_as_refs = gen_impl_ref_as_refs(s);
+    let impl_as_shared_refs = gen_impl_as_shared_refs(s);
+    let impl_as_refs_mut = gen_impl_as_refs_mut(s);
+    let impl_as_hidden_refs = gen_impl_as_hidden_refs(s);
+    let impl_as_pin_refs = gen_impl_as_pin_refs(s);
+    let ref_macro = gen_ref_macro(s);
+    let impl_extract_fields = gen_impl_extract_fields(s);
+    let impl_into_fields = gen_impl_into_fields(s);
+    let impl_borrow_shape = gen_impl_borrow_shape(s);
+    let impl_from_fields = gen_impl_from_fields(s);
+    let impl_join = gen_impl_join(s);
+    let impl_ref_flatten = gen_impl_ref_flatten(s);
+    let impl_debug = gen_impl_debug(s);
+    let impl_clone_fields = gen_impl_clone_fields(s);
+    let impl_from_superset = gen_impl_from_superset(s);
+    let impl_map_fields = gen_impl_map_fields(s);
+    let impl_into_tuple = gen_impl_into_tuple(s);
+    let impl_visit_fields = gen_impl_visit_fields(s);
+    let impl_visible_fields = gen_impl_visible_fields(s);
+    let impl_into_iterator = gen_impl_into_iterator(s);
+    let impl_index = gen_impl_index(s);
+    let view = gen_view(s);
+    let field_accessors = gen_field_accessors(s);
+    let impl_has_refs = gen_impl_has_refs(s);
+    let impl_as_refs_raw = gen_impl_as_refs_raw(s);
+    let impl_field_offsets = gen_impl_field_offsets(s);
+    let field_markers = gen_field_markers(s);
+    quote! {
+        #ref_struct
+        #field_assertions
+        #layout_assertions
+        #impl_has_field_types
+        #impl_reflect
+        #impl_as_refs
+        #impl_ref_as_refs
+        #impl_as_shared_refs
+        #impl_as_refs_mut
+        #impl_as_hidden_refs
+        #impl_as_pin_refs
+        #ref_macro
+        #impl_extract_fields
+        #impl_into_fields
+        #impl_borrow_shape
+        #impl_from_fields
+        #impl_join
+        #impl_ref_flatten
+        #impl_debug
+        #impl_clone_fields
+        #impl_from_superset
+        #impl_map_fields
+        #impl_into_tuple
+        #impl_visit_fields
+        #impl_visible_fields
+        #impl_into_iterator
+        #impl_index
+        #view
+        #field_accessors
+        #impl_has_refs
+        #impl_as_refs_raw
+        #impl_field_offsets
+        #field_markers
+    }
+}
+
+// Generates, for `#[borrow(deref)] struct App(Ctx);`:
+// const _: () = assert_derives_partial_borrow::<Ctx>();
+//
+// impl App {
+//     pub fn as_refs_mut(&mut self) -> <Ctx as HasRefs<'_>>::RefsMut {
+//         self.0.as_refs_mut()
+//     }
+// }
+//
+// impl<'t, T> AsRefs<'t, T> for App where Ctx: AsRefs<'t, T> { ... }
+// impl<'t, T> AsSharedRefs<'t, T> for App where Ctx: AsSharedRefs<'t, T> { ... }
+// impl AsRefsSource for App {}
+// impl<'t> HasRefs<'t> for App { ... }
+// impl Reflect for App { ... }
+//
+// macro_rules! _App {
+//     ([$lib:tt] [] $($ts:tt)*) => { $crate::data::Ctx! { [$lib] [] $($ts)* } };
+// }
+// pub use _App as App;
+//
+// The selector macro forwards to the one of the wrapped type, so `p!(<mut scene> App)` is the same
+// type as `p!(<mut scene> Ctx)`. The assertion is spanned at the wrapped type, so if it does not
+// derive `PartialBorrow`, the error points at it, with a message from `HasRefs`.
+fn derive_newtype(s: &Newtype) -> pm::TokenStream {
+    let Newtype { lib, macro_vis, ident, macro_ident, inner, inner_macro, inner_args } = s;
+    let macro_export = matches!(macro_vis, syn::Visibility::Public(_)).then(|| quote! {#[macro_export]});
+    // Spanned at the wrapped type, as all the items fail to compile if it doesn't derive
+    // `PartialBorrow`.
+    let impls = quote_spanned! {inner.span()=>
+        const _: () = #lib::assert_derives_partial_borrow::<#inner>();
+
+        impl #ident {
+            /// Borrows all fields of the wrapped struct mutably.
+            #[inline(always)]
+            pub fn as_refs_mut(&mut self) -> <#inner as #lib::HasRefs<'_>>::RefsMut {
+                self.0.as_refs_mut()
+            }
+        }
+
+        impl<'_t, _T> #lib::AsRefs<'_t, _T> for #ident where #inner: #lib::AsRefs<'_t, _T> {
+            #[inline(always)]
+            fn as_refs_impl(&'_t mut self) -> _T {
+                #lib::AsRefs::as_refs_impl(&mut self.0)
+            }
+        }
+
+        impl<'_t, _T> #lib::AsSharedRefs<'_t, _T> for #ident where #inner: #lib::AsSharedRefs<'_t, _T> {
+            #[inline(always)]
+            fn as_shared_refs_impl(&'_t self) -> _T {
+                #lib::AsSharedRefs::as_shared_refs_impl(&self.0)
+            }
+        }
+
+        impl #lib::AsRefsSource for #ident {}
+
+        impl<'_t> #lib::HasRefs<'_t> for #ident where #inner: #lib::HasRefs<'_t> {
+            type RefsMut = <#inner as #lib::HasRefs<'_t>>::RefsMut;
+            type Refs = <#inner as #lib::HasRefs<'_t>>::Refs;
+        }
+
+        impl #lib::Reflect for #ident where #inner: #lib::Reflect {
+            const FIELD_NAMES: &'static [&'static str] = <#inner as #lib::Reflect>::FIELD_NAMES;
         }
-    } else {
-        Vec::new()
     };
+    quote! {
+        #impls
+
+        #macro_export
+        macro_rules! #macro_ident {
+            ([$lib:tt] [] $($ts:tt)*) => { #inner_macro! { [$lib] [#inner_args] $($ts)* } };
+        }
+
+        #macro_vis use #macro_ident as #ident;
+    }
+}
 
-    let field_idents = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect_vec();
-    let field_types = fields.iter().map(|f| &f.ty).collect_vec();
-    let params = field_idents.iter().map(|i| Ident::new(&i.to_string(), i.span())).collect_vec();
-
-    // Generates:
-    // #[repr(C)]
-    // pub struct CtxRef<geometry, material, mesh, scene> {
-    //     geometry: geometry,
-    //     material: material,
-    //     mesh: mesh,
-    //     scene: scene,
-    // }
-    let ref_struct = quote! {
-        #[derive(Debug)]
+// Generates:
+// #[repr(C)]
+// #[derive(Clone, Copy)]
+// pub struct CtxRef<geometry, material, mesh, scene> {
+//     pub geometry: geometry,
+//     pub material: material,
+//     pub mesh: mesh,
+//     pub scene: scene,
+// }
+//
+// The fields have the same visibility as the fields of the original struct. The derived `Clone`
+// and `Copy` impls hold only if no field is borrowed mutably. They are not generated for
+// `#[borrow(no_clone)]` structs, so that users can implement `Clone` for the ref struct
+// themselves. The derives of the original struct are never copied to the ref struct.
+fn gen_ref_struct(s: &Struct) -> pm::TokenStream {
+    let Struct { ref_ident, field_idents, field_vis, params, derive_clone, .. } = s;
+    let derive = derive_clone.then(|| quote! { #[derive(Clone, Copy)] });
+    quote! {
         #[repr(C)]
+        #derive
         #[allow(non_camel_case_types)]
-        pub struct #ref_struct_ident<#(#params),*> {
-            #(pub #field_idents : #params),*
+        pub struct #ref_ident<#(#params),*> {
+            #(#field_vis #field_idents : #params),*
         }
-    };
+    }
+}
+
+// Generates:
+// const _: () = {
+//     type Layout0 = CtxRef<&'static mut (), &'static (), Hidden<()>, Nested<(), ()>>;
+//     type Layout1 = CtxRef<&'static (), Hidden<()>, Nested<(), ()>, Option<&'static mut ()>>;
+//     ...
+//     assert!(size_of::<Layout0>() == size_of::<Layout1>(), "...");
+//     assert!(align_of::<Layout0>() == align_of::<Layout1>(), "...");
+//     assert!(offset_of!(Layout0, geometry) == offset_of!(Layout1, geometry), "...");
+//     ...
+// };
+//
+// `PartialBorrow` casts between ref structs that differ only in their slot types, which is sound
+// only if all of them have the same layout. The ref struct is `repr(C)` and every borrow slot is a
+// thin pointer to a sized field, or an `Option` of it, which has the same layout, so this holds for
+// all instantiations with borrow slots. The assertions make sure it stays that way. As the slot
+// kinds are rotated across the layouts, every field is checked with every slot kind. Slot layouts
+// don't depend on the pointee, so `()` is used, which works for generic structs as well.
+fn gen_layout_assertions(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, .. } = s;
+    let slots = [
+        quote! { &'static mut () },
+        quote! { &'static () },
+        quote! { #lib::Hidden<()> },
+        quote! { #lib::Nested<(), ()> },
+        quote! { ::core::option::Option<&'static mut ()> },
+    ];
+    let layouts = (0..slots.len()).map(|k| {
+        let params = (0..field_idents.len()).map(|i| &slots[(i + k) % slots.len()]);
+        quote! { #ref_ident<#(#params,)*> }
+    }).collect_vec();
+    let first = &layouts[0];
+    let msg = format!("all instantiations of `{ref_ident}` must have the same layout");
+    let assertions = layouts[1..].iter().map(|other| quote! {
+        assert!(::core::mem::size_of::<#first>() == ::core::mem::size_of::<#other>(), #msg);
+        assert!(::core::mem::align_of::<#first>() == ::core::mem::align_of::<#other>(), #msg);
+        #(
+            assert!(
+                ::core::mem::offset_of!(#first, #field_idents) ==
+                ::core::mem::offset_of!(#other, #field_idents),
+                #msg
+            );
+        )*
+    });
+    quote! {
+        const _: () = {
+            #(#assertions)*
+        };
+    }
+}
 
-    // Generates:
-    // impl<'t, geometry, material, mesh, scene>
-    //     AsRefs<'t, CtxRef<geometry, material, mesh, scene>> for Ctx
-    // where
-    //     GeometryCtx: RefCast<'t, geometry>,
-    //     MaterialCtx: RefCast<'t, material>,
-    //     MeshCtx:     RefCast<'t, mesh>,
-    //     SceneCtx:    RefCast<'t, scene>,
-    // {
-    //     fn as_refs_impl(&'t mut self) -> CtxRef<geometry, material, mesh, scene> {
-    //         CtxRef {
-    //             geometry: RefCast::ref_cast(&mut self.geometry),
-    //             material: RefCast::ref_cast(&mut self.material),
-    //             mesh:     RefCast::ref_cast(&mut self.mesh),
-    //             scene:    RefCast::ref_cast(&mut self.scene),
-    //         }
-    //     }
-    // }
-    let impl_as_refs = quote! {
+// Generates:
+// impl<geometry, material, mesh, scene>
+// Debug for CtxRef<geometry, material, mesh, scene> where
+//     geometry: DebugField,
+//     material: DebugField,
+//     mesh: DebugField,
+//     scene: DebugField,
+// {
+//     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+//         f.debug_struct("CtxRef")
+//             .field("geometry", &DebugFieldFmt(&self.geometry))
+//             .field("material", &DebugFieldFmt(&self.material))
+//             .field("mesh", &DebugFieldFmt(&self.mesh))
+//             .field("scene", &DebugFieldFmt(&self.scene))
+//             .finish()
+//     }
+// }
+fn gen_impl_debug(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    let ref_name = ref_ident.to_string();
+    let field_names = field_idents.iter().map(|i| i.unraw().to_string()).collect_vec();
+    quote! {
         #[allow(non_camel_case_types)]
-        impl<'_t, #(#params,)*>
-        #lib::AsRefs<'_t, #ref_struct_ident<#(#params,)*>> for #struct_ident
-        where #(#field_types: #lib::RefCast<'_t, #params>,)* {
+        impl<#(#params,)*> ::core::fmt::Debug for #ref_ident<#(#params,)*>
+        where #(#params: #lib::DebugField,)* {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.debug_struct(#ref_name)
+                    #(.field(#field_names, &#lib::DebugFieldFmt(&self.#field_idents)))*
+                    .finish()
+            }
+        }
+    }
+}
+
+// Generates:
+// impl<geometry, material, mesh, scene>
+// CloneFields for CtxRef<geometry, material, mesh, scene> where
+//     geometry: CloneField,
+//     material: CloneField,
+//     mesh: CloneField,
+//     scene: CloneField,
+// {
+//     type Owned = CtxRef<geometry::Owned, material::Owned, mesh::Owned, scene::Owned>;
+//     fn clone_fields_impl(&self) -> Self::Owned {
+//         CtxRef {
+//             geometry: CloneField::clone_field(&self.geometry),
+//             material: CloneField::clone_field(&self.material),
+//             mesh: CloneField::clone_field(&self.mesh),
+//             scene: CloneField::clone_field(&self.scene),
+//         }
+//     }
+// }
+fn gen_impl_clone_fields(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<#(#params,)*> #lib::CloneFields for #ref_ident<#(#params,)*>
+        where #(#params: #lib::CloneField,)* {
+            type Owned = #ref_ident<#(<#params as #lib::CloneField>::Owned,)*>;
             #[inline(always)]
-            fn as_refs_impl(& '_t mut self) -> #ref_struct_ident<#(#params,)*> {
-                #ref_struct_ident {
-                    #(#field_idents: #lib::RefCast::ref_cast(&mut self.#field_idents),)*
+            fn clone_fields_impl(&self) -> Self::Owned {
+                #ref_ident {
+                    #(#field_idents: #lib::CloneField::clone_field(&self.#field_idents),)*
                 }
             }
         }
-    };
+    }
+}
+
+// Generates, for `#[borrow(subset_of = Ctx)]` on `RenderCtx`:
+// impl<'t> RenderCtxRef<&'t mut GeometryCtx, &'t mut MeshCtx> {
+//     pub fn from_ctx(source: &'t mut p!(<'t, mut geometry, mut mesh> Ctx)) -> Self {
+//         Self {
+//             geometry: &mut *source.geometry,
+//             mesh:     &mut *source.mesh,
+//         }
+//     }
+// }
+//
+// impl<'t, Source> Subset<'t, Source> for RenderCtx
+// where Source: PartialBorrow<p!(<'t, mut geometry, mut mesh> Ctx)> {
+//     type Refs = RenderCtxRef<&'t mut GeometryCtx, &'t mut MeshCtx>;
+//     fn project(source: &'t mut Source) -> Self::Refs {
+//         RenderCtxRef::from_ctx(source.partial_borrow_impl())
+//     }
+// }
+//
+// The fields are matched by name. The source type is created by the selector macro of `Ctx`, so
+// it fails to compile if `Ctx` has no field with the same name. The `Subset` impl allows projecting
+// any borrow of `Ctx` with `ctx.project::<RenderCtx>()`, inferring both borrows.
+fn gen_impl_from_superset(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, generics_decl, bounds, field_idents, optional_fields, .. } = s;
+    let Some(superset) = &s.subset_of else { return quote! {} };
+    let mut macro_path = superset.clone();
+    let Some(last) = macro_path.segments.last_mut() else { return quote! {} };
+    let args = mem::replace(&mut last.arguments, syn::PathArguments::None);
+    let fn_name = Ident::new(&format!("from_{}", snake_case(&last.ident.unraw().to_string())), last.ident.span());
+    let doc = format!("Borrows the fields of `{ref_ident}` from a borrow of `{}`, matching them by name.", last.ident);
+    let slots = s.slot_types(&quote! {'_t}, &quote! {mut});
+    let values = field_idents.iter().zip(optional_fields.iter()).map(|(field, optional)| {
+        if *optional { quote! {source.#field.as_deref_mut()} } else { quote! {&mut *source.#field} }
+    });
+    let struct_type = s.struct_type();
+    let source = quote! {#macro_path! { [#lib] [#args] '_t, #(mut #field_idents),* }};
+    quote! {
+        impl<'_t, #(#generics_decl,)* _Source> #lib::Subset<'_t, _Source> for #struct_type
+        where _Source: #lib::PartialBorrow<#source>, #(#bounds,)* {
+            type Refs = #ref_ident<#(#slots,)*>;
+            #[inline(always)]
+            fn project(source: &'_t mut _Source) -> Self::Refs {
+                #ref_ident::#fn_name(#lib::PartialBorrow::partial_borrow_impl(source))
+            }
+        }
 
-    // Generates:
-    // impl Ctx {
-    //     pub fn as_refs_mut(&mut self) -> CtxRef<&mut GeometryCtx, &mut MaterialCtx, &mut MeshCtx, &mut SceneCtx> {
-    //         CtxRef {
-    //             geometry: &mut self.geometry,
-    //             material: &mut self.material,
-    //             mesh:     &mut self.mesh,
-    //             scene:    &mut self.scene,
-    //         }
-    //     }
-    // }
-    let impl_as_refs_mut = {
+        impl<'_t, #(#generics_decl,)*> #ref_ident<#(#slots,)*> where #(#bounds,)* {
+            #[doc = #doc]
+            #[inline(always)]
+            pub fn #fn_name(
+                source: &'_t mut #source
+            ) -> Self {
+                Self {
+                    #(#field_idents: #values,)*
+                }
+            }
+        }
+    }
+}
+
+// Generates:
+// impl Ctx {
+//     fn _assert_field_geometry_can_be_borrowed() {
+//         assert_field_can_be_borrowed::<GeometryCtx>();
+//     }
+//     ...
+// }
+//
+// The assertions are spanned at the field types, so if a field type can't be borrowed, like an
+// unsized slice, the error points at the field instead of the derive.
+fn gen_field_assertions(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, generics_decl, bounds, field_idents, field_types, .. } = s;
+    let struct_type = s.struct_type();
+    let fns = field_idents.iter().zip(field_types.iter()).map(|(field, ty)| {
+        let name = Ident::new(&format!("_assert_field_{}_can_be_borrowed", field.unraw()), field.span());
+        quote_spanned! {ty.span()=>
+            #[doc(hidden)]
+            #[allow(dead_code)]
+            fn #name() {
+                #lib::assert_field_can_be_borrowed::<#ty>();
+            }
+        }
+    });
+    quote! {
+        impl<#(#generics_decl,)*> #struct_type where #(#bounds,)* {
+            #(#fns)*
+        }
+    }
+}
+
+// Generates:
+// impl HasFieldTypes for Ctx {
+//     type FieldTypes = HList![GeometryCtx, MaterialCtx, MeshCtx, SceneCtx];
+// }
+fn gen_impl_has_field_types(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, generics_decl, bounds, field_types, .. } = s;
+    let struct_type = s.struct_type();
+    quote! {
+        impl<#(#generics_decl,)*> #lib::HasFieldTypes for #struct_type
+        where #(#bounds,)* {
+            type FieldTypes = #lib::HList!{#(#field_types,)*};
+        }
+    }
+}
+
+// Generates:
+// impl Reflect for Ctx {
+//     const FIELD_NAMES: &'static [&'static str] = &["geometry", "material", "mesh", "scene"];
+// }
+fn gen_impl_reflect(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, generics_decl, bounds, field_idents, .. } = s;
+    let struct_type = s.struct_type();
+    let field_names = field_idents.iter().map(|i| i.unraw().to_string()).collect_vec();
+    quote! {
+        impl<#(#generics_decl,)*> #lib::Reflect for #struct_type
+        where #(#bounds,)* {
+            const FIELD_NAMES: &'static [&'static str] = &[#(#field_names,)*];
+        }
+    }
+}
+
+// Generates, for `#[borrow(field_markers = ctx_fields)]`:
+// pub mod ctx_fields {
+//     pub struct geometry;
+//     pub struct material;
+//     pub struct mesh;
+//     pub struct scene;
+// }
+// impl FieldIndex<ctx_fields::geometry> for Ctx {
+//     type Index = Zero;
+//     const INDEX: usize = 0;
+// }
+// ...
+fn gen_field_markers(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, vis, generics_decl, bounds, field_idents, .. } = s;
+    let Some(module) = &s.field_markers else { return quote! {} };
+    let struct_type = s.struct_type();
+    let impls = field_idents.iter().enumerate().map(|(i, field)| {
+        let n = nat(lib, i);
         quote! {
-            #[allow(non_camel_case_types)]
-            impl #struct_ident {
-                #[inline(always)]
-                pub fn as_refs_mut(&mut self) -> #ref_struct_ident<#(&mut #field_types,)*> {
-                    #ref_struct_ident {
-                        #(#field_idents: &mut self.#field_idents,)*
+            impl<#(#generics_decl,)*> #lib::FieldIndex<#module::#field> for #struct_type
+            where #(#bounds,)* {
+                type Index = #n;
+                const INDEX: usize = #i;
+            }
+        }
+    });
+    quote! {
+        /// Marker types of the fields, used with `FieldIndex`.
+        #[allow(non_camel_case_types)]
+        #vis mod #module {
+            #(
+                #[derive(Clone, Copy, Debug)]
+                pub struct #field_idents;
+            )*
+        }
+        #(#impls)*
+    }
+}
+
+// Generates:
+// impl<'t> HasRefs<'t> for Ctx {
+//     type RefsMut = CtxRef<&'t mut GeometryCtx, &'t mut MaterialCtx, &'t mut MeshCtx, &'t mut SceneCtx>;
+//     type Refs = CtxRef<&'t GeometryCtx, &'t MaterialCtx, &'t MeshCtx, &'t SceneCtx>;
+// }
+fn gen_impl_has_refs(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, generics_decl, bounds, field_types, .. } = s;
+    let outlives = s.outlives_bounds(&quote! {'_t});
+    let struct_type = s.struct_type();
+    let refs_mut = s.slot_types(&quote! {'_t}, &quote! {mut});
+    let refs = s.slot_types(&quote! {'_t}, &quote! {});
+    quote! {
+        impl<'_t, #(#generics_decl,)*> #lib::HasRefs<'_t> for #struct_type
+        where #(#field_types: '_t,)* #(#outlives,)* #(#bounds,)* {
+            type RefsMut = #ref_ident<#(#refs_mut,)*>;
+            type Refs = #ref_ident<#(#refs,)*>;
+        }
+    }
+}
+
+// Generates:
+// impl<'t, geometry, material, mesh, scene>
+//     AsRefsRaw<'t, CtxRef<geometry, material, mesh, scene>> for Ctx
+// where
+//     GeometryCtx: RawRefCast<'t, geometry>,
+//     MaterialCtx: RawRefCast<'t, material>,
+//     MeshCtx:     RawRefCast<'t, mesh>,
+//     SceneCtx:    RawRefCast<'t, scene>,
+// {
+//     unsafe fn as_refs_raw(this: *mut Self) -> CtxRef<geometry, material, mesh, scene> {
+//         unsafe {
+//             CtxRef {
+//                 geometry: RawRefCast::raw_ref_cast(ptr::addr_of_mut!((*this).geometry)),
+//                 material: RawRefCast::raw_ref_cast(ptr::addr_of_mut!((*this).material)),
+//                 mesh:     RawRefCast::raw_ref_cast(ptr::addr_of_mut!((*this).mesh)),
+//                 scene:    RawRefCast::raw_ref_cast(ptr::addr_of_mut!((*this).scene)),
+//             }
+//         }
+//     }
+// }
+//
+// Not generated for structs with `#[borrow(deref)]` or `#[borrow(via = ...)]` fields, as borrowing
+// them requires a reference to the field, which could alias other borrows of the field in nested
+// borrows.
+fn gen_impl_as_refs_raw(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, generics_decl, bounds, field_idents, place_types, params, .. } = s;
+    if s.has_projected_fields() { return quote! {} }
+    let outlives = s.outlives_bounds(&quote! {'_t});
+    let struct_type = s.struct_type();
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<'_t, #(#generics_decl,)* #(#params,)*>
+        #lib::AsRefsRaw<'_t, #ref_ident<#(#params,)*>> for #struct_type
+        where #(#place_types: #lib::RawRefCast<'_t, #params>,)* #(#outlives,)* #(#bounds,)* {
+            #[inline(always)]
+            unsafe fn as_refs_raw(this: *mut Self) -> #ref_ident<#(#params,)*> {
+                unsafe {
+                    #ref_ident {
+                        #(#field_idents: #lib::RawRefCast::raw_ref_cast(
+                            ::core::ptr::addr_of_mut!((*this).#field_idents)
+                        ),)*
                     }
                 }
             }
         }
-    };
+    }
+}
+
+// Generates:
+// unsafe impl FieldOffsets for Ctx {
+//     const FIELD_OFFSETS: &'static [usize] = &[
+//         ::core::mem::offset_of!(Self, geometry),
+//         ...
+//     ];
+// }
+//
+// Not generated for structs with `#[borrow(deref)]`, `#[borrow(via = ...)]`, `#[borrow(optional)]`,
+// or `#[borrow(cow)]` fields, as the offsets must be the offsets of fields of the borrowed types.
+fn gen_impl_field_offsets(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, generics_decl, bounds, field_idents, .. } = s;
+    if s.has_projected_fields() || s.has_wrapped_fields() { return quote! {} }
+    let struct_type = s.struct_type();
+    quote! {
+        unsafe impl<#(#generics_decl,)*> #lib::FieldOffsets for #struct_type
+        where #(#bounds,)* {
+            const FIELD_OFFSETS: &'static [usize] = &[
+                #(::core::mem::offset_of!(Self, #field_idents),)*
+            ];
+        }
+    }
+}
+
+// Generates:
+// impl<'t, geometry, material, mesh, scene>
+//     AsRefs<'t, CtxRef<geometry, material, mesh, scene>> for Ctx
+// where
+//     GeometryCtx: RefCast<'t, geometry>,
+//     MaterialCtx: RefCast<'t, material>,
+//     MeshCtx:     RefCast<'t, mesh>,
+//     SceneCtx:    RefCast<'t, scene>,
+// {
+//     fn as_refs_impl(&'t mut self) -> CtxRef<geometry, material, mesh, scene> {
+//         CtxRef {
+//             geometry: RefCast::ref_cast(&mut self.geometry),
+//             material: RefCast::ref_cast(&mut self.material),
+//             mesh:     RefCast::ref_cast(&mut self.mesh),
+//             scene:    RefCast::ref_cast(&mut self.scene),
+//         }
+//     }
+// }
+fn gen_impl_as_refs(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, generics_decl, bounds, field_idents, place_types, params, .. } = s;
+    let outlives = s.outlives_bounds(&quote! {'_t});
+    let struct_type = s.struct_type();
+    let field_places = s.field_places();
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<'_t, #(#generics_decl,)* #(#params,)*>
+        #lib::AsRefs<'_t, #ref_ident<#(#params,)*>> for #struct_type
+        where #(#place_types: #lib::RefCast<'_t, #params>,)* #(#outlives,)* #(#bounds,)* {
+            #[inline(always)]
+            fn as_refs_impl(& '_t mut self) -> #ref_ident<#(#params,)*> {
+                #ref_ident {
+                    #(#field_idents: #lib::RefCast::ref_cast(&mut #field_places),)*
+                }
+            }
+        }
+
+        impl<#(#generics_decl,)*> #lib::AsRefsSource for #struct_type where #(#bounds,)* {}
+    }
+}
+
+// Generates:
+// impl<'t, geometry, material, mesh, scene, geometry_target, material_target, mesh_target, scene_target>
+//     AsRefs<'t, CtxRef<geometry_target, material_target, mesh_target, scene_target>>
+//     for CtxRef<geometry, material, mesh, scene>
+// where
+//     geometry: ReborrowField<'t, geometry_target>,
+//     material: ReborrowField<'t, material_target>,
+//     mesh:     ReborrowField<'t, mesh_target>,
+//     scene:    ReborrowField<'t, scene_target>,
+// {
+//     fn as_refs_impl(&'t mut self) -> CtxRef<geometry_target, material_target, mesh_target, scene_target> {
+//         CtxRef {
+//             geometry: ReborrowField::reborrow_field(&mut self.geometry),
+//             material: ReborrowField::reborrow_field(&mut self.material),
+//             mesh:     ReborrowField::reborrow_field(&mut self.mesh),
+//             scene:    ReborrowField::reborrow_field(&mut self.scene),
+//         }
+//     }
+// }
+//
+// Selects a new set of the fields borrowed by the ref struct. Hidden fields stay hidden.
+fn gen_impl_ref_as_refs(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    let target_params = params.iter().map(|i| Ident::new(&format!("{}_target", i.unraw()), i.span())).collect_vec();
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<'_t, #(#params,)* #(#target_params,)*>
+        #lib::AsRefs<'_t, #ref_ident<#(#target_params,)*>> for #ref_ident<#(#params,)*>
+        where #(#params: #lib::ReborrowField<'_t, #target_params>,)* {
+            #[inline(always)]
+            fn as_refs_impl(&'_t mut self) -> #ref_ident<#(#target_params,)*> {
+                #ref_ident {
+                    #(#field_idents: #lib::ReborrowField::reborrow_field(&mut self.#field_idents),)*
+                }
+            }
+        }
+
+        #[allow(non_camel_case_types)]
+        impl<#(#params,)*> #lib::AsRefsSource for #ref_ident<#(#params,)*> {}
+    }
+}
+
+// Generates:
+// impl<'t, geometry, material, mesh, scene>
+//     AsSharedRefs<'t, CtxRef<geometry, material, mesh, scene>> for Ctx
+// where
+//     GeometryCtx: SharedRefCast<'t, geometry>,
+//     MaterialCtx: SharedRefCast<'t, material>,
+//     MeshCtx:     SharedRefCast<'t, mesh>,
+//     SceneCtx:    SharedRefCast<'t, scene>,
+// {
+//     fn as_shared_refs_impl(&'t self) -> CtxRef<geometry, material, mesh, scene> {
+//         CtxRef {
+//             geometry: SharedRefCast::shared_ref_cast(&self.geometry),
+//             material: SharedRefCast::shared_ref_cast(&self.material),
+//             mesh:     SharedRefCast::shared_ref_cast(&self.mesh),
+//             scene:    SharedRefCast::shared_ref_cast(&self.scene),
+//         }
+//     }
+// }
+//
+// Not generated for structs with `#[borrow(via = ...)]` fields, as their methods borrow the fields
+// mutably.
+fn gen_impl_as_shared_refs(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, generics_decl, bounds, field_idents, place_types, params, .. } = s;
+    if s.via_methods.iter().any(Option::is_some) { return quote! {} }
+    let outlives = s.outlives_bounds(&quote! {'_t});
+    let struct_type = s.struct_type();
+    let field_places = s.shared_field_places();
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<'_t, #(#generics_decl,)* #(#params,)*>
+        #lib::AsSharedRefs<'_t, #ref_ident<#(#params,)*>> for #struct_type
+        where #(#place_types: #lib::SharedRefCast<'_t, #params>,)* #(#outlives,)* #(#bounds,)* {
+            #[inline(always)]
+            fn as_shared_refs_impl(& '_t self) -> #ref_ident<#(#params,)*> {
+                #ref_ident {
+                    #(#field_idents: #lib::SharedRefCast::shared_ref_cast(&#field_places),)*
+                }
+            }
+        }
+    }
+}
+
+// Generates:
+// impl Ctx {
+//     pub fn as_refs_mut(&mut self) -> CtxRef<&mut GeometryCtx, &mut MaterialCtx, &mut MeshCtx, &mut SceneCtx> {
+//         CtxRef {
+//             geometry: &mut self.geometry,
+//             material: &mut self.material,
+//             mesh:     &mut self.mesh,
+//             scene:    &mut self.scene,
+//         }
+//     }
+// }
+//
+// impl<'t> From<&'t mut Ctx> for CtxRef<&'t mut GeometryCtx, &'t mut MaterialCtx, &'t mut MeshCtx, &'t mut SceneCtx> {
+//     fn from(ctx: &'t mut Ctx) -> Self {
+//         ctx.as_refs_mut()
+//     }
+// }
+fn gen_impl_as_refs_mut(s: &Struct) -> pm::TokenStream {
+    let Struct { ref_ident, generics_decl, bounds, field_idents, field_types, optional_fields, cow_fields, .. } = s;
+    let struct_type = s.struct_type();
+    let outlives = s.outlives_bounds(&quote! {'_t});
+    let field_refs = s.field_places().into_iter().zip(optional_fields.iter()).zip(cow_fields.iter())
+        .map(|((place, optional), cow)| {
+            if *optional { return quote! {#place.as_mut()} }
+            if *cow { quote! {#place.to_mut()} } else { quote! {&mut #place} }
+        });
+    let slots = s.slot_types(&quote! {}, &quote! {mut});
+    let slots_with_lifetime = s.slot_types(&quote! {'_t}, &quote! {mut});
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<#(#generics_decl,)*> #struct_type where #(#bounds,)* {
+            #[inline(always)]
+            pub fn as_refs_mut(&mut self) -> #ref_ident<#(#slots,)*> {
+                #ref_ident {
+                    #(#field_idents: #field_refs,)*
+                }
+            }
+        }
+
+        #[allow(non_camel_case_types)]
+        impl<'_t, #(#generics_decl,)*> ::core::convert::From<&'_t mut #struct_type>
+        for #ref_ident<#(#slots_with_lifetime,)*>
+        where #(#field_types: '_t,)* #(#outlives,)* #(#bounds,)* {
+            #[inline(always)]
+            fn from(this: &'_t mut #struct_type) -> Self {
+                this.as_refs_mut()
+            }
+        }
+    }
+}
+
+// Generates:
+// impl Ctx {
+//     pub fn as_hidden_refs(&mut self)
+//     -> CtxRef<Hidden<GeometryCtx>, Hidden<MaterialCtx>, Hidden<MeshCtx>, Hidden<SceneCtx>> {
+//         AsRefs::as_refs_impl(self)
+//     }
+// }
+//
+// The hidden slots grant no access and have no lifetime, so the returned borrow doesn't keep the
+// struct borrowed.
+fn gen_impl_as_hidden_refs(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, generics_decl, bounds, field_types, .. } = s;
+    let struct_type = s.struct_type();
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<#(#generics_decl,)*> #struct_type where #(#bounds,)* {
+            /// Borrows no fields, like `p!(<!*> Self)`. Joining it with another borrow of the
+            /// struct gives that borrow back, so it can be used as the initial value when joining
+            /// several borrows.
+            #[inline(always)]
+            pub fn as_hidden_refs(&mut self) -> #ref_ident<#(#lib::Hidden<#field_types>,)*> {
+                #lib::AsRefs::as_refs_impl(self)
+            }
+        }
+    }
+}
+
+// Generates:
+// impl<'t, geometry, material, mesh, scene>
+//     AsPinRefs<'t, CtxRef<geometry, material, mesh, scene>> for Ctx
+// where
+//     GeometryCtx: PinRefCast<'t, geometry>,
+//     MaterialCtx: PinRefCast<'t, material>,
+//     MeshCtx:     PinRefCast<'t, mesh>,
+//     SceneCtx:    PinRefCast<'t, scene>,
+// {
+//     fn as_pin_refs_impl(self: Pin<&'t mut Self>) -> CtxRef<geometry, material, mesh, scene> {
+//         let this = unsafe { Pin::get_unchecked_mut(self) };
+//         CtxRef {
+//             geometry: PinRefCast::pin_ref_cast(&mut this.geometry),
+//             material: PinRefCast::pin_ref_cast(&mut this.material),
+//             mesh:     PinRefCast::pin_ref_cast(&mut this.mesh),
+//             scene:    PinRefCast::pin_ref_cast(&mut this.scene),
+//         }
+//     }
+// }
+fn gen_impl_as_pin_refs(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, generics_decl, bounds, field_idents, place_types, params, .. } = s;
+    let outlives = s.outlives_bounds(&quote! {'_t});
+    let struct_type = s.struct_type();
+    let field_places = s.field_places_of(&quote! { this });
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<'_t, #(#generics_decl,)* #(#params,)*>
+        #lib::AsPinRefs<'_t, #ref_ident<#(#params,)*>> for #struct_type
+        where #(#place_types: #lib::PinRefCast<'_t, #params>,)* #(#outlives,)* #(#bounds,)* {
+            #[inline(always)]
+            fn as_pin_refs_impl(self: ::core::pin::Pin<&'_t mut Self>) -> #ref_ident<#(#params,)*> {
+                // SAFETY: The struct is not moved. Its fields are borrowed mutably only if they
+                // are `Unpin`, see `PinRefCast`.
+                let this = unsafe { ::core::pin::Pin::get_unchecked_mut(self) };
+                #ref_ident {
+                    #(#field_idents: #lib::PinRefCast::pin_ref_cast(&mut #field_places),)*
+                }
+            }
+        }
+    }
+}
+
+// Generates:
+// unsafe impl<geometry, material, mesh, scene>
+// HasFields for CtxRef<geometry, material, mesh, scene> {
+//     type Fields = HList![geometry, material, mesh, scene];
+// }
+fn gen_impl_into_fields(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, params, .. } = s;
+    quote! {
+        #[allow(non_camel_case_types)]
+        unsafe impl<#(#params,)*>
+        #lib::HasFields for #ref_ident<#(#params,)*> {
+            type Fields = #lib::HList!{#(#params,)*};
+        }
+    }
+}
+
+// Generates:
+// impl<geometry, material, mesh, scene>
+// BorrowShape for CtxRef<geometry, material, mesh, scene>
+// where geometry: SlotAccess, material: SlotAccess, mesh: SlotAccess, scene: SlotAccess {
+//     const BORROW_MASK: &'static [Access] = &[
+//         geometry::ACCESS, material::ACCESS, mesh::ACCESS, scene::ACCESS
+//     ];
+// }
+fn gen_impl_borrow_shape(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, params, .. } = s;
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<#(#params,)*> #lib::reflect::BorrowShape for #ref_ident<#(#params,)*>
+        where #(#params: #lib::reflect::SlotAccess,)* {
+            const BORROW_MASK: &'static [#lib::reflect::Access] = &[
+                #(<#params as #lib::reflect::SlotAccess>::ACCESS,)*
+            ];
+        }
+    }
+}
+
+// Generates:
+// unsafe impl<geometry_target, material_target, mesh_target, scene_target,
+//             geometry,        material,        mesh,        scene>
+// FromFields<HList![geometry_target, material_target, mesh_target, scene_target]>
+// for CtxRef<geometry, material, mesh, scene> {
+//     type Result = CtxRef<geometry_target, material_target, mesh_target, scene_target>;
+// }
+fn gen_impl_from_fields(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, params, .. } = s;
+    let target_params = params.iter().map(|i| Ident::new(&format!("{}_target", i.unraw()), i.span())).collect_vec();
+    quote! {
+        #[allow(non_camel_case_types)]
+        unsafe impl<#(#params,)* #(#target_params,)*>
+        #lib::FromFields<#lib::HList!{#(#target_params,)*}> for #ref_ident<#(#params,)*> {
+            type Result = #ref_ident<#(#target_params,)*>;
+        }
+    }
+}
 
-    // Generates:
-    // impl<geometry, material, mesh, scene>
-    // HasFields for CtxRef<geometry, material, mesh, scene> {
-    //     type Fields = HList![geometry, material, mesh, scene];
-    // }
-    let impl_into_fields = {
+// Generates:
+// impl<'t, geometry, material, mesh, scene, geometry_other, material_other, mesh_other, scene_other>
+// Join<&'t mut CtxRef<geometry_other, material_other, mesh_other, scene_other>>
+// for &'t mut CtxRef<geometry, material, mesh, scene> where
+//     geometry: UnifyFieldImpl<'t, geometry2>,
+//     material: UnifyFieldImpl<'t, material2>,
+//     mesh: UnifyFieldImpl<'t, mesh2>,
+//     scene: UnifyFieldImpl<'t, scene2>,
+// {
+//     type Result = CtxRef<
+//         <geometry as UnifyFieldImpl<'t, geometry_other>>::Result,
+//         <material as UnifyFieldImpl<'t, material_other>>::Result,
+//         <mesh as UnifyFieldImpl<'t, mesh_other>>::Result,
+//         <scene as UnifyFieldImpl<'t, scene_other>>::Result,
+//     >;
+//     fn join(self, other: &'t mut CtxRef<geometry_other, material_other, mesh_other, scene_other>) -> Self::Result {
+//         let geometry = self.geometry.join_field(&mut other.geometry);
+//         let material = self.material.join_field(&mut other.material);
+//         let mesh = self.mesh.join_field(&mut other.mesh);
+//         let scene = self.scene.join_field(&mut other.scene);
+//         CtxRef { geometry, material, mesh, scene }
+//     }
+// }
+fn gen_impl_join(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    let other_params = params.iter().map(|i| Ident::new(&format!("{}_other", i.unraw()), i.span())).collect_vec();
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<'_t, #(#params,)* #(#other_params,)*>
+        #lib::UnifyImpl<&'_t mut #ref_ident<#(#other_params,)*>> for &'_t mut #ref_ident<#(#params,)*>
+        where
+            #(#params: #lib::UnifyFieldImpl<'_t, #other_params>,)*
+        {
+            type Result = #ref_ident<#(<#params as #lib::UnifyFieldImpl<'_t, #other_params>>::Result,)*>;
+            #[inline(always)]
+            fn union(self, other: &'_t mut #ref_ident<#(#other_params,)*>) -> Self::Result {
+                #ref_ident {
+                    #(#field_idents: #lib::UnifyFieldImpl::unify_field(&mut self.#field_idents, &mut other.#field_idents),)*
+                }
+            }
+        }
+    }
+}
+
+// Generates:
+// impl<'t, geometry, material, mesh, scene>
+// RefFlatten<'t> for CtxRef<geometry, material, mesh, scene> where
+//     geometry: RefFlatten<'t>,
+//     material: RefFlatten<'t>,
+//     mesh: RefFlatten<'t>,
+//     scene: RefFlatten<'t>,
+// {
+//     type Output = CtxRef<
+//         <geometry as RefFlatten<'t>>::Output,
+//         <material as RefFlatten<'t>>::Output,
+//         <mesh as RefFlatten<'t>>::Output,
+//         <scene as RefFlatten<'t>>::Output,
+//     >;
+//     fn ref_flatten(&'t mut self) -> Self::Output {
+//         CtxRef {
+//             geometry: RefFlatten::ref_flatten(&mut self.geometry),
+//             material: RefFlatten::ref_flatten(&mut self.material),
+//             mesh: RefFlatten::ref_flatten(&mut self.mesh),
+//             scene: RefFlatten::ref_flatten(&mut self.scene),
+//         }
+//     }
+// }
+fn gen_impl_ref_flatten(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<'_t, #(#params,)*> #lib::RefFlatten<'_t> for #ref_ident<#(#params,)*>
+        where #(#params: #lib::RefFlatten<'_t>,)* {
+            type Output = #ref_ident<#(<#params as #lib::RefFlatten<'_t>>::Output,)*>;
+            #[inline(always)]
+            fn ref_flatten(&'_t mut self) -> Self::Output {
+                #ref_ident {
+                    #(#field_idents: #lib::RefFlatten::ref_flatten(&mut self.#field_idents),)*
+                }
+            }
+        }
+    }
+}
+
+// Generates:
+// pub struct CtxRefMapped<geometry, material, mesh, scene> {
+//     pub geometry: geometry,
+//     pub material: material,
+//     pub mesh: mesh,
+//     pub scene: scene,
+// }
+//
+// impl<geometry, material, mesh, scene> CtxRef<geometry, material, mesh, scene> {
+//     pub fn map_geometry<'t, T>(
+//         &'t mut self,
+//         f: impl FnOnce(<geometry as RefFlatten<'t>>::Output) -> T
+//     ) -> CtxRefMapped<
+//         T,
+//         <material as RefFlatten<'t>>::Output,
+//         <mesh as RefFlatten<'t>>::Output,
+//         <scene as RefFlatten<'t>>::Output,
+//     > where
+//         geometry: RefFlatten<'t>,
+//         material: RefFlatten<'t>,
+//         mesh: RefFlatten<'t>,
+//         scene: RefFlatten<'t>,
+//     {
+//         CtxRefMapped {
+//             geometry: f(RefFlatten::ref_flatten(&mut self.geometry)),
+//             material: RefFlatten::ref_flatten(&mut self.material),
+//             mesh: RefFlatten::ref_flatten(&mut self.mesh),
+//             scene: RefFlatten::ref_flatten(&mut self.scene),
+//         }
+//     }
+//     // ...
+// }
+//
+// The closure receives only the mapped field, so it can't alias other fields. The result is a
+// separate struct, not the ref struct, as the closure output can be any type, not only a borrow
+// slot of the field. It doesn't implement `HasFields`, so it can't be cast by `partial_borrow`.
+fn gen_impl_map_fields(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, field_vis, params, .. } = s;
+    let mapped_ident = Ident::new(&format!("{ref_ident}Mapped"), ref_ident.span());
+    let doc = format!(
+        "Fields of [`{ref_ident}`] with one of them replaced by the output of a `map_$field` \
+        method. In contrast to the ref struct, it can't be partially borrowed."
+    );
+    let fns = field_idents.iter().zip(params.iter()).map(|(field, param)| {
+        let name = Ident::new(&format!("map_{}", field.unraw()), field.span());
+        let output_types = params.iter().map(|p| {
+            if p == param { quote! {_T} } else { quote! {<#p as #lib::RefFlatten<'_t>>::Output} }
+        });
+        let values = field_idents.iter().map(|f| {
+            let value = quote! {#lib::RefFlatten::ref_flatten(&mut self.#f)};
+            if f == field { quote! {#f: f(#value)} } else { quote! {#f: #value} }
+        });
         quote! {
-            #[allow(non_camel_case_types)]
-            impl<#(#params,)*>
-            #lib::HasFields for #ref_struct_ident<#(#params,)*> {
-                type Fields = #lib::HList!{#(#params,)*};
+            #[inline(always)]
+            pub fn #name<'_t, _T>(
+                &'_t mut self,
+                f: impl FnOnce(<#param as #lib::RefFlatten<'_t>>::Output) -> _T
+            ) -> #mapped_ident<#(#output_types,)*>
+            where #(#params: #lib::RefFlatten<'_t>,)* {
+                #mapped_ident { #(#values,)* }
             }
         }
+    });
+    quote! {
+        #[doc = #doc]
+        #[allow(non_camel_case_types)]
+        pub struct #mapped_ident<#(#params),*> {
+            #(#field_vis #field_idents : #params),*
+        }
+
+        #[allow(non_camel_case_types)]
+        impl<#(#params,)*> #ref_ident<#(#params,)*> {
+            #(#fns)*
+        }
+    }
+}
+
+// Generates:
+// impl<geometry, material, mesh, scene> CtxRef<geometry, material, mesh, scene> {
+//     pub fn into_tuple<'_t>(&'_t mut self) -> ... { ... }
+// }
+fn gen_impl_into_tuple(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    let fields = quote! {
+        #lib::HList![#(<#params as #lib::RefFlatten<'_t>>::Output),*]
     };
+    let live_fields = quote! {<#fields as #lib::LiveFields>::Output};
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<#(#params,)*> #ref_ident<#(#params,)*> {
+            /// Returns a tuple of the borrowed fields in the order of the ref struct fields,
+            /// skipping the hidden ones.
+            #[inline(always)]
+            pub fn into_tuple<'_t>(&'_t mut self) -> <#live_fields as #lib::hlist::IntoTuple>::Tuple
+            where
+                #(#params: #lib::RefFlatten<'_t>,)*
+                #fields: #lib::LiveFields,
+                #live_fields: #lib::hlist::IntoTuple {
+                let fields = #lib::hlist![#(#lib::RefFlatten::ref_flatten(&mut self.#field_idents)),*];
+                #lib::hlist::IntoTuple::into_tuple(#lib::LiveFields::live_fields(fields))
+            }
+        }
+    }
+}
 
-    // Generates:
-    // impl<geometry_target, material_target, mesh_target, scene_target,
-    //      geometry,        material,        mesh,        scene>
-    // FromFields<HList![geometry_target, material_target, mesh_target, scene_target]>
-    // for CtxRef<geometry, material, mesh, scene> {
-    //     type Result = CtxRef<geometry_target, material_target, mesh_target, scene_target>;
-    // }
-    let impl_from_fields = {
-        let target_params = params.iter().map(|i| Ident::new(&format!("{i}_target"), i.span())).collect_vec();
-        quote! {
-            #[allow(non_camel_case_types)]
-            impl<#(#params,)* #(#target_params,)*>
-            #lib::FromFields<#lib::HList!{#(#target_params,)*}> for #ref_struct_ident<#(#params,)*> {
-                type Result = #ref_struct_ident<#(#target_params,)*>;
+// Generates:
+// impl<geometry, material, mesh, scene> CtxRef<geometry, material, mesh, scene> {
+//     pub fn for_each_visible_field<_V: FieldVisitor>(&self, visitor: &mut _V)
+//     where geometry: VisitSlot, material: VisitSlot, mesh: VisitSlot, scene: VisitSlot {
+//         let fields = hlist![&self.geometry, &self.material, &self.mesh, &self.scene];
+//         VisitFields::visit_fields(&fields, &["geometry", "material", "mesh", "scene"], visitor)
+//     }
+// }
+fn gen_impl_visit_fields(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    let field_names = field_idents.iter().map(|i| i.unraw().to_string()).collect_vec();
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<#(#params,)*> #ref_ident<#(#params,)*> {
+            /// Calls the visitor for every field that is not hidden, in the order of the ref
+            /// struct fields. See [`FieldVisitor`](#lib::FieldVisitor).
+            #[inline(always)]
+            pub fn for_each_visible_field<_V: #lib::FieldVisitor>(&self, visitor: &mut _V)
+            where #(#params: #lib::VisitSlot,)* {
+                let fields = #lib::hlist![#(&self.#field_idents),*];
+                #lib::VisitFields::visit_fields(&fields, &[#(#field_names,)*], visitor)
             }
         }
+    }
+}
+
+// Generates:
+// impl<geometry, material, mesh, scene> CtxRef<geometry, material, mesh, scene> {
+//     pub fn visible_fields<'s>(&'s self)
+//     -> VisibleFields<HList![&'s geometry, &'s material, &'s mesh, &'s scene]> {
+//         let fields = hlist![&self.geometry, &self.material, &self.mesh, &self.scene];
+//         VisibleFields::new(fields, &["geometry", "material", "mesh", "scene"])
+//     }
+// }
+fn gen_impl_visible_fields(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    let field_names = field_idents.iter().map(|i| i.unraw().to_string()).collect_vec();
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<#(#params,)*> #ref_ident<#(#params,)*> {
+            /// Returns the fields that are not hidden, formatted by `Debug` as a map from their
+            /// names to their values. See [`VisibleFields`](#lib::VisibleFields).
+            #[inline(always)]
+            pub fn visible_fields<'_s>(&'_s self) -> #lib::VisibleFields<#lib::HList![#(&'_s #params),*]> {
+                let fields = #lib::hlist![#(&self.#field_idents),*];
+                #lib::VisibleFields::new(fields, &[#(#field_names,)*])
+            }
+        }
+    }
+}
+
+// Generates:
+// impl<geometry, material, mesh, scene> IntoIterator for CtxRef<geometry, material, mesh, scene>
+// where
+//     HList![geometry, material, mesh, scene]: SingleLiveField,
+//     <HList![geometry, material, mesh, scene] as SingleLiveField>::Output: IntoIterator,
+// {
+//     type Item = ...;
+//     type IntoIter = ...;
+//     fn into_iter(self) -> Self::IntoIter {
+//         let fields = hlist![self.geometry, self.material, self.mesh, self.scene];
+//         SingleLiveField::single_live_field(fields).into_iter()
+//     }
+// }
+//
+// impl<'t, geometry, material, mesh, scene> IntoIterator for &'t mut CtxRef<geometry, material, mesh, scene>
+// where ... {
+//     // Same as above, with the fields flattened with `RefFlatten<'t>`.
+// }
+//
+// Implemented only if exactly one field is not hidden, so that it's clear which field is iterated.
+// Not generated for structs without fields, as the bounds would not depend on any parameter.
+fn gen_impl_into_iterator(s: &Struct) -> pm::TokenStream {
+    if s.field_idents.is_empty() { return quote! {} }
+    let by_value = gen_into_iterator_by_value(s);
+    let by_mut_ref = gen_into_iterator_by_mut_ref(s);
+    quote! {
+        #by_value
+        #by_mut_ref
+    }
+}
+
+fn gen_into_iterator_by_value(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    gen_into_iterator_for(
+        lib,
+        &quote! { #(#params,)* },
+        &quote! { #ref_ident<#(#params,)*> },
+        &quote! {},
+        &quote! { #lib::HList![#(#params),*] },
+        &quote! { #lib::hlist![#(self.#field_idents),*] },
+    )
+}
+
+fn gen_into_iterator_by_mut_ref(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    gen_into_iterator_for(
+        lib,
+        &quote! { '_t, #(#params,)* },
+        &quote! { &'_t mut #ref_ident<#(#params,)*> },
+        &quote! { #(#params: #lib::RefFlatten<'_t>,)* },
+        &quote! { #lib::HList![#(<#params as #lib::RefFlatten<'_t>>::Output),*] },
+        &quote! { #lib::hlist![#(#lib::RefFlatten::ref_flatten(&mut self.#field_idents)),*] },
+    )
+}
+
+/// `IntoIterator` impl for `self_type`, iterating the single live field of the `fields` list.
+fn gen_into_iterator_for(
+    lib: &Ident,
+    impl_params: &pm::TokenStream,
+    self_type: &pm::TokenStream,
+    bounds: &pm::TokenStream,
+    fields_type: &pm::TokenStream,
+    fields: &pm::TokenStream,
+) -> pm::TokenStream {
+    let field = quote! { <#fields_type as #lib::SingleLiveField>::Output };
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<#impl_params> ::core::iter::IntoIterator for #self_type
+        where #bounds #fields_type: #lib::SingleLiveField, #field: ::core::iter::IntoIterator {
+            type Item = <#field as ::core::iter::IntoIterator>::Item;
+            type IntoIter = <#field as ::core::iter::IntoIterator>::IntoIter;
+            #[inline(always)]
+            fn into_iter(self) -> Self::IntoIter {
+                let fields = #fields;
+                ::core::iter::IntoIterator::into_iter(#lib::SingleLiveField::single_live_field(fields))
+            }
+        }
+    }
+}
+
+// Generates:
+// impl<_Idx, geometry, material, mesh, scene> Index<_Idx> for CtxRef<geometry, material, mesh, scene>
+// where
+//     geometry: SlotPtr,
+//     ...
+//     HList![<geometry as SlotPtr>::Ptr, ...]: SingleLiveField,
+//     <HList![<geometry as SlotPtr>::Ptr, ...] as SingleLiveField>::Output: LiveFieldPtr,
+//     <<HList![...] as SingleLiveField>::Output as LiveFieldPtr>::Target: Index<_Idx>,
+// {
+//     type Output = <<<HList![...] as SingleLiveField>::Output as LiveFieldPtr>::Target as Index<_Idx>>::Output;
+//     fn index(&self, index: _Idx) -> &Self::Output {
+//         let fields = hlist![SlotPtr::slot_ptr(&self.geometry), ...];
+//         let field = unsafe { LiveFieldPtr::field_ref(SingleLiveField::single_live_field(fields)) };
+//         Index::index(field, index)
+//     }
+// }
+//
+// impl<_Idx, geometry, material, mesh, scene> IndexMut<_Idx> for CtxRef<geometry, material, mesh, scene>
+// where ... {
+//     // Same as above, with `slot_ptr_mut` and `LiveFieldPtrMut`.
+// }
+//
+// Implemented only if exactly one field is not hidden, like `IntoIterator`, so that
+// `graph[edge_id]` indexes the edges of `p!(&<mut edges> Graph)`. `IndexMut` requires the field
+// to be borrowed mutably. The fields are found by pointers, as the types of references to them
+// would depend on the lifetime of `&self`. Not generated for structs without fields.
+fn gen_impl_index(s: &Struct) -> pm::TokenStream {
+    if s.field_idents.is_empty() { return quote! {} }
+    let index = gen_index(s);
+    let index_mut = gen_index_mut(s);
+    quote! {
+        #index
+        #index_mut
+    }
+}
+
+/// The bounds of the `Index` impl of the ref struct, and the type of the indexed field.
+fn index_bounds(s: &Struct) -> (pm::TokenStream, pm::TokenStream) {
+    let Struct { lib, params, .. } = s;
+    let ptrs = quote! { #lib::HList![#(<#params as #lib::SlotPtr>::Ptr),*] };
+    let field = quote! { <#ptrs as #lib::SingleLiveField>::Output };
+    let target = quote! { <#field as #lib::LiveFieldPtr>::Target };
+    let bounds = quote! {
+        #(#params: #lib::SlotPtr,)*
+        #ptrs: #lib::SingleLiveField,
+        #field: #lib::LiveFieldPtr,
+        #target: ::core::ops::Index<_Idx>,
     };
+    (bounds, target)
+}
 
-    // Generates:
-    // impl<'t, geometry, material, mesh, scene, geometry_other, material_other, mesh_other, scene_other>
-    // Join<&'t mut CtxRef<geometry_other, material_other, mesh_other, scene_other>>
-    // for &'t mut CtxRef<geometry, material, mesh, scene> where
-    //     geometry: UnifyFieldImpl<'t, geometry2>,
-    //     material: UnifyFieldImpl<'t, material2>,
-    //     mesh: UnifyFieldImpl<'t, mesh2>,
-    //     scene: UnifyFieldImpl<'t, scene2>,
-    // {
-    //     type Result = CtxRef<
-    //         <geometry as UnifyFieldImpl<'t, geometry_other>>::Result,
-    //         <material as UnifyFieldImpl<'t, material_other>>::Result,
-    //         <mesh as UnifyFieldImpl<'t, mesh_other>>::Result,
-    //         <scene as UnifyFieldImpl<'t, scene_other>>::Result,
-    //     >;
-    //     fn join(self, other: &'t mut CtxRef<geometry_other, material_other, mesh_other, scene_other>) -> Self::Result {
-    //         let geometry = self.geometry.join_field(&mut other.geometry);
-    //         let material = self.material.join_field(&mut other.material);
-    //         let mesh = self.mesh.join_field(&mut other.mesh);
-    //         let scene = self.scene.join_field(&mut other.scene);
-    //         CtxRef { geometry, material, mesh, scene }
-    //     }
-    // }
-    let impl_join = {
-        let other_params = params.iter().map(|i| Ident::new(&format!("{i}_other"), i.span())).collect_vec();
-        quote! {
-            #[allow(non_camel_case_types)]
-            impl<'_t, #(#params,)* #(#other_params,)*>
-            #lib::UnifyImpl<&'_t mut #ref_struct_ident<#(#other_params,)*>> for &'_t mut #ref_struct_ident<#(#params,)*>
-            where
-                #(#params: #lib::UnifyFieldImpl<'_t, #other_params>,)*
-            {
-                type Result = #ref_struct_ident<#(<#params as #lib::UnifyFieldImpl<'_t, #other_params>>::Result,)*>;
+fn gen_index(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    let (bounds, target) = index_bounds(s);
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<_Idx, #(#params,)*> ::core::ops::Index<_Idx> for #ref_ident<#(#params,)*>
+        where #bounds {
+            type Output = <#target as ::core::ops::Index<_Idx>>::Output;
+            #[inline(always)]
+            fn index(&self, index: _Idx) -> &Self::Output {
+                let fields = #lib::hlist![#(#lib::SlotPtr::slot_ptr(&self.#field_idents)),*];
+                // SAFETY: The pointer is created from the fields of `self`, which are borrowed for
+                // the lifetime of the returned reference.
+                let field = unsafe {
+                    #lib::LiveFieldPtr::field_ref(#lib::SingleLiveField::single_live_field(fields))
+                };
+                ::core::ops::Index::index(field, index)
+            }
+        }
+    }
+}
+
+fn gen_index_mut(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    let (bounds, target) = index_bounds(s);
+    let ptrs_mut = quote! { #lib::HList![#(<#params as #lib::SlotPtr>::PtrMut),*] };
+    let field_mut = quote! { <#ptrs_mut as #lib::SingleLiveField>::Output };
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<_Idx, #(#params,)*> ::core::ops::IndexMut<_Idx> for #ref_ident<#(#params,)*>
+        where
+            #bounds
+            #ptrs_mut: #lib::SingleLiveField,
+            #field_mut: #lib::LiveFieldPtrMut<Target = #target>,
+            #target: ::core::ops::IndexMut<_Idx>,
+        {
+            #[inline(always)]
+            fn index_mut(&mut self, index: _Idx) -> &mut Self::Output {
+                let fields = #lib::hlist![#(#lib::SlotPtr::slot_ptr_mut(&mut self.#field_idents)),*];
+                // SAFETY: The pointer is created from the fields of `self`, which are borrowed
+                // mutably for the lifetime of the returned reference.
+                let field = unsafe {
+                    #lib::LiveFieldPtrMut::field_mut(#lib::SingleLiveField::single_live_field(fields))
+                };
+                ::core::ops::IndexMut::index_mut(field, index)
+            }
+        }
+    }
+}
+
+// Generates:
+// impl<geometry, material, mesh, scene> CtxRef<geometry, material, mesh, scene> {
+//     pub fn geometry_mut<_Target: ?Sized>(&mut self) -> &mut _Target
+//     where geometry: MutField<Target = _Target> {
+//         MutField::field_mut(&mut self.geometry)
+//     }
+//     pub fn geometry_ref<_Target: ?Sized>(&self) -> &_Target
+//     where geometry: SharedField<Target = _Target> {
+//         SharedField::field_ref(&self.geometry)
+//     }
+//     pub fn take_geometry<_Target>(&self) -> _Target
+//     where geometry: SharedField<Target = _Target>, _Target: Copy {
+//         *SharedField::field_ref(&self.geometry)
+//     }
+//     ...
+// }
+//
+// Accessing a field that is not borrowed mutably, or is hidden, reports the `MutField` or
+// `SharedField` error instead of a type mismatch. The bounds mention the method-level `_Target`
+// param, so they are checked after the method is selected. Bounds on the struct params only, or
+// projections like `<geometry as MutField>::Target` in the signature, would make the method
+// disappear from lookup, reporting E0599 instead. The `take_$field` methods copy `Copy` fields
+// out of the borrow, leaving them unchanged. Not generated for `#[borrow(optional)]` fields.
+fn gen_field_accessors(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    let accessors = field_idents.iter().zip(params.iter()).zip(s.optional_fields.iter())
+        .filter(|(_, optional)| !**optional)
+        .map(|((field, param), _)| {
+            let name_mut = Ident::new(&format!("{}_mut", field.unraw()), field.span());
+            let name_ref = Ident::new(&format!("{}_ref", field.unraw()), field.span());
+            let name_take = Ident::new(&format!("take_{}", field.unraw()), field.span());
+            quote! {
                 #[inline(always)]
-                fn union(self, other: &'_t mut #ref_struct_ident<#(#other_params,)*>) -> Self::Result {
-                    #ref_struct_ident {
-                        #(#field_idents: #lib::UnifyFieldImpl::unify_field(&mut self.#field_idents, &mut other.#field_idents),)*
-                    }
+                pub fn #name_mut<_Target: ?Sized>(&mut self) -> &mut _Target
+                where #param: #lib::MutField<Target = _Target> {
+                    #lib::MutField::field_mut(&mut self.#field)
+                }
+
+                #[inline(always)]
+                pub fn #name_ref<_Target: ?Sized>(&self) -> &_Target
+                where #param: #lib::SharedField<Target = _Target> {
+                    #lib::SharedField::field_ref(&self.#field)
+                }
+
+                #[inline(always)]
+                pub fn #name_take<_Target>(&self) -> _Target
+                where #param: #lib::SharedField<Target = _Target>, _Target: ::core::marker::Copy {
+                    *#lib::SharedField::field_ref(&self.#field)
                 }
             }
+        });
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<#(#params,)*> #ref_ident<#(#params,)*> {
+            #(#accessors)*
         }
-    };
+    }
+}
 
-    // Generates:
-    // #[macro_export]
-    // macro_rules! _Ctx {
-    //     (@ $lt:lifetime [$t0:tt $t1:tt $t2:tt $t3:tt] [, $($lt2:lifetime)? * $($xs:tt)*]) => {
-    //         _Ctx! { @ $lt [
-    //             [lifetime_chooser!{ $lt $($lt2)? GeometryCtx }]
-    //             [lifetime_chooser!{ $lt $($lt2)? MaterialCtx }]
-    //             [lifetime_chooser!{ $lt $($lt2)? MeshCtx }]
-    //             [lifetime_chooser!{ $lt $($lt2)? SceneCtx }]
-    //         ] [$ ($xs) *] }
-    //     };
-    //     (@ $lt:lifetime [$t0:tt $t1:tt $t2:tt $t3:tt] [, mut * $ ($xs:tt) *]) => {
-    //         _Ctx! { @ $lt [
-    //             [lifetime_chooser!{ $lt $($lt2)? mut GeometryCtx }]
-    //             [lifetime_chooser!{ $lt $($lt2)? mut MaterialCtx }]
-    //             [lifetime_chooser!{ $lt $($lt2)? mut MeshCtx }]
-    //             [lifetime_chooser!{ $lt $($lt2)? mut SceneCtx }]
-    //         ] [$ ($xs) *] }
-    //     };
-    //
-    //
-    //     (@ $lt:lifetime [$t0:tt $t1:tt $t2:tt $t3:tt] [, $($lt2:lifetime)? $(ref)? geometry $ ($xs:tt) *]) => {
-    //         _Ctx! { @ $lt [[lifetime_chooser!{ $lt $($lt2)? GeometryCtx}] $t1 $t2 $t3] [$ ($xs) *] }
-    //     };
-    //     (@ $lt:lifetime [$t0:tt $t1:tt $t2:tt $t3:tt] [, $($lt2:lifetime)? $(ref)? material $ ($xs:tt) *]) => {
-    //         _Ctx! { @ $lt [$t0 [lifetime_chooser!{ $lt $($lt2)? MaterialCtx}] $t2 $t3] [$ ($xs) *] }
-    //     };
-    //     (@ $lt:lifetime [$t0:tt $t1:tt $t2:tt $t3:tt] [, $($lt2:lifetime)? $(ref)? mesh $ ($xs:tt) *]) => {
-    //         _Ctx! { @ $lt [$t0 $t1 [lifetime_chooser!{ $lt $($lt2)? MeshCtx}] $t3] [$ ($xs) *] }
-    //     };
-    //     (@ $lt:lifetime [$t0:tt $t1:tt $t2:tt $t3:tt] [, $($lt2:lifetime)? $(ref)? scene $ ($xs:tt) *]) => {
-    //         _Ctx! { @ $lt [$t0 $t1 $t2 [lifetime_chooser!{ $lt $($lt2)? SceneCtx}]] [$ ($xs) *] }
-    //     };
-    //
-    //
-    //     (@ $lt:lifetime [$t0:tt $t1:tt $t2:tt $t3:tt] [, $($lt2:lifetime)? mut geometry $ ($xs:tt) *]) => {
-    //         _Ctx! { @ $lt [[lifetime_chooser!{ $lt $($lt2)? mut GeometryCtx}] $t1 $t2 $t3] [$ ($xs) *] }
-    //     };
-    //     (@ $lt:lifetime [$t0:tt $t1:tt $t2:tt $t3:tt] [, $($lt2:lifetime)?mut material $ ($xs:tt) *]) => {
-    //         _Ctx! { @ $lt [$t0 [lifetime_chooser!{ $lt $($lt2)? mut MaterialCtx}] $t2 $t3] [$ ($xs) *] }
-    //     };
-    //     (@ $lt:lifetime [$t0:tt $t1:tt $t2:tt $t3:tt] [, $($lt2:lifetime)? mut mesh $ ($xs:tt) *]) => {
-    //         _Ctx! { @ $lt [$t0 $t1 [lifetime_chooser!{ $lt $($lt2)? mut MeshCtx}] $t3] [$ ($xs) *] }
-    //     };
-    //     (@ $lt:lifetime [$t0:tt $t1:tt $t2:tt $t3:tt] [, $($lt2:lifetime)? mut scene $ ($xs:tt) *]) => {
-    //         _Ctx! { @ $lt [$t0 $t1 $t2 [lifetime_chooser!{ $lt $($lt2)? mut SceneCtx}]] [$ ($xs) *] }
-    //     };
-    //
-    //
-    //     (@ $lt:lifetime [$t0:tt $t1:tt $t2:tt $t3:tt] [, ! geometry $ ($xs:tt) *]) => {
-    //         _Ctx! { @ $lt [[Hidden<GeometryCtx>] $t1 $t2 $t3] [$ ($xs) *] }
-    //     };
-    //     (@ $lt:lifetime [$t0:tt $t1:tt $t2:tt $t3:tt] [, ! material $ ($xs:tt) *]) => {
-    //         _Ctx! { @ $lt [$t0 [Hidden<MaterialCtx>] $t2 $t3] [$ ($xs) *] }
-    //     };
-    //     (@ $lt:lifetime [$t0:tt $t1:tt $t2:tt $t3:tt] [, ! mesh $ ($xs:tt) *]) => {
-    //         _Ctx! { @ $lt [$t0 $t1 [Hidden<MeshCtx>] $t3] [$ ($xs) *] }
-    //     };
-    //     (@ $lt:lifetime [$t0:tt $t1:tt $t2:tt $t3:tt] [, ! scene $ ($xs:tt) *]) => {
-    //         _Ctx! { @ $lt [$t0 $t1 $t2 [Hidden<SceneCtx>]] [$ ($xs) *] }
-    //     };
-    //
-    //
-    //     (@ $lt:lifetime [$ ([$ ($ts:tt) *]) *] [$ (,) *]) => {
-    //         CtxRef < $ ($ ($ts) *), * >
-    //     };
-    //
-    //     (@ $($ts:tt)*) => { error { $($ts)* } };
-    //
-    //     ($lt:lifetime $ ($ts:tt) *) => {
-    //         _Ctx! { @ $lt [[Hidden<GeometryCtx>] [Hidden<MaterialCtx>] [Hidden<MeshCtx>] [Hidden<SceneCtx>]] [$($ts)*] }
-    //     };
-    //
-    //     ($($ts:tt)*) => {
-    //         _Ctx! { @ '_ [[Hidden<GeometryCtx>] [Hidden<MaterialCtx>] [Hidden<MeshCtx>] [Hidden<SceneCtx>]] [, $ ($ts) *] }
-    //     };
-    // }
-    // pub use _Ctx as Ctx;
-    let ref_macro = {
-        let all_hidden = quote! {#([#lib::Hidden<#module::#field_types>])*};
-        let all_ref = quote! {#([#lib::lifetime_chooser!{$lt $($lt2)? #module::#field_types}])*};
-        let all_ref_mut = quote! {#([#lib::lifetime_chooser!{$lt $($lt2)? mut #module::#field_types}])*};
-        let ts_idents = field_idents.iter().enumerate().map(|(i, _)| Ident::new(&format!("t{i}"), Span::call_site())).collect_vec();
-        let ts = ts_idents.iter().map(|t| quote!($#t)).collect_vec();
-        let struct_ident2 = Ident::new(&format!("_{}", struct_ident), struct_ident.span());
-        let gen_patterns = |pattern: pm::TokenStream, f: Box<dyn Fn(&syn::Type) -> pm::TokenStream>| {
-            field_idents.iter().zip(field_types.iter()).enumerate().map(|(i, (name, tp))| {
-                let result = f(tp);
-                let mut results = ts.iter().collect_vec();
-                results[i] = &result;
-                quote! { (@ $lt:lifetime [#(#ts:tt)*] [, #pattern #name $($xs:tt)*]) => {
-                $crate::#struct_ident! {@ $lt [#(#results)*] [$($xs)*]} };
+// Generates:
+// #[repr(transparent)]
+// pub struct CtxRefView<'_t, geometry, material, mesh, scene>(
+//     &'_t mut CtxRef<geometry, material, mesh, scene>
+// );
+//
+// impl<geometry, material, mesh, scene> CtxRef<geometry, material, mesh, scene> {
+//     pub fn view(&mut self) -> CtxRefView<'_, geometry, material, mesh, scene> {
+//         CtxRefView(self)
+//     }
+// }
+//
+// impl<'_t, geometry, material, mesh, scene> CtxRefView<'_t, geometry, material, mesh, scene> {
+//     pub fn geometry<'_s>(&'_s mut self) -> <geometry as RefFlatten<'_s>>::Output
+//     where geometry: VisibleField + RefFlatten<'_s> {
+//         RefFlatten::ref_flatten(&mut self.0.geometry)
+//     }
+//     ...
+// }
+//
+// Accessing a hidden field reports the `VisibleField` error instead of a type mismatch.
+fn gen_view(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    let view_ident = Ident::new(&format!("{ref_ident}View"), ref_ident.span());
+    let doc = format!("Accessors of the fields of [`{ref_ident}`] that are part of the borrow.");
+    let accessors = field_idents.iter().zip(params.iter()).map(|(field, param)| quote! {
+        #[inline(always)]
+        pub fn #field<'_s>(&'_s mut self) -> <#param as #lib::RefFlatten<'_s>>::Output
+        where #param: #lib::VisibleField + #lib::RefFlatten<'_s> {
+            #lib::RefFlatten::ref_flatten(&mut self.0.#field)
+        }
+    });
+    quote! {
+        #[doc = #doc]
+        #[repr(transparent)]
+        #[allow(non_camel_case_types)]
+        pub struct #view_ident<'_t, #(#params),*>(&'_t mut #ref_ident<#(#params),*>);
+
+        #[allow(non_camel_case_types)]
+        impl<#(#params,)*> #ref_ident<#(#params,)*> {
+            /// Returns the accessors of the borrowed fields. In contrast to accessing the fields
+            /// directly, accessing hidden fields reports an error explaining that the field is
+            /// not part of the borrow.
+            #[inline(always)]
+            pub fn view(&mut self) -> #view_ident<'_, #(#params),*> {
+                #view_ident(self)
             }
-            }).collect_vec()
+        }
+
+        #[allow(non_camel_case_types)]
+        impl<'_t, #(#params,)*> #view_ident<'_t, #(#params,)*> {
+            #(#accessors)*
+        }
+    }
+}
+
+// Generates:
+// #[macro_export]
+// macro_rules! _Ctx {
+//     (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$t0:tt $t1:tt $t2:tt $t3:tt] [, $($lt2:lifetime)? * $($xs:tt)*]) => {
+//         $crate::data::Ctx! { @ $lib $lt [$($ps)*] [
+//             [$lib::lifetime_chooser!{ $lt $($lt2)? $lib::FieldAt<N0, $crate::data::Ctx $($ps)*> }]
+//             [$lib::lifetime_chooser!{ $lt $($lt2)? $lib::FieldAt<N1, $crate::data::Ctx $($ps)*> }]
+//             [$lib::lifetime_chooser!{ $lt $($lt2)? $lib::FieldAt<N2, $crate::data::Ctx $($ps)*> }]
+//             [$lib::lifetime_chooser!{ $lt $($lt2)? $lib::FieldAt<N3, $crate::data::Ctx $($ps)*> }]
+//         ] [$ ($xs) *] }
+//     };
+//     (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$t0:tt $t1:tt $t2:tt $t3:tt] [, mut * $ ($xs:tt) *]) => {
+//         $crate::data::Ctx! { @ $lib $lt [$($ps)*] [
+//             [$lib::lifetime_chooser!{ $lt $($lt2)? mut $lib::FieldAt<N0, $crate::data::Ctx $($ps)*> }]
+//             [$lib::lifetime_chooser!{ $lt $($lt2)? mut $lib::FieldAt<N1, $crate::data::Ctx $($ps)*> }]
+//             [$lib::lifetime_chooser!{ $lt $($lt2)? mut $lib::FieldAt<N2, $crate::data::Ctx $($ps)*> }]
+//             [$lib::lifetime_chooser!{ $lt $($lt2)? mut $lib::FieldAt<N3, $crate::data::Ctx $($ps)*> }]
+//         ] [$ ($xs) *] }
+//     };
+//
+//
+//     (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$t0:tt $t1:tt $t2:tt $t3:tt] [, $($lt2:lifetime)? $(ref)? geometry $ ($xs:tt) *]) => {
+//         $crate::data::Ctx! { @ $lib $lt [$($ps)*] [[$lib::lifetime_chooser!{ $lt $($lt2)? $lib::FieldAt<N0, $crate::data::Ctx $($ps)*>}] $t1 $t2 $t3] [$ ($xs) *] }
+//     };
+//
+//     ...
+//
+//     (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$t0:tt $t1:tt $t2:tt $t3:tt] [, $($lt2:lifetime)? mut geometry $ ($xs:tt) *]) => {
+//         $crate::data::Ctx! { @ $lib $lt [$($ps)*] [[$lib::lifetime_chooser!{ $lt $($lt2)? mut $lib::FieldAt<N0, $crate::data::Ctx $($ps)*>}] $t1 $t2 $t3] [$ ($xs) *] }
+//     };
+//
+//     ...
+//
+//     (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$t0:tt $t1:tt $t2:tt $t3:tt] [, ! geometry $ ($xs:tt) *]) => {
+//         $crate::data::Ctx! { @ $lib $lt [$($ps)*] [[$lib::Hidden<$lib::FieldAt<N0, $crate::data::Ctx $($ps)*>>] $t1 $t2 $t3] [$ ($xs) *] }
+//     };
+//
+//     ...
+//
+//     (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$ ([$ ($ts:tt) *]) *] [$ (,) *]) => {
+//         $crate::data::CtxRef < $ ($ ($ts) *), * >
+//     };
+//
+//     // Error patterns, see `gen_error_patterns`.
+//
+//     ([$lib:tt] [$($ps:tt)*] $lt:lifetime $ ($ts:tt) *) => {
+//         $crate::data::Ctx! { @ $lib $lt [$($ps)*] [[$lib::Hidden<$lib::FieldAt<N0, $crate::data::Ctx $($ps)*>>] ...] [$($ts)*] }
+//     };
+//
+//     ([$lib:tt] [$($ps:tt)*] $($ts:tt)*) => {
+//         $crate::data::Ctx! { @ $lib '_ [$($ps)*] [[$lib::Hidden<$lib::FieldAt<N0, $crate::data::Ctx $($ps)*>>] ...] [, $ ($ts) *] }
+//     };
+// }
+// pub use _Ctx as Ctx;
+//
+// The `[$($ps:tt)*]` group contains generic arguments of the struct as provided by the user, like
+// `<'v, V>`. They are used to resolve field types of parametrized structs.
+//
+// The `$lib` token is the `$crate` of this library, passed by `p!`, so that the library types
+// resolve regardless of how the crate using the macro refers to this library. The leading `crate`
+// of the module path is replaced by `$crate`, referring to the crate defining the struct.
+fn gen_ref_macro(s: &Struct) -> pm::TokenStream {
+    let Struct { ident, ref_ident, macro_ident, selector_ident, field_idents, .. } = s;
+    let module = s.module_prefix();
+    let field_types = (0..field_idents.len()).map(|i| {
+        let n = nat(&quote! {$lib}, i);
+        quote! {$lib::FieldAt<#n, #module #ident $($ps)*>}
+    }).collect_vec();
+    let all_hidden = quote! {#([$lib::Hidden<#field_types>])*};
+    let ts_idents = field_idents.iter().enumerate().map(|(i, _)| Ident::new(&format!("t{i}"), Span::call_site())).collect_vec();
+    let ts = ts_idents.iter().map(|t| quote!($#t)).collect_vec();
+    // Fields marked with `#[borrow(additive)]` keep their slot when selected with `*`.
+    let gen_all = |prefix: pm::TokenStream| {
+        let slots = field_types.iter().enumerate().map(|(i, t)| {
+            if s.additive_fields[i] { return ts[i].clone() }
+            let slot = optional_slot(s.optional_fields[i], quote! {$lib::lifetime_chooser!{$lt $($lt2)? #prefix #t}});
+            quote! {[#slot]}
+        });
+        quote! {#(#slots)*}
+    };
+    let all_ref = gen_all(quote! {});
+    let all_ref_mut = gen_all(quote! {mut});
+    let patterns_duplicate = gen_duplicate_patterns(s);
+    let patterns_field = gen_field_patterns(s, &field_types, &ts);
+    let patterns_group = gen_group_patterns(s);
+    let flatten_patterns = gen_flatten_patterns(s, &ts);
+    let error_patterns = gen_error_patterns(s);
+    // A field named `only` is selected if it is not followed by other selectors.
+    let patterns_only_field = field_idents.iter().any(|f| f == "only").then(|| quote! {
+        (checked [$lib:tt] [$($ps:tt)*] $lt:lifetime, only $(, $($ts:tt)*)?) => {
+            #module #selector_ident! {@ $lib $lt [$($ps)*] [#all_hidden] [, only $(, $($ts)*)?]}
+        };
+        (checked [$lib:tt] [$($ps:tt)*] only $(, $($ts:tt)*)?) => {
+            #module #selector_ident! {@ $lib '_ [$($ps)*] [#all_hidden] [, only $(, $($ts)*)?]}
+        };
+    });
+    let macro_vis = &s.macro_vis;
+    let macro_export = matches!(macro_vis, syn::Visibility::Public(_)).then(|| quote! {#[macro_export]});
+    quote! {
+        #macro_export
+        macro_rules! #macro_ident {
+            #(#patterns_duplicate)*
+            (@ $lib:tt $lt:lifetime [$($ps:tt)*] [#(#ts:tt)*] [, ! * $($xs:tt)*]) => {
+                #module #selector_ident! {@ $lib $lt [$($ps)*] [#all_hidden] [$($xs)*]}
+            };
+            (@ $lib:tt $lt:lifetime [$($ps:tt)*] [#(#ts:tt)*] [, $($lt2:lifetime)? $(ref)? * $($xs:tt)*]) => {
+                #module #selector_ident! {@ $lib $lt [$($ps)*] [#all_ref] [$($xs)*]}
+            };
+            (@ $lib:tt $lt:lifetime [$($ps:tt)*] [#(#ts:tt)*] [, $($lt2:lifetime)? mut * $($xs:tt)*]) => {
+                #module #selector_ident! {@ $lib $lt [$($ps)*] [#all_ref_mut] [$($xs)*]}
+            };
+            #(#patterns_field)*
+            #(#patterns_group)*
+            (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$([$($ts:tt)*])*] [$(,)*]) => { #module #ref_ident<$($($ts)*),*> };
+            (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, @ $fields:ident $($xs:tt)*]) => {
+                $fields! { [#module #selector_ident] [@ $lib $lt [$($ps)*] [$($ts)*]] [$($xs)*] }
+            };
+            (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, mut @ $fields:ident $($xs:tt)*]) => {
+                $fields! { [$lib::mut_by_default] [[#module #selector_ident] [@ $lib $lt [$($ps)*] [$($ts)*]] [$($xs)*]] [] }
+            };
+            #(#flatten_patterns)*
+            #(#error_patterns)*
+
+            #patterns_only_field
+            (checked [$lib:tt] [$($ps:tt)*] $lt:lifetime, only $($ts:tt)*) => {
+                $lib::mut_by_default! { [#module #selector_ident] [@ $lib $lt [$($ps)*] [#all_hidden]] [] [, $($ts)*] }
+            };
+            (checked [$lib:tt] [$($ps:tt)*] only $($ts:tt)*) => {
+                $lib::mut_by_default! { [#module #selector_ident] [@ $lib '_ [$($ps)*] [#all_hidden]] [] [, $($ts)*] }
+            };
+            // A leading lifetime is the default one only if followed by a comma, so that
+            // `<'a mut edges>` sets the lifetime of the `edges` field only. It replaces `'_` for
+            // every selected field without a lifetime of its own, including fields selected with
+            // `*`, groups, and nested selectors, so aliases like `p!(<'t, nodes, 'm edges> Graph)`
+            // can be used in struct fields, where `'_` is not allowed.
+            (checked [$lib:tt] [$($ps:tt)*] $lt:lifetime $(, $($ts:tt)*)?) => {
+                #module #selector_ident! {@ $lib $lt [$($ps)*] [#all_hidden] [$(, $($ts)*)?]}
+            };
+            (checked [$lib:tt] [$($ps:tt)*] $($ts:tt)*) => {
+                #module #selector_ident! {@ $lib '_ [$($ps)*] [#all_hidden] [,$($ts)*]}
+            };
+        }
+
+        #macro_vis use #macro_ident as #selector_ident;
+    }
+}
+
+// Generates, for `#[borrow(flatten)]` on the `rendering: RenderingCtx` field:
+// (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$t0:tt [$($tf:tt)*]] [, $($lt2:lifetime)? mut $name:ident $($xs:tt)*]) => {
+//     $crate::data::Ctx! {@ $lib $lt [$($ps)*] [
+//         $t0 [$lib::MergedNested<$($tf)*, $crate::data::RenderingCtx! {[$lib] [] $lt, $($lt2)? mut $name}>]
+//     ] [$($xs)*]}
+// };
+// (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$t0:tt [$($tf:tt)*]] [, $($lt2:lifetime)? $(ref)? $name:ident $($xs:tt)*]) => {
+//     ...
+// };
+//
+// These patterns are matched only if no field of the struct matched, so the fields of the struct
+// take precedence over the fields of the flattened one with the same name. Unknown fields are
+// reported by the selector macro of the flattened struct.
+fn gen_flatten_patterns(s: &Struct, ts: &[pm::TokenStream]) -> Vec<pm::TokenStream> {
+    let Struct { selector_ident, field_types, .. } = s;
+    let Some(i) = s.flatten_field else { return vec![] };
+    let Some(nested_macro) = field_types.get(i).and_then(|ty| nested_macro_path(s, ty)) else { return vec![] };
+    let module = s.module_prefix();
+    let mut ts_patterns = ts.iter().map(|t| quote! {#t:tt}).collect_vec();
+    ts_patterns[i] = quote! {[$($tf:tt)*]};
+    let gen_pattern = |pattern: pm::TokenStream, prefix: pm::TokenStream| {
+        let result = quote! {
+            [$lib::MergedNested<$($tf)*, #nested_macro! {[$lib] [] $lt, $($lt2)? #prefix $name}>]
         };
-        let patterns_ref = gen_patterns(quote!{$($lt2:lifetime)? $(ref)?}, Box::new(|t: &syn::Type| quote!{[#lib::lifetime_chooser!{$lt $($lt2)? #module::#t}]}));
-        let patterns_ref_mut = gen_patterns(quote!{$($lt2:lifetime)? mut}, Box::new(|t: &syn::Type| quote!{[#lib::lifetime_chooser!{$lt $($lt2)? mut #module::#t}]}));
-        let patterns_ref_none = gen_patterns(quote!{!}, Box::new(|t: &syn::Type| quote!{[#lib::Hidden<#module::#t>]}));
+        let mut results = ts.iter().cloned().collect_vec();
+        results[i] = result;
+        quote! { (@ $lib:tt $lt:lifetime [$($ps:tt)*] [#(#ts_patterns)*] [, #pattern $name:ident $($xs:tt)*]) => {
+            #module #selector_ident! {@ $lib $lt [$($ps)*] [#(#results)*] [$($xs)*]} };
+        }
+    };
+    vec![
+        gen_pattern(quote! {$($lt2:lifetime)? mut}, quote! {mut}),
+        gen_pattern(quote! {$($lt2:lifetime)? $(ref)?}, quote! {}),
+    ]
+}
+
+// Generates:
+// (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, $($lt2:lifetime)? $(mut)? $(ref)? @ $name:tt $($xs:tt)*]) => {
+//     compile_error!(concat!("Unknown group `", stringify!($name), "` of `Ctx`. ..."))
+// };
+// (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, $($lt2:lifetime)? mut $name:tt $($xs:tt)*]) => {
+//     compile_error!(concat!("Unknown field `", stringify!($name), "` of `Ctx`. Valid fields: geometry, material, mesh, scene."))
+// };
+// ...
+//
+// These patterns are matched only if no valid selector matched, so they report the first unknown
+// field or group in the selector list.
+fn gen_error_patterns(s: &Struct) -> Vec<pm::TokenStream> {
+    let Struct { ident, field_idents, groups, .. } = s;
+    let names = |idents: Vec<&Ident>| if idents.is_empty() {
+        "none".to_string()
+    } else {
+        idents.iter().map(|i| i.to_string()).join(", ")
+    };
+    let fields_info = format!("` of `{ident}`. Valid fields: {}.", names(field_idents.iter().collect()));
+    let groups_info = format!("` of `{ident}`. Valid groups: {}.", names(groups.iter().map(|g| &g.ident).collect()));
+    let field_error = quote! {
+        compile_error!(concat!("Unknown field `", stringify!($name), #fields_info))
+    };
+    let group_error = quote! {
+        compile_error!(concat!("Unknown group `", stringify!($name), #groups_info))
+    };
+    [
+        quote! {(@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, $($lt2:lifetime)? $(mut)? $(ref)? @ $name:tt $($xs:tt)*]) => { #group_error };},
+        quote! {(@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, ! @ $name:tt $($xs:tt)*]) => { #group_error };},
+        quote! {(@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, $($lt2:lifetime)? mut $name:tt $($xs:tt)*]) => { #field_error };},
+        quote! {(@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, $($lt2:lifetime)? ref $name:tt $($xs:tt)*]) => { #field_error };},
+        quote! {(@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, ! $name:tt $($xs:tt)*]) => { #field_error };},
+        quote! {(@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, $lt2:lifetime $name:tt $($xs:tt)*]) => { #field_error };},
+        quote! {(@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, $name:tt $($xs:tt)*]) => { #field_error };},
+        quote! {(@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [$($xs:tt)*]) => {
+            compile_error!(concat!("Invalid selector `", stringify!($($xs)*), "` of `", stringify!(#ident), "`."))
+        };},
+        quote! {(@ $($ts:tt)*) => {
+            compile_error!(concat!("Invalid selector of `", stringify!(#ident), "`."))
+        };},
+    ].into_iter().collect()
+}
+
+// Whether the field type is a path without generic arguments, which is required for nested
+// borrows.
+fn is_nested_type(ty: &syn::Type) -> bool {
+    let syn::Type::Path(ty) = ty else { return false };
+    ty.qself.is_none() && ty.path.segments.iter().all(|segment| segment.arguments.is_none())
+}
+
+// Path of the selector macro of the field type, like `crate::data::MeshCtx`. Available only for
+// field types that are paths without generic arguments. Relative paths are resolved in the
+// module of the struct.
+fn nested_macro_path(s: &Struct, ty: &syn::Type) -> Option<pm::TokenStream> {
+    if !is_nested_type(ty) { return None }
+    let syn::Type::Path(ty) = ty else { return None };
+    let path = &ty.path;
+    let is_absolute = path.leading_colon.is_some()
+        || path.segments.first().is_some_and(|segment| segment.ident == "crate");
+    let module = if is_absolute { quote! {} } else { s.module_prefix() };
+    let path = macro_path(path);
+    Some(quote! {#module #path})
+}
+
+/// Borrow slot of a field for the given field type, and whether the field is marked with
+/// `#[borrow(optional)]`.
+type SlotFn = dyn Fn(&pm::TokenStream, bool) -> pm::TokenStream;
+
+// Borrow slot of a field, wrapped in `Option` for `#[borrow(optional)]` fields.
+fn optional_slot(optional: bool, slot: pm::TokenStream) -> pm::TokenStream {
+    if optional { quote! {::core::option::Option<#slot>} } else { slot }
+}
+
+// Generates:
+// ([$lib:tt] [$($ps:tt)*] $($ts:tt)*) => {
+//     $crate::data::Ctx! {@check [[$lib] [$($ps)*] $($ts)*] [_ _ _ _] [, $($ts)*]}
+// };
+// (@check $args:tt [x $f1:tt $f2:tt $f3:tt] [, $($lt2:lifetime)? $(mut)? $(ref)? geometry $($xs:tt)*]) => {
+//     compile_error!("Field `geometry` of `Ctx` is selected more than once.")
+// };
+// (@check $args:tt [$f0:tt $f1:tt $f2:tt $f3:tt] [, $($lt2:lifetime)? $(mut)? $(ref)? geometry $($xs:tt)*]) => {
+//     $crate::data::Ctx! {@check $args [x $f1 $f2 $f3] [$($xs)*]}
+// };
+// ...
+// (@check $args:tt $fs:tt [, $lt2:lifetime, $($xs:tt)*]) => { ... [, $($xs)*] ... };
+// (@check $args:tt $fs:tt [, ! $name:tt $($xs:tt)*]) => { ... [$($xs)*] ... };
+// ...
+// (@check $args:tt $fs:tt [$x:tt $($xs:tt)*]) => { ... [$($xs)*] ... };
+// (@check [$($args:tt)*] $fs:tt []) => {
+//     $crate::data::Ctx! {checked $($args)*}
+// };
+//
+// Before expanding the selectors, the fields named in them are marked in the `$fs` list, so that
+// naming a field twice, like in `p!(<mut nodes, nodes> Graph)`, is reported. Only the selectors
+// written in the invocation are checked, so fields selected with `*`, `!field`, groups, and field
+// lists can still be overridden by later selectors.
+fn gen_duplicate_patterns(s: &Struct) -> Vec<pm::TokenStream> {
+    let Struct { ident, field_idents, selector_ident, .. } = s;
+    let module = s.module_prefix();
+    let fs = (0..field_idents.len()).map(|i| Ident::new(&format!("f{i}"), Span::call_site())).collect_vec();
+    let unset = fs.iter().map(|_| quote! {_});
+    let patterns_fields = field_idents.iter().enumerate().map(|(i, name)| {
+        let mut duplicate = fs.iter().map(|f| quote! {$#f:tt}).collect_vec();
+        duplicate[i] = quote! {x};
+        let mut marked = fs.iter().map(|f| quote! {$#f}).collect_vec();
+        marked[i] = quote! {x};
+        let fs_pattern = fs.iter().map(|f| quote! {$#f:tt});
+        let error = format!("Field `{}` of `{ident}` is selected more than once.", name.unraw());
         quote! {
-            #[macro_export]
-            macro_rules! #struct_ident2 {
-                (@ $lt:lifetime [#(#ts:tt)*] [, ! * $($xs:tt)*]) => {
-                    $crate::#struct_ident! {@ $lt [#all_hidden] [$($xs)*]}
-                };
-                (@ $lt:lifetime [#(#ts:tt)*] [, $($lt2:lifetime)? * $($xs:tt)*]) => {
-                    $crate::#struct_ident! {@ $lt [#all_ref] [$($xs)*]}
-                };
-                (@ $lt:lifetime [#(#ts:tt)*] [, $($lt2:lifetime)? mut * $($xs:tt)*]) => {
-                    $crate::#struct_ident! {@ $lt [#all_ref_mut] [$($xs)*]}
-                };
-                #(#patterns_ref)*
-                #(#patterns_ref_mut)*
-                #(#patterns_ref_none)*
-                (@ $lt:lifetime [$([$($ts:tt)*])*] [$(,)*]) => { #module::#ref_struct_ident<$($($ts)*),*> };
-                (@ $($ts:tt)*) => { error {$($ts)*} };
-
-                ($lt:lifetime $($ts:tt)*) => {
-                    $crate::#struct_ident! {@ $lt [#all_hidden] [$($ts)*]}
-                };
-                ($($ts:tt)*) => {
-                    $crate::#struct_ident! {@ '_ [#all_hidden] [,$($ts)*]}
-                };
+            (@check $args:tt [#(#duplicate)*] [, $($lt2:lifetime)? $(mut)? $(ref)? #name $($xs:tt)*]) => {
+                compile_error!(#error)
+            };
+            (@check $args:tt [#(#fs_pattern)*] [, $($lt2:lifetime)? $(mut)? $(ref)? #name $($xs:tt)*]) => {
+                #module #selector_ident! {@check $args [#(#marked)*] [$($xs)*]}
+            };
+        }
+    }).collect_vec();
+    // The `only` keyword is skipped, unless it can be a field name.
+    let pattern_only = field_idents.iter().all(|f| f != "only").then(|| quote! {
+        (@check $args:tt $fs:tt [, only $($xs:tt)*]) => {
+            #module #selector_ident! {@check $args $fs [, $($xs)*]}
+        };
+    });
+    let patterns = quote! {
+        ([$lib:tt] [$($ps:tt)*] $($ts:tt)*) => {
+            #module #selector_ident! {@check [[$lib] [$($ps)*] $($ts)*] [#(#unset)*] [, $($ts)*]}
+        };
+        #pattern_only
+        #(#patterns_fields)*
+        (@check $args:tt $fs:tt [, $lt2:lifetime, $($xs:tt)*]) => {
+            #module #selector_ident! {@check $args $fs [, $($xs)*]}
+        };
+        (@check $args:tt $fs:tt [, ! $name:tt $($xs:tt)*]) => {
+            #module #selector_ident! {@check $args $fs [$($xs)*]}
+        };
+        (@check $args:tt $fs:tt [, @ $name:tt $($xs:tt)*]) => {
+            #module #selector_ident! {@check $args $fs [$($xs)*]}
+        };
+        (@check $args:tt $fs:tt [, $name:tt $($xs:tt)*]) => {
+            #module #selector_ident! {@check $args $fs [$($xs)*]}
+        };
+        (@check $args:tt $fs:tt [$x:tt $($xs:tt)*]) => {
+            #module #selector_ident! {@check $args $fs [$($xs)*]}
+        };
+        (@check [$($args:tt)*] $fs:tt []) => {
+            #module #selector_ident! {checked $($args)*}
+        };
+    };
+    vec![patterns]
+}
+
+// Generates, for every field:
+// (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$t0:tt $t1:tt $t2:tt $t3:tt] [, $($lt2:lifetime)? $(ref)? geometry $($xs:tt)*]) => {
+//     $crate::data::Ctx! { @ $lib $lt [$($ps)*] [
+//         [$lib::lifetime_chooser!{ $lt $($lt2)? $lib::FieldAt<N0, $crate::data::Ctx $($ps)*> }] $t1 $t2 $t3
+//     ] [$($xs)*] }
+// };
+// (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$t0:tt $t1:tt $t2:tt $t3:tt] [, $($lt2:lifetime)? mut geometry $($xs:tt)*]) => {
+//     $crate::data::Ctx! { @ $lib $lt [$($ps)*] [
+//         [$lib::lifetime_chooser!{ $lt $($lt2)? mut $lib::FieldAt<N0, $crate::data::Ctx $($ps)*> }] $t1 $t2 $t3
+//     ] [$($xs)*] }
+// };
+// (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$t0:tt $t1:tt $t2:tt $t3:tt] [, ! geometry $($xs:tt)*]) => {
+//     $crate::data::Ctx! { @ $lib $lt [$($ps)*] [
+//         [$lib::Hidden<$lib::FieldAt<N0, $crate::data::Ctx $($ps)*>>] $t1 $t2 $t3
+//     ] [$($xs)*] }
+// };
+// (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$t0:tt $t1:tt $t2:tt $t3:tt] [, geometry ($($sel:tt)*) $($xs:tt)*]) => {
+//     $crate::data::Ctx! { @ $lib $lt [$($ps)*] [
+//         [$lib::Nested<$lib::FieldAt<N0, $crate::data::Ctx $($ps)*>, $crate::data::GeometryCtx! {[$lib] [] $lt, $($sel)*}>]
+//         $t1 $t2 $t3
+//     ] [$($xs)*] }
+// };
+//
+// The nested patterns are generated only for fields with types supported by `nested_macro_path`.
+// For `#[borrow(optional)]` fields, the `&` and `&mut` slots are wrapped in `Option`.
+fn gen_field_patterns(
+    s: &Struct,
+    field_types: &[pm::TokenStream],
+    ts: &[pm::TokenStream]
+) -> Vec<pm::TokenStream> {
+    let Struct { selector_ident, field_idents, .. } = s;
+    let module = s.module_prefix();
+    let gen_patterns = |pattern: pm::TokenStream, f: Box<SlotFn>| {
+        field_idents.iter().zip(field_types.iter()).zip(s.optional_fields.iter()).enumerate().map(|(i, ((name, tp), optional))| {
+            let result = f(tp, *optional);
+            let mut results = ts.iter().collect_vec();
+            results[i] = &result;
+            quote! { (@ $lib:tt $lt:lifetime [$($ps:tt)*] [#(#ts:tt)*] [, #pattern #name $($xs:tt)*]) => {
+                #module #selector_ident! {@ $lib $lt [$($ps)*] [#(#results)*] [$($xs)*]} };
             }
+        }).collect_vec()
+    };
+    let patterns_nested = field_idents.iter().zip(field_types.iter()).zip(s.field_types.iter()).enumerate()
+        .filter_map(|(i, ((name, tp), ty))| {
+            let nested_macro = nested_macro_path(s, ty)?;
+            let result = quote! {[$lib::Nested<#tp, #nested_macro! {[$lib] [] $lt, $($sel)*}>]};
+            let mut results = ts.iter().collect_vec();
+            results[i] = &result;
+            Some(quote! { (@ $lib:tt $lt:lifetime [$($ps:tt)*] [#(#ts:tt)*] [, #name ($($sel:tt)*) $($xs:tt)*]) => {
+                #module #selector_ident! {@ $lib $lt [$($ps)*] [#(#results)*] [$($xs)*]} };
+            })
+        }).collect_vec();
+    let patterns_ref = gen_patterns(quote!{$($lt2:lifetime)? $(ref)?}, Box::new(|t, optional| {
+        let slot = optional_slot(optional, quote!{$lib::lifetime_chooser!{$lt $($lt2)? #t}});
+        quote!{[#slot]}
+    }));
+    let patterns_ref_mut = gen_patterns(quote!{$($lt2:lifetime)? mut}, Box::new(|t, optional| {
+        let slot = optional_slot(optional, quote!{$lib::lifetime_chooser!{$lt $($lt2)? mut #t}});
+        quote!{[#slot]}
+    }));
+    let patterns_ref_none = gen_patterns(quote!{!}, Box::new(|t, _| quote!{[$lib::Hidden<#t>]}));
+    [patterns_nested, patterns_ref, patterns_ref_mut, patterns_ref_none].concat()
+}
 
-            pub use #struct_ident2 as #struct_ident;
+// Generates, for `#[group(rendering = geometry, material, mesh)]`:
+// (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, $($lt2:lifetime)? $(ref)? @rendering $($xs:tt)*]) => {
+//     $crate::data::Ctx! {@ $lib $lt [$($ps)*] [$($ts)*] [, $($lt2)? geometry, $($lt2)? material, $($lt2)? mesh $($xs)*]}
+// };
+// (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, $($lt2:lifetime)? mut @rendering $($xs:tt)*]) => {
+//     $crate::data::Ctx! {@ $lib $lt [$($ps)*] [$($ts)*] [, $($lt2)? mut geometry, $($lt2)? mut material, $($lt2)? mut mesh $($xs)*]}
+// };
+// (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, ! @rendering $($xs:tt)*]) => {
+//     $crate::data::Ctx! {@ $lib $lt [$($ps)*] [$($ts)*] [, !geometry, !material, !mesh $($xs)*]}
+// };
+//
+// Groups are expanded to individual field selectors in place, so they follow the same override
+// rules as the fields.
+fn gen_group_patterns(s: &Struct) -> Vec<pm::TokenStream> {
+    let Struct { selector_ident, groups, .. } = s;
+    let module = s.module_prefix();
+    let gen_pattern = |pattern: pm::TokenStream, prefix: &pm::TokenStream, group: &Group| {
+        let name = &group.ident;
+        let fields = group.fields.iter().map(|field| quote! {, #prefix #field});
+        quote! { (@ $lib:tt $lt:lifetime [$($ps:tt)*] [$($ts:tt)*] [, #pattern @ #name $($xs:tt)*]) => {
+            #module #selector_ident! {@ $lib $lt [$($ps)*] [$($ts)*] [#(#fields)* $($xs)*]} };
         }
     };
+    groups.iter().flat_map(|group| [
+        gen_pattern(quote!{$($lt2:lifetime)? $(ref)?}, &quote!{$($lt2)?}, group),
+        gen_pattern(quote!{$($lt2:lifetime)? mut}, &quote!{$($lt2)? mut}, group),
+        gen_pattern(quote!{!}, &quote!{!}, group),
+    ]).collect_vec()
+}
 
-    // Generates:
-    // impl<'t1, 't2, 't3, 't4, geometry, material, mesh, scene>
-    // CtxRef<geometry, material, mesh, scene> where
-    // 't1: 't2,
-    // 't4: 't2,
-    // 't1: 't3,
-    // 't4: 't3
-    // {
-    //     pub fn extract_geometry(&'t1 mut self)
-    //         -> (&'t2 mut GeometryCtx, &'t3 mut <Self as PartialBorrow<Ctx!['t4, mut geometry]>>::Rest)
-    //     where geometry: Acquire<&'t4 mut GeometryCtx> {
-    //         let (a, b) = <Self as PartialBorrow<Ctx! ['t4, mut geometry]>>::split_impl(self);
-    //         (a.geometry, b)
-    //     }
-    //
-    //     ...
-    //
-    // }
-    let impl_extract_fields = {
-        let fns = field_idents.iter().zip(field_types.iter()).map(|(field, ty)| {
-            let name = Ident::new(&format!("extract_{field}"), field.span());
-            quote! {
-                #[inline(always)]
-                pub fn #name(&'_t1 mut self) -> (&'_t2 mut #ty, &'_t3 mut <Self as #lib::PartialBorrow<#struct_ident!['_t4, mut #field]>>::Rest)
-                where #field: #lib::Acquire<&'_t4 mut #ty> {
-                    let (a, b) = <Self as #lib::PartialBorrow<#struct_ident!['_t4, mut #field]>>::split_impl(self);
-                    (a.#field, b)
+// Generates:
+// impl<'t1, 't2, 't3, 't4, geometry, material, mesh, scene>
+// CtxRef<geometry, material, mesh, scene> where
+// 't1: 't2,
+// 't4: 't2,
+// 't1: 't3,
+// 't4: 't3
+// {
+//     pub fn extract_geometry(&'t1 mut self) -> (
+//         &'t2 mut <geometry as RefTarget>::Target,
+//         &'t3 mut <Self as PartialBorrow<CtxRef<
+//             &'t4 mut <geometry as RefTarget>::Target,
+//             Hidden<material>,
+//             Hidden<mesh>,
+//             Hidden<scene>,
+//         >>>::Rest
+//     ) where geometry: RefTarget + Acquire<&'t4 mut <geometry as RefTarget>::Target> {
+//         let (a, b) = <Self as PartialBorrow<CtxRef<...>>>::split_impl(self);
+//         (a.geometry, b)
+//     }
+//
+//     pub fn extract_geometry_ref(&'t1 mut self) -> (
+//         &'t2 <geometry as RefTarget>::Target,
+//         &'t3 mut <Self as PartialBorrow<CtxRef<
+//             &'t4 <geometry as RefTarget>::Target,
+//             Hidden<material>,
+//             Hidden<mesh>,
+//             Hidden<scene>,
+//         >>>::Rest
+//     ) where geometry: RefTarget + Acquire<&'t4 <geometry as RefTarget>::Target> {
+//         let (a, b) = <Self as PartialBorrow<CtxRef<...>>>::split_impl(self);
+//         (a.geometry, b)
+//     }
+//
+//     pub fn extract_geometry_as<Rest>(&'t1 mut self) -> (&'t2 mut <geometry as RefTarget>::Target, &'t3 mut Rest)
+//     where
+//         geometry: RefTarget + Acquire<&'t4 mut <geometry as RefTarget>::Target>,
+//         <geometry as RefTarget>::Target: 't4,
+//         <Self as PartialBorrow<...>>::Rest: PartialBorrow<Rest>,
+//     {
+//         let (field, rest) = self.extract_geometry();
+//         (field, PartialBorrow::partial_borrow_impl(rest))
+//     }
+//
+//     pub fn with_geometry<R>(
+//         &'t1 mut self,
+//         f: impl FnOnce(&mut <geometry as RefTarget>::Target, &mut <Self as PartialBorrow<...>>::Rest) -> R
+//     ) -> R where
+//         geometry: RefTarget + Acquire<&'t4 mut <geometry as RefTarget>::Target>,
+//         <geometry as RefTarget>::Target: 't4,
+//     {
+//         let (field, rest) = self.extract_geometry();
+//         f(field, rest)
+//     }
+//
+//     pub fn for_each_in_geometry(
+//         &'t1 mut self,
+//         f: impl FnMut(<&'t2 mut <geometry as RefTarget>::Target as IntoIterator>::Item, &mut <Self as PartialBorrow<...>>::Rest)
+//     ) where
+//         geometry: RefTarget + Acquire<&'t4 mut <geometry as RefTarget>::Target>,
+//         <geometry as RefTarget>::Target: 't4,
+//         &'t2 mut <geometry as RefTarget>::Target: IntoIterator,
+//     {
+//         let (field, rest) = self.extract_geometry();
+//         for item in field {
+//             f(item, rest);
+//         }
+//     }
+//
+//     pub fn swap_geometry<Other>(&'t1 mut self, other: &mut Other) where
+//         geometry: RefTarget + Acquire<&'t4 mut <geometry as RefTarget>::Target>,
+//         <geometry as RefTarget>::Target: 't4,
+//         Other: PartialBorrow<CtxRef<...>>,
+//     {
+//         let (field, _) = self.extract_geometry();
+//         ::core::mem::swap(field, PartialBorrow::partial_borrow_impl(other).geometry);
+//     }
+//
+//     pub fn replace_geometry(&'t1 mut self, value: <geometry as RefTarget>::Target)
+//         -> <geometry as RefTarget>::Target
+//     where
+//         geometry: RefTarget + Acquire<&'t4 mut <geometry as RefTarget>::Target>,
+//         <geometry as RefTarget>::Target: 't4,
+//     {
+//         let (field, _) = self.extract_geometry();
+//         ::core::mem::replace(field, value)
+//     }
+//
+//     pub fn hide_geometry(&mut self) -> &mut CtxRef<
+//         Hidden<<geometry as RefTarget>::Target>,
+//         material,
+//         mesh,
+//         scene,
+//     > where geometry: RefTarget + VisibleField, Self: PartialBorrow<CtxRef<...>> {
+//         PartialBorrow::partial_borrow_impl(self)
+//     }
+//
+//     ...
+//
+// }
+//
+// The closure of `with_$field` methods is generic over the lifetimes of its arguments, so the
+// references can't escape it.
+//
+// The `for_each_in_$field` methods are available only for fields which can be iterated mutably,
+// like `Vec<T>`. The rest of the fields is reborrowed for every element.
+//
+// The `_ref` variants work for fields borrowed immutably as well. The extracted field stays
+// borrowed immutably in the rest of the fields.
+//
+// The `swap_$field` methods swap the field with the same field of another borrow of the struct.
+// Both borrows need to borrow the field mutably. The `replace_$field` methods replace the field
+// with the provided value and return the previous one. The `hide_$field` methods borrow the same
+// fields but the hidden one, which needs to be visible.
+//
+// For `#[borrow(optional)]` fields, the extracted field is `Option<&mut T>` or `Option<&T>`, and
+// the `for_each_in_$field`, `swap_$field`, and `replace_$field` methods are not generated.
+//
+// The field type is obtained from the borrow slot (`geometry`), so the methods do not depend on
+// the generic parameters of the original struct.
+//
+// The lifetimes are parameters of the impl, so they are inferred separately for every call. The
+// extracted field is borrowed only as long as it is used, and fields can be extracted in sequence.
+fn gen_impl_extract_fields(s: &Struct) -> pm::TokenStream {
+    let Struct { lib, ref_ident, field_idents, params, .. } = s;
+    let gen_fn = |i: usize, field: &Ident, param: &Ident, suffix: &str, mutability: pm::TokenStream| {
+        let name = Ident::new(&format!("extract_{}{suffix}", field.unraw()), field.span());
+        let ty = quote! {<#param as #lib::RefTarget>::Target};
+        let optional = s.optional_fields.get(i).copied().unwrap_or_default();
+        let slot = optional_slot(optional, quote! {&'_t4 #mutability #ty});
+        let output = optional_slot(optional, quote! {&'_t2 #mutability #ty});
+        let value = if optional {
+            quote! {#lib::RefFlatten::ref_flatten(&mut a.#field)}
+        } else {
+            quote! {a.#field}
+        };
+        let target_params = params.iter().enumerate().map(|(j, p)| {
+            if i == j { slot.clone() } else { quote! {#lib::Hidden<#p>} }
+        }).collect_vec();
+        let target = quote! {#ref_ident<#(#target_params,)*>};
+        let op = name.unraw().to_string();
+        quote! {
+            #[inline(always)]
+            #[must_use = "the returned partial borrow must be used"]
+            pub fn #name(&'_t1 mut self) -> (#output, &'_t3 mut <Self as #lib::PartialBorrow<#target>>::Rest)
+            where #param: #lib::RefTarget + #lib::Acquire<#slot> {
+                #lib::trace::record::<#target>(#op);
+                let (a, b) = <Self as #lib::PartialBorrow<#target>>::split_impl(self);
+                (#value, b)
+            }
+        }
+    };
+    let gen_with_fn = |i: usize, field: &Ident, param: &Ident| {
+        let name = Ident::new(&format!("with_{}", field.unraw()), field.span());
+        let extract_name = Ident::new(&format!("extract_{}", field.unraw()), field.span());
+        let ty = quote! {<#param as #lib::RefTarget>::Target};
+        let optional = s.optional_fields.get(i).copied().unwrap_or_default();
+        let slot = optional_slot(optional, quote! {&'_t4 mut #ty});
+        let arg = optional_slot(optional, quote! {&mut #ty});
+        let target_params = params.iter().enumerate().map(|(j, p)| {
+            if i == j { slot.clone() } else { quote! {#lib::Hidden<#p>} }
+        }).collect_vec();
+        let target = quote! {#ref_ident<#(#target_params,)*>};
+        quote! {
+            #[inline(always)]
+            pub fn #name<_R>(
+                &'_t1 mut self,
+                f: impl FnOnce(#arg, &mut <Self as #lib::PartialBorrow<#target>>::Rest) -> _R
+            ) -> _R
+            where #param: #lib::RefTarget + #lib::Acquire<#slot>, #ty: '_t4 {
+                let (field, rest) = self.#extract_name();
+                f(field, rest)
+            }
+        }
+    };
+    let gen_as_fn = |i: usize, field: &Ident, param: &Ident| {
+        let name = Ident::new(&format!("extract_{}_as", field.unraw()), field.span());
+        let extract_name = Ident::new(&format!("extract_{}", field.unraw()), field.span());
+        let ty = quote! {<#param as #lib::RefTarget>::Target};
+        let optional = s.optional_fields.get(i).copied().unwrap_or_default();
+        let slot = optional_slot(optional, quote! {&'_t4 mut #ty});
+        let output = optional_slot(optional, quote! {&'_t2 mut #ty});
+        let target_params = params.iter().enumerate().map(|(j, p)| {
+            if i == j { slot.clone() } else { quote! {#lib::Hidden<#p>} }
+        }).collect_vec();
+        let target = quote! {#ref_ident<#(#target_params,)*>};
+        let rest = quote! {<Self as #lib::PartialBorrow<#target>>::Rest};
+        quote! {
+            #[inline(always)]
+            #[must_use = "the returned partial borrow must be used"]
+            pub fn #name<_Rest>(&'_t1 mut self) -> (#output, &'_t3 mut _Rest)
+            where
+                #param: #lib::RefTarget + #lib::Acquire<#slot>,
+                #ty: '_t4,
+                #rest: #lib::PartialBorrow<_Rest> {
+                let (field, rest) = self.#extract_name();
+                (field, #lib::PartialBorrow::partial_borrow_impl(rest))
+            }
+        }
+    };
+    let gen_for_each_fn = |i: usize, field: &Ident, param: &Ident| {
+        let name = Ident::new(&format!("for_each_in_{}", field.unraw()), field.span());
+        let extract_name = Ident::new(&format!("extract_{}", field.unraw()), field.span());
+        let ty = quote! {<#param as #lib::RefTarget>::Target};
+        let target_params = params.iter().enumerate().map(|(j, p)| {
+            if i == j { quote! {&'_t4 mut #ty} } else { quote! {#lib::Hidden<#p>} }
+        }).collect_vec();
+        let target = quote! {#ref_ident<#(#target_params,)*>};
+        quote! {
+            #[inline(always)]
+            pub fn #name(
+                &'_t1 mut self,
+                mut f: impl FnMut(
+                    <&'_t2 mut #ty as ::core::iter::IntoIterator>::Item,
+                    &mut <Self as #lib::PartialBorrow<#target>>::Rest
+                )
+            )
+            where
+                #param: #lib::RefTarget + #lib::Acquire<&'_t4 mut #ty>,
+                #ty: '_t4,
+                &'_t2 mut #ty: ::core::iter::IntoIterator {
+                let (field, rest) = self.#extract_name();
+                for item in field {
+                    f(item, rest);
                 }
             }
+        }
+    };
+    let gen_swap_fn = |i: usize, field: &Ident, param: &Ident| {
+        let name = Ident::new(&format!("swap_{}", field.unraw()), field.span());
+        let extract_name = Ident::new(&format!("extract_{}", field.unraw()), field.span());
+        let ty = quote! {<#param as #lib::RefTarget>::Target};
+        let slot = quote! {&'_t4 mut #ty};
+        let target_params = params.iter().enumerate().map(|(j, p)| {
+            if i == j { slot.clone() } else { quote! {#lib::Hidden<#p>} }
         }).collect_vec();
+        let target = quote! {#ref_ident<#(#target_params,)*>};
         quote! {
-            #[allow(non_camel_case_types)]
-            impl<'_t1, '_t2, '_t3, '_t4, #(#params,)*> #ref_struct_ident<#(#params,)*> where
-            '_t1: '_t2,
-            '_t4: '_t2,
-            '_t1: '_t3,
-            '_t4: '_t3
-            {
-                #(#fns)*
+            #[inline(always)]
+            pub fn #name<_Other>(&'_t1 mut self, other: &mut _Other)
+            where
+                #param: #lib::RefTarget + #lib::Acquire<#slot>,
+                #ty: '_t4,
+                _Other: #lib::PartialBorrow<#target> {
+                let (field, _) = self.#extract_name();
+                ::core::mem::swap(field, #lib::PartialBorrow::partial_borrow_impl(other).#field);
             }
         }
     };
-
-    let out = quote! {
-        #ref_struct
-        #impl_as_refs
-        #impl_as_refs_mut
-        #ref_macro
-        #impl_extract_fields
-        #impl_into_fields
-        #impl_from_fields
-        #impl_join
+    let gen_replace_fn = |field: &Ident, param: &Ident| {
+        let name = Ident::new(&format!("replace_{}", field.unraw()), field.span());
+        let extract_name = Ident::new(&format!("extract_{}", field.unraw()), field.span());
+        let ty = quote! {<#param as #lib::RefTarget>::Target};
+        quote! {
+            #[inline(always)]
+            pub fn #name(&'_t1 mut self, value: #ty) -> #ty
+            where #param: #lib::RefTarget + #lib::Acquire<&'_t4 mut #ty>, #ty: '_t4 {
+                let (field, _) = self.#extract_name();
+                ::core::mem::replace(field, value)
+            }
+        }
+    };
+    let gen_hide_fn = |i: usize, field: &Ident, param: &Ident| {
+        let name = Ident::new(&format!("hide_{}", field.unraw()), field.span());
+        let target_params = params.iter().enumerate().map(|(j, p)| {
+            if i == j { quote! {#lib::Hidden<<#p as #lib::RefTarget>::Target>} } else { quote! {#p} }
+        }).collect_vec();
+        let target = quote! {#ref_ident<#(#target_params,)*>};
+        quote! {
+            #[inline(always)]
+            #[must_use = "the returned partial borrow must be used"]
+            pub fn #name(&mut self) -> &mut #target
+            where #param: #lib::RefTarget + #lib::VisibleField, Self: #lib::PartialBorrow<#target> {
+                #lib::PartialBorrow::partial_borrow_impl(self)
+            }
+        }
     };
+    let fns = field_idents.iter().zip(params.iter()).zip(s.optional_fields.iter()).enumerate()
+        .flat_map(|(i, ((field, param), optional))| [
+            gen_fn(i, field, param, "", quote! {mut}),
+            gen_fn(i, field, param, "_ref", quote! {}),
+            gen_with_fn(i, field, param),
+            gen_as_fn(i, field, param),
+            if *optional { quote! {} } else { gen_for_each_fn(i, field, param) },
+            if *optional { quote! {} } else { gen_swap_fn(i, field, param) },
+            if *optional { quote! {} } else { gen_replace_fn(field, param) },
+            gen_hide_fn(i, field, param),
+        ]).collect_vec();
+    quote! {
+        #[allow(non_camel_case_types)]
+        impl<'_t1, '_t2, '_t3, '_t4, #(#params,)*> #ref_ident<#(#params,)*> where
+        '_t1: '_t2,
+        '_t4: '_t2,
+        '_t1: '_t3,
+        '_t4: '_t3
+        {
+            #(#fns)*
+        }
+    }
+}
 
-    // println!(">>> {}", out);
-    TokenStream::from(out)
+
+// ===================
+// === ScopedSplit ===
+// ===================
+
+/// A field extracted by `scoped_split!`, like `mut scene` or `ref geometry`.
+struct SplitField {
+    mutable: bool,
+    ident: Ident,
+}
+
+/// Input of `scoped_split!`, like `ctx => mut scene, ref geometry, rest`.
+struct ScopedSplit {
+    ctx: syn::Expr,
+    fields: Vec<SplitField>,
+    rest: Option<Ident>,
+}
+
+impl Parse for ScopedSplit {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ctx = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let mut fields = vec![];
+        let mut rest = None;
+        while !input.is_empty() {
+            if let Some(rest) = &rest {
+                return Err(syn::Error::new_spanned(rest,
+                    "Fields need to be prefixed with 'mut' or 'ref'. A name without a prefix binds the \
+                    remaining fields and must be the last one."
+                ));
+            }
+            if input.parse::<Option<Token![mut]>>()?.is_some() {
+                fields.push(SplitField { mutable: true, ident: input.call(Ident::parse_any)? });
+            } else if input.parse::<Option<Token![ref]>>()?.is_some() {
+                fields.push(SplitField { mutable: false, ident: input.call(Ident::parse_any)? });
+            } else {
+                rest = Some(input.parse()?);
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(Self { ctx, fields, rest })
+    }
+}
+
+/// Binds disjoint borrows of fields of a partial borrow in the enclosing scope, like
+/// `scoped_split!(ctx => mut scene, ref geometry, rest)`. Fields prefixed with `mut` are borrowed
+/// mutably, fields prefixed with `ref` immutably, and an optional last name binds the borrow of the
+/// remaining fields. Borrowing a field mutably twice, or a field not available in `ctx`, fails to
+/// compile.
+///
+/// Generates:
+/// ```ignore
+/// let (scene, __rest) = (ctx).extract_scene();
+/// let (geometry, __rest) = __rest.extract_geometry_ref();
+/// let rest = __rest;
+/// ```
+#[proc_macro]
+pub fn scoped_split(input: TokenStream) -> TokenStream {
+    let ScopedSplit { ctx, fields, rest } = parse_macro_input!(input as ScopedSplit);
+    let tmp = Ident::new("__rest", Span::mixed_site());
+    let mut source = quote! {(#ctx)};
+    let bindings = fields.iter().map(|SplitField { mutable, ident }| {
+        let suffix = if *mutable { "" } else { "_ref" };
+        let method = Ident::new(&format!("extract_{}{suffix}", ident.unraw()), ident.span());
+        let out = quote! { let (#ident, #tmp) = #source.#method(); };
+        source = quote! {#tmp};
+        out
+    }).collect_vec();
+    let rest = rest.map(|rest| quote! { let #rest = #source; });
+    quote! {
+        #(#bindings)*
+        #rest
+    }.into()
 }
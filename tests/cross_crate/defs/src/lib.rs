@@ -0,0 +1,26 @@
+//! Structs deriving `PartialBorrow`, borrowed partially in the `borrow-cross-crate-user` crate.
+
+pub mod data {
+    use borrow::PartialBorrow;
+
+    #[derive(Debug, Default)]
+    pub struct Node {
+        pub outputs: Vec<usize>,
+        pub inputs: Vec<usize>,
+    }
+
+    #[derive(Debug, Default)]
+    pub struct Edge {
+        pub from: Option<usize>,
+        pub to: Option<usize>,
+    }
+
+    #[derive(Debug, Default, PartialBorrow)]
+    #[module(crate::data)]
+    #[group(topology = nodes, edges)]
+    pub struct Graph {
+        pub nodes: Vec<Node>,
+        pub edges: Vec<Edge>,
+        pub labels: Vec<String>,
+    }
+}
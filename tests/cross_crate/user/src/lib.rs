@@ -0,0 +1 @@
+//! Uses `p!` with structs defined in the `borrow-cross-crate-defs` crate.
@@ -0,0 +1,57 @@
+#![allow(dead_code)]
+
+use std::mem;
+use borrow_cross_crate_defs::data::Edge;
+use borrow_cross_crate_defs::data::Graph;
+use borrow_cross_crate_defs::data::GraphRef;
+use borrow_cross_crate_defs::data::Node;
+use partial::partial_borrow as p;
+use partial::traits::*;
+
+// =============
+// === Utils ===
+// =============
+
+fn detach_node(graph: p!(&<mut edges> Graph), node: &mut Node) {
+    for edge_id in mem::take(&mut node.outputs) {
+        graph.edges[edge_id].from = None;
+    }
+    for edge_id in mem::take(&mut node.inputs) {
+        graph.edges[edge_id].to = None;
+    }
+}
+
+fn detach_all_nodes(graph: p!(&<mut *, !labels> Graph)) {
+    let (nodes, graph2) = graph.extract_nodes();
+    for node in nodes {
+        detach_node(graph2.partial_borrow(), node);
+    }
+}
+
+fn count_edges(graph: p!(&<@topology> Graph)) -> usize {
+    graph.edges.len()
+}
+
+fn label_all(graph: p!(&<only labels, ref nodes> Graph)) {
+    let GraphRef { nodes, labels, .. } = graph;
+    labels.extend((0..nodes.len()).map(|i| i.to_string()));
+}
+
+// =============
+// === Tests ===
+// =============
+
+#[test]
+fn test_cross_crate() {
+    let mut graph = Graph {
+        nodes: vec![Node { outputs: vec![0], inputs: vec![] }, Node { outputs: vec![], inputs: vec![0] }],
+        edges: vec![Edge { from: Some(0), to: Some(1) }],
+        labels: vec![],
+    };
+    assert_eq!(count_edges(graph.as_refs_mut().partial_borrow()), 1);
+    detach_all_nodes(graph.as_refs_mut().partial_borrow());
+    label_all(graph.as_refs_mut().partial_borrow());
+    assert!(graph.edges[0].from.is_none());
+    assert!(graph.edges[0].to.is_none());
+    assert_eq!(graph.labels, vec!["0".to_string(), "1".to_string()]);
+}